@@ -0,0 +1,57 @@
+// Measures the raw instruction throughput of the CPU (run with `cargo bench`).
+// Criterion isn't a dependency, so this is a plain binary reporting the best of a few runs.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use emu_core::cpu::cpu::Cpu;
+use emu_core::memory::Mmu;
+
+const STEPS: u32 = 10_000_000;
+const RUNS: u32 = 5;
+
+// A loop mixing loads, ALU, CB-prefixed, stack and branch instructions
+#[rustfmt::skip]
+const PROGRAM: [u8; 28] = [
+    0x21, 0x00, 0xC0, // 0x0100: ld hl, $C000
+    0x01, 0x00, 0x01, // 0x0103: ld bc, $0100
+    0x7E,             // 0x0106: ld a, [hl]
+    0x81,             //         add a, c
+    0xA8,             //         xor a, b
+    0xCB, 0x37,       //         swap a
+    0xCB, 0x5F,       //         bit 3, a
+    0x22,             //         ld [hl+], a
+    0xC5,             //         push bc
+    0xCD, 0x1A, 0x01, //         call $011A
+    0xC1,             //         pop bc
+    0x0B,             //         dec bc
+    0x78,             //         ld a, b
+    0xB1,             //         or a, c
+    0x20, 0xEE,       //         jr nz, $0106
+    0x18, 0xE6,       //         jr $0100
+    0x14,             // 0x011A: inc d
+    0xC9,             //         ret
+];
+
+fn run() -> Duration {
+    let mut rom = vec![0; 0x8000];
+    rom[0x100..0x100 + PROGRAM.len()].copy_from_slice(&PROGRAM);
+    let mut cpu = Cpu::new(Mmu::new(rom));
+
+    let start = Instant::now();
+    for _ in 0..STEPS {
+        cpu.tick();
+    }
+    let elapsed = start.elapsed();
+    black_box(cpu.reg.d);
+    elapsed
+}
+
+fn main() {
+    let best = (0..RUNS).map(|_| run()).min().unwrap();
+    let ns_per_step = best.as_nanos() as f64 / STEPS as f64;
+    println!(
+        "cpu: {} steps in {:?} ({:.2} ns/step, {:.1} M steps/s)",
+        STEPS, best, ns_per_step, 1e3 / ns_per_step
+    );
+}
@@ -0,0 +1,288 @@
+use std::fs;
+use std::path::Path;
+
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{load_ram, mapped_bank, rom_byte, Cartridge, RAM_BANK_SIZE};
+use crate::error::{ImageError, StateError};
+
+/// Size of the pictures taken by the camera, in pixels
+pub const WIDTH: usize = 128;
+pub const HEIGHT: usize = 112;
+
+// Value of 0x4000 - 0x5FFF mapping the sensor registers at 0xA000 - 0xBFFF instead of RAM
+const REGISTERS_SELECTED: u8 = 0x10;
+
+// Sensor registers: A000 is the only one that can be read back
+const REG_CONTROL: usize = 0x00; // bit 0: starts a capture, and reads set until it is over
+const REG_EDGE_MODE: usize = 0x01; // bits 5 - 6: edge enhancement direction (none, horizontal, vertical, 2D)
+const REG_EXPOSURE_HIGH: usize = 0x02;
+const REG_EXPOSURE_LOW: usize = 0x03;
+const REG_EDGE_RATIO: usize = 0x04; // bits 4 - 6: edge enhancement ratio, bit 3: inverted output
+const REG_DITHER: usize = 0x06; // 4x4 matrix of 3 thresholds per pixel, row by row
+const REGISTER_COUNT: usize = 0x36;
+
+// Edge enhancement ratios selected by REG_EDGE_RATIO
+const EDGE_RATIOS: [f32; 8] = [0.5, 0.75, 1.0, 1.25, 2.0, 3.0, 4.0, 5.0];
+
+// Exposure time at which the sensor output equals the light it receives
+const NEUTRAL_EXPOSURE: u32 = 0x0800;
+
+// The picture is stored in RAM bank 0 from 0x0100, as 16x14 tiles
+const PICTURE_OFFSET: usize = 0x0100;
+
+/// Grayscale image for the camera sensor to see, 0 being black and 255 white.
+/// Images of any size are stretched to the camera's 128x112 pixels.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CameraImage {
+    width: usize,
+    height: usize,
+    pixels: Vec<u8>,
+}
+
+impl CameraImage {
+    /// Wraps a buffer of grayscale pixels, row by row.
+    pub fn from_gray(width: usize, height: usize, pixels: Vec<u8>) -> Result<Self, ImageError> {
+        if pixels.len() != width * height || pixels.is_empty() {
+            return Err(ImageError::SizeMismatch { width, height, len: pixels.len() });
+        }
+        Ok(Self { width, height, pixels })
+    }
+
+    /// Decodes a PNG image, converting colors to their luminance.
+    pub fn from_png(data: &[u8]) -> Result<Self, ImageError> {
+        let mut decoder = png::Decoder::new(data);
+        decoder.set_transformations(png::Transformations::EXPAND | png::Transformations::STRIP_16);
+        let mut reader = decoder.read_info().map_err(|err| ImageError::Png(err.to_string()))?;
+        let mut buf = vec![0; reader.output_buffer_size()];
+        let frame = reader.next_frame(&mut buf).map_err(|err| ImageError::Png(err.to_string()))?;
+
+        let channels = frame.color_type.samples();
+        let pixels = buf[..frame.buffer_size()]
+            .chunks_exact(channels)
+            .map(|pixel| match frame.color_type {
+                png::ColorType::Rgb | png::ColorType::Rgba => {
+                    ((pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000) as u8
+                },
+                // Alpha is ignored
+                _ => pixel[0],
+            })
+            .collect();
+        Self::from_gray(frame.width as usize, frame.height as usize, pixels)
+    }
+
+    /// Reads and decodes a PNG file.
+    pub fn load_png(path: impl AsRef<Path>) -> Result<Self, ImageError> {
+        let data = fs::read(path).map_err(|err| ImageError::Io(err.to_string()))?;
+        Self::from_png(&data)
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Light received by a pixel of the sensor, picking the nearest pixel of the image
+    fn sample(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.height / HEIGHT * self.width + x * self.width / WIDTH]
+    }
+}
+
+/// Game Boy Camera (Pocket Camera): up to 1MB of ROM, 128KB of RAM and a Mitsubishi M64282FP image
+/// sensor (see: https://gbdev.io/pandocs/Gameboy_Camera.html). Its registers are mapped at 0xA000 - 0xBFFF
+/// by selecting RAM bank 0x10, and captures are processed like the sensor does before being
+/// written to RAM as tiles: exposure, edge enhancement, then dithering into 4 shades through a matrix of thresholds.
+pub(crate) struct PocketCamera {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool, // 0x0000 - 0x1FFF: only 0x0A enables RAM writes, reads are always enabled
+    rom_bank: u8,      // 0x2000 - 0x3FFF: 6 bits, bank 0 can be mapped
+    ram_bank: u8,      // 0x4000 - 0x5FFF: 4 bits, or 0x10 for the sensor registers
+
+    registers: [u8; REGISTER_COUNT],
+    image: Option<CameraImage>,
+    capture_cycles: u32, // T-cycles until the capture in progress is over
+}
+
+impl PocketCamera {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Self {
+            rom,
+            ram: vec![0; 16 * RAM_BANK_SIZE],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            registers: [0; REGISTER_COUNT],
+            image: None,
+            capture_cycles: 0,
+        }
+    }
+
+    fn exposure(&self) -> u32 {
+        u16::from_be_bytes([self.registers[REG_EXPOSURE_HIGH], self.registers[REG_EXPOSURE_LOW]]) as u32
+    }
+
+    // A capture takes 32446 M-cycles, 512 more without the N bit, and 16 more per step of exposure
+    fn capture_duration(&self) -> u32 {
+        let n = self.registers[REG_EDGE_MODE] & 0x80 != 0;
+        4 * (32446 + if n { 0 } else { 512 } + 16 * self.exposure())
+    }
+
+    // Output of a pixel of the sensor after exposure, before edge enhancement.
+    // Pixels outside the picture read as their nearest neighbour
+    fn exposed(&self, x: isize, y: isize) -> f32 {
+        let Some(image) = &self.image else {
+            return 0.0;
+        };
+        let x = x.clamp(0, WIDTH as isize - 1) as usize;
+        let y = y.clamp(0, HEIGHT as isize - 1) as usize;
+        (image.sample(x, y) as u32 * self.exposure() / NEUTRAL_EXPOSURE) as f32
+    }
+
+    fn processed(&self, x: isize, y: isize) -> u8 {
+        let ratio = EDGE_RATIOS[(self.registers[REG_EDGE_RATIO] >> 4 & 0x07) as usize];
+        let center = self.exposed(x, y);
+        // Edges are enhanced by subtracting the neighbours from the pixel
+        let horizontal = 2.0 * center - self.exposed(x - 1, y) - self.exposed(x + 1, y);
+        let vertical = 2.0 * center - self.exposed(x, y - 1) - self.exposed(x, y + 1);
+        let value = match self.registers[REG_EDGE_MODE] >> 5 & 0x03 {
+            0 => center,
+            1 => center + ratio * horizontal,
+            2 => center + ratio * vertical,
+            _ => center + ratio * (horizontal + vertical),
+        };
+        let value = value.clamp(0.0, 255.0) as u8;
+        if self.registers[REG_EDGE_RATIO] & 0x08 != 0 { 255 - value } else { value }
+    }
+
+    // Takes the picture, and writes it to RAM
+    fn capture(&mut self) {
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let value = self.processed(x as isize, y as isize);
+                let dither = REG_DITHER + ((y & 3) * 4 + (x & 3)) * 3;
+                let thresholds = &self.registers[dither..dither + 3];
+                // The darker the pixel, the more thresholds it is below
+                let shade = thresholds.iter().filter(|&&threshold| value < threshold).count() as u8;
+
+                let tile = PICTURE_OFFSET + (y / 8 * (WIDTH / 8) + x / 8) * 16;
+                let row = tile + (y & 7) * 2;
+                let bit = 0x80 >> (x & 7);
+                for (plane, mask) in [(0, 0x01), (1, 0x02)] {
+                    if shade & mask != 0 {
+                        self.ram[row + plane] |= bit;
+                    } else {
+                        self.ram[row + plane] &= !bit;
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Cartridge for PocketCamera {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            rom_byte(&self.rom, 0, addr)
+        } else {
+            rom_byte(&self.rom, self.rom_bank as usize, addr)
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = val == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = val & 0x3F,
+            0x4000..=0x5FFF => {
+                self.ram_bank = if val & REGISTERS_SELECTED != 0 { REGISTERS_SELECTED } else { val & 0x0F }
+            },
+            _ => {},
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if self.ram_bank == REGISTERS_SELECTED {
+            // The registers are mirrored every 0x80 bytes, and are write-only except for A000
+            return match addr as usize & 0x7F {
+                REG_CONTROL => self.registers[REG_CONTROL] & 0x07,
+                _ => 0x00,
+            };
+        }
+        self.ram[self.ram_bank as usize * RAM_BANK_SIZE + (addr as usize & (RAM_BANK_SIZE - 1))]
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        if self.ram_bank == REGISTERS_SELECTED {
+            let reg = addr as usize & 0x7F;
+            if reg == REG_CONTROL {
+                let capturing = self.capture_cycles > 0;
+                self.registers[REG_CONTROL] = val & 0x07;
+                if val & 0x01 != 0 && !capturing {
+                    self.capture_cycles = self.capture_duration();
+                } else if val & 0x01 == 0 {
+                    // Clearing the bit aborts the capture
+                    self.capture_cycles = 0;
+                }
+            } else if reg < REGISTER_COUNT {
+                self.registers[reg] = val;
+            }
+            return;
+        }
+        if self.ram_enabled {
+            self.ram[self.ram_bank as usize * RAM_BANK_SIZE + (addr as usize & (RAM_BANK_SIZE - 1))] = val;
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 { 0 } else { mapped_bank(&self.rom, self.rom_bank as usize) as u16 }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn tick(&mut self, t_cycles: u32) {
+        if self.capture_cycles == 0 {
+            return;
+        }
+        self.capture_cycles = self.capture_cycles.saturating_sub(t_cycles);
+        if self.capture_cycles == 0 {
+            self.capture();
+            self.registers[REG_CONTROL] &= !0x01;
+        }
+    }
+
+    fn set_camera_image(&mut self, image: CameraImage) {
+        self.image = Some(image);
+    }
+
+    // The image seen by the sensor is input from the host, and isn't saved
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+        state.write_bool(self.ram_enabled);
+        state.write_u8(self.rom_bank);
+        state.write_u8(self.ram_bank);
+        state.write_bytes(&self.registers);
+        state.write_u32(self.capture_cycles);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.ram)?;
+        self.ram_enabled = state.read_bool()?;
+        self.rom_bank = state.read_u8_max(0x3F)?;
+        self.ram_bank = state.read_u8_max(REGISTERS_SELECTED)?;
+        state.read_bytes(&mut self.registers)?;
+        self.capture_cycles = state.read_u32()?;
+        Ok(())
+    }
+}
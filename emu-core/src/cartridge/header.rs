@@ -0,0 +1,263 @@
+use crate::error::HeaderError;
+
+/// Logo checked by the boot ROM, which refuses to run cartridges without it
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+/// Size of a ROM bank (16KB)
+pub const ROM_BANK_SIZE: usize = 0x4000;
+
+// The header spans 0x0100 - 0x014F
+const HEADER_END: usize = 0x0150;
+
+/// Memory bank controller, or lack thereof
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mapper {
+    RomOnly,
+    Mbc1,
+    Mbc1Multicart, // MBC1M, wired differently (only detected from the ROM contents)
+    Mbc2,
+    Mmm01,
+    Mbc3,
+    Mbc30, // MBC3 with 8 ROM and RAM bank bits (only detected from the ROM and RAM sizes)
+    Mbc5,
+    Mbc6,
+    Mbc7,
+    PocketCamera,
+    Tama5,
+    HuC3,
+    HuC1,
+    // Unlicensed mappers, which don't have a cartridge type code (only detected from the ROM contents)
+    WisdomTree,
+    SachenMmc1,
+    SachenMmc2,
+    M161,
+    LiCheng,
+}
+
+/// Hardware found on a cartridge, as advertised by the cartridge type (0x0147)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CartridgeType {
+    pub mapper: Mapper,
+    pub ram: bool,     // external RAM, sized by the header (MBC2 and MBC7 have their own instead)
+    pub battery: bool, // RAM (or RTC) contents survive power-off
+    pub timer: bool,   // real-time clock
+    pub rumble: bool,
+    pub sensor: bool,  // accelerometer
+}
+
+impl CartridgeType {
+    /// Decodes a cartridge type code (see: https://gbdev.io/pandocs/The_Cartridge_Header.html#0147--cartridge-type).
+    pub fn from_code(code: u8) -> Option<Self> {
+        use Mapper::*;
+        let (mapper, ram, battery, timer, rumble, sensor) = match code {
+            0x00 => (RomOnly, false, false, false, false, false),
+            0x01 => (Mbc1, false, false, false, false, false),
+            0x02 => (Mbc1, true, false, false, false, false),
+            0x03 => (Mbc1, true, true, false, false, false),
+            0x05 => (Mbc2, false, false, false, false, false),
+            0x06 => (Mbc2, false, true, false, false, false),
+            0x08 => (RomOnly, true, false, false, false, false),
+            0x09 => (RomOnly, true, true, false, false, false),
+            0x0B => (Mmm01, false, false, false, false, false),
+            0x0C => (Mmm01, true, false, false, false, false),
+            0x0D => (Mmm01, true, true, false, false, false),
+            0x0F => (Mbc3, false, true, true, false, false),
+            0x10 => (Mbc3, true, true, true, false, false),
+            0x11 => (Mbc3, false, false, false, false, false),
+            0x12 => (Mbc3, true, false, false, false, false),
+            0x13 => (Mbc3, true, true, false, false, false),
+            0x19 => (Mbc5, false, false, false, false, false),
+            0x1A => (Mbc5, true, false, false, false, false),
+            0x1B => (Mbc5, true, true, false, false, false),
+            0x1C => (Mbc5, false, false, false, true, false),
+            0x1D => (Mbc5, true, false, false, true, false),
+            0x1E => (Mbc5, true, true, false, true, false),
+            0x20 => (Mbc6, true, true, false, false, false),
+            0x22 => (Mbc7, false, true, false, true, true), // its RAM is an EEPROM
+            0xFC => (PocketCamera, true, true, false, false, false),
+            0xFD => (Tama5, false, true, true, false, false),
+            0xFE => (HuC3, true, true, true, false, false),
+            0xFF => (HuC1, true, true, false, false, false),
+            _ => return None,
+        };
+        Some(Self { mapper, ram, battery, timer, rumble, sensor })
+    }
+}
+
+/// Color support advertised by the cartridge (0x0143)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgbSupport {
+    Dmg,      // no CGB features, the byte is part of the title
+    Enhanced, // 0x80: runs on both DMG and CGB
+    Only,     // 0xC0: CGB only
+}
+
+/// Publisher of the game
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Licensee {
+    Old(u8),      // 0x014B
+    New([u8; 2]), // 0x0144 - 0x0145, two ASCII characters (used when 0x014B is 0x33)
+}
+
+/// Cartridge header (see: https://gbdev.io/pandocs/The_Cartridge_Header.html)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header {
+    pub logo: [u8; 48],                    // 0x0104 - 0x0133
+    pub title: String,                     // 0x0134 - 0x0143, shortened on newer cartridges
+    pub manufacturer_code: Option<String>, // 0x013F - 0x0142, on some CGB cartridges
+    pub cgb: CgbSupport,                   // 0x0143
+    pub licensee: Licensee,                // 0x0144 - 0x0145 or 0x014B
+    pub sgb: bool,                         // 0x0146
+    pub cartridge_type: u8,                // 0x0147
+    pub rom_size: u8,                      // 0x0148
+    pub ram_size: u8,                      // 0x0149
+    pub japanese: bool,                    // 0x014A, destination code
+    pub version: u8,                       // 0x014C, mask ROM version
+    pub header_checksum: u8,               // 0x014D
+    pub global_checksum: u16,              // 0x014E - 0x014F, big-endian
+
+    rom_len: usize,
+    computed_header_checksum: u8,
+    computed_global_checksum: u16,
+}
+
+impl Header {
+    /// Parses the header of a ROM image. This only fails if the ROM is too short to contain one:
+    /// see `validate` to check its contents.
+    pub fn parse(rom: &[u8]) -> Result<Self, HeaderError> {
+        if rom.len() < HEADER_END {
+            return Err(HeaderError::Truncated(rom.len()));
+        }
+
+        let cgb = match rom[0x0143] {
+            0xC0 => CgbSupport::Only,
+            flag if flag & 0x80 != 0 => CgbSupport::Enhanced,
+            _ => CgbSupport::Dmg,
+        };
+
+        let licensee = match rom[0x014B] {
+            0x33 => Licensee::New([rom[0x0144], rom[0x0145]]),
+            code => Licensee::Old(code),
+        };
+
+        // Nothing flags the manufacturer code: it is only assumed on CGB cartridges from the new licensee
+        // era, when it looks like one (4 uppercase characters), as regular titles can be that long too
+        let code = &rom[0x013F..0x0143];
+        let manufacturer_code = (cgb != CgbSupport::Dmg
+            && matches!(licensee, Licensee::New(_))
+            && code.iter().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()))
+        .then(|| String::from_utf8_lossy(code).into_owned());
+
+        let title_end = match (&manufacturer_code, cgb) {
+            (Some(_), _) => 0x013F,
+            (None, CgbSupport::Dmg) => 0x0144,
+            (None, _) => 0x0143,
+        };
+        let title = rom[0x0134..title_end]
+            .iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| if c.is_ascii_graphic() || c == b' ' { c as char } else { '?' })
+            .collect();
+
+        let computed_header_checksum = rom[0x0134..=0x014C]
+            .iter()
+            .fold(0u8, |sum, &byte| sum.wrapping_sub(byte).wrapping_sub(1));
+        let computed_global_checksum = rom
+            .iter()
+            .enumerate()
+            .filter(|&(addr, _)| addr != 0x014E && addr != 0x014F)
+            .fold(0u16, |sum, (_, &byte)| sum.wrapping_add(byte as u16));
+
+        Ok(Self {
+            logo: rom[0x0104..0x0134].try_into().unwrap(),
+            title,
+            manufacturer_code,
+            cgb,
+            licensee,
+            sgb: rom[0x0146] == 0x03 && rom[0x014B] == 0x33, // ignored by the SGB with an old licensee code
+            cartridge_type: rom[0x0147],
+            rom_size: rom[0x0148],
+            ram_size: rom[0x0149],
+            japanese: rom[0x014A] == 0x00,
+            version: rom[0x014C],
+            header_checksum: rom[0x014D],
+            global_checksum: u16::from_be_bytes([rom[0x014E], rom[0x014F]]),
+            rom_len: rom.len(),
+            computed_header_checksum,
+            computed_global_checksum,
+        })
+    }
+
+    /// Checks everything the boot ROM checks (logo and header checksum), and that the header
+    /// is consistent with itself and with the ROM size. The global checksum is not checked,
+    /// as nothing on hardware does.
+    pub fn validate(&self) -> Result<(), HeaderError> {
+        if !self.logo_valid() {
+            return Err(HeaderError::BadLogo);
+        }
+        if !self.header_checksum_valid() {
+            return Err(HeaderError::HeaderChecksum { expected: self.header_checksum, computed: self.computed_header_checksum });
+        }
+
+        let kind = self.kind().ok_or(HeaderError::UnknownCartridgeType(self.cartridge_type))?;
+        let rom_banks = self.rom_banks().ok_or(HeaderError::UnknownRomSize(self.rom_size))?;
+        let ram_bytes = self.ram_bytes().ok_or(HeaderError::UnknownRamSize(self.ram_size))?;
+
+        if rom_banks * ROM_BANK_SIZE != self.rom_len {
+            return Err(HeaderError::RomSizeMismatch { header: rom_banks * ROM_BANK_SIZE, actual: self.rom_len });
+        }
+        if kind.ram != (ram_bytes > 0) {
+            return Err(HeaderError::RamSizeMismatch { cartridge_type: self.cartridge_type, ram_size: self.ram_size });
+        }
+        Ok(())
+    }
+
+    /// Returns true if the header contains the Nintendo logo.
+    pub fn logo_valid(&self) -> bool {
+        self.logo == NINTENDO_LOGO
+    }
+
+    /// Returns true if the header checksum matches the header (0x0134 - 0x014C).
+    pub fn header_checksum_valid(&self) -> bool {
+        self.header_checksum == self.computed_header_checksum
+    }
+
+    /// Returns true if the global checksum matches the whole ROM.
+    pub fn global_checksum_valid(&self) -> bool {
+        self.global_checksum == self.computed_global_checksum
+    }
+
+    /// Decodes the cartridge type, or returns None if it is unknown.
+    pub fn kind(&self) -> Option<CartridgeType> {
+        CartridgeType::from_code(self.cartridge_type)
+    }
+
+    /// Returns the number of 16KB ROM banks, or None if the size code is unknown.
+    pub fn rom_banks(&self) -> Option<usize> {
+        match self.rom_size {
+            0x00..=0x08 => Some(2 << self.rom_size),
+            // Only listed in unofficial docs, and no known cartridge uses them
+            0x52 => Some(72),
+            0x53 => Some(80),
+            0x54 => Some(96),
+            _ => None,
+        }
+    }
+
+    /// Returns the size of the external RAM in bytes, or None if the size code is unknown.
+    pub fn ram_bytes(&self) -> Option<usize> {
+        match self.ram_size {
+            0x00 => Some(0),
+            0x01 => Some(0x800), // unused, but listed in various unofficial docs
+            0x02 => Some(0x2000),
+            0x03 => Some(0x8000),
+            0x04 => Some(0x20000),
+            0x05 => Some(0x10000),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{load_ram, mapped_bank, ram_index, rom_byte, Cartridge};
+use crate::error::StateError;
+use crate::infrared::{InfraredLink, InfraredPort};
+
+/// HuC1: up to 1MB of ROM, 32KB of RAM and an infrared LED and sensor (see: https://gbdev.io/pandocs/HuC1.html).
+/// RAM is always enabled: 0x0000 - 0x1FFF selects between RAM and the infrared port instead.
+pub(crate) struct HuC1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ir: InfraredPort,
+    ir_selected: bool, // 0x0000 - 0x1FFF: 0x0E maps the infrared port at 0xA000 - 0xBFFF, anything else RAM
+    rom_bank: u8,      // 0x2000 - 0x3FFF: 6 bits
+    ram_bank: u8,      // 0x4000 - 0x5FFF: 2 bits
+}
+
+impl HuC1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            ir: InfraredPort::new(),
+            ir_selected: false,
+            rom_bank: 1,
+            ram_bank: 0,
+        }
+    }
+}
+
+impl Cartridge for HuC1 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            rom_byte(&self.rom, 0, addr)
+        } else {
+            rom_byte(&self.rom, self.rom_bank as usize, addr)
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ir_selected = val & 0x0F == 0x0E,
+            0x2000..=0x3FFF => self.rom_bank = val & 0x3F,
+            0x4000..=0x5FFF => self.ram_bank = val & 0x03,
+            _ => {},
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if self.ir_selected {
+            // Bit 0 is set while light is received
+            return 0xC0 | self.ir.receiving() as u8;
+        }
+        ram_index(&self.ram, self.ram_bank as usize, addr).map_or(0xFF, |i| self.ram[i])
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        if self.ir_selected {
+            self.ir.set_led(val & 0x01 != 0);
+        } else if let Some(i) = ram_index(&self.ram, self.ram_bank as usize, addr) {
+            self.ram[i] = val;
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 { 0 } else { mapped_bank(&self.rom, self.rom_bank as usize) as u16 }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn ram_enabled(&self) -> bool {
+        !self.ir_selected
+    }
+
+    fn set_infrared_link(&mut self, link: Box<dyn InfraredLink>) {
+        self.ir.connect(link);
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+        state.write_bool(self.ir.led());
+        state.write_bool(self.ir_selected);
+        state.write_u8(self.rom_bank);
+        state.write_u8(self.ram_bank);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.ram)?;
+        self.ir.set_led(state.read_bool()?);
+        self.ir_selected = state.read_bool()?;
+        self.rom_bank = state.read_u8()?;
+        self.ram_bank = state.read_u8()?;
+        Ok(())
+    }
+}
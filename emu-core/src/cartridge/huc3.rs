@@ -0,0 +1,309 @@
+use std::time::Duration;
+
+use crate::cartridge::rtc::{unix_time, Elapsed, TimeSource};
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{load_ram, mapped_bank, ram_index, rom_byte, Cartridge, ToneCallback};
+use crate::error::StateError;
+use crate::infrared::{InfraredLink, InfraredPort};
+
+const MINUTES_PER_DAY: u16 = 24 * 60;
+
+// Addresses of the clock in the HuC3 memory: minutes of the day then days, in 3 nibbles each, low nibble first
+const TIME_ADDR: usize = 0x00;
+// Size of the clock footer of save files, as written by SameBoy: the time of saving as a 64-bit UNIX
+// timestamp, the minutes and days counters, then an alarm (16-bit minutes and days, and an enable byte)
+const FOOTER_SIZE: usize = 17;
+
+// Address of the nibble selecting the tone played by the speaker
+const TONE_ADDR: usize = 0x26;
+
+/// HuC3: up to 2MB of ROM, 32KB of RAM, an infrared port, a clock and a speaker (see: https://gbdev.io/pandocs/HuC3.html).
+/// The clock and speaker are driven by a microcontroller, which software talks to through a command
+/// register, a response register and a semaphore, mapped at 0xA000 - 0xBFFF.
+pub(crate) struct HuC3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ir: InfraredPort,
+    clock: Clock,
+    tone_callback: Option<ToneCallback>,
+    mode: u8,         // 0x0000 - 0x1FFF: what is mapped at 0xA000 - 0xBFFF
+    rom_bank: u8,     // 0x2000 - 0x3FFF: 7 bits
+    ram_bank: u8,     // 0x4000 - 0x5FFF: 2 bits
+    command: u8,      // command (bits 4 - 6) and its argument (bits 0 - 3), run when the semaphore is cleared
+    response: u8,     // result of the last command (4 bits)
+    address: u8,      // address in the microcontroller memory used by the read and write commands
+    memory: [u8; 256], // microcontroller memory, in nibbles
+}
+
+impl HuC3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, source: Box<dyn TimeSource>) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            ir: InfraredPort::new(),
+            clock: Clock::new(source),
+            tone_callback: None,
+            mode: 0,
+            rom_bank: 1,
+            ram_bank: 0,
+            command: 0,
+            response: 0,
+            address: 0,
+            memory: [0; 256],
+        }
+    }
+
+    fn run_command(&mut self) {
+        let arg = self.command & 0x0F;
+        match self.command >> 4 & 0x07 {
+            // Read a nibble and move to the next one
+            0x1 => {
+                self.response = self.memory[self.address as usize];
+                self.address = self.address.wrapping_add(1);
+            },
+            // Write a nibble and move to the next one
+            0x3 => {
+                self.memory[self.address as usize] = arg;
+                self.address = self.address.wrapping_add(1);
+            },
+            0x4 => self.address = (self.address & 0xF0) | arg,
+            0x5 => self.address = (self.address & 0x0F) | arg << 4,
+            0x6 => self.run_extended_command(arg),
+            _ => {},
+        }
+    }
+
+    fn run_extended_command(&mut self, arg: u8) {
+        match arg {
+            // Copy the clock to memory
+            0x0 => {
+                let (minutes, days) = self.clock.time();
+                write_nibbles(&mut self.memory[TIME_ADDR..TIME_ADDR + 3], minutes);
+                write_nibbles(&mut self.memory[TIME_ADDR + 3..TIME_ADDR + 6], days);
+            },
+            // Set the clock from memory
+            0x1 => {
+                let minutes = read_nibbles(&self.memory[TIME_ADDR..TIME_ADDR + 3]);
+                let days = read_nibbles(&self.memory[TIME_ADDR + 3..TIME_ADDR + 6]);
+                self.clock.set_time(minutes, days);
+            },
+            // Status check: games wait for this before using the clock
+            0x2 => self.response = 0x1,
+            // Ring the speaker
+            0xE => {
+                let tone = self.memory[TONE_ADDR];
+                if let Some(callback) = &mut self.tone_callback {
+                    callback(tone);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+impl Cartridge for HuC3 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            rom_byte(&self.rom, 0, addr)
+        } else {
+            rom_byte(&self.rom, self.rom_bank as usize, addr)
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.mode = val & 0x0F,
+            0x2000..=0x3FFF => self.rom_bank = val & 0x7F,
+            0x4000..=0x5FFF => self.ram_bank = val & 0x03,
+            _ => {},
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match self.mode {
+            // RAM, read-only in mode 0x0
+            0x0 | 0xA => ram_index(&self.ram, self.ram_bank as usize, addr).map_or(0xFF, |i| self.ram[i]),
+            0xC => 0x80 | (self.command & 0x70) | self.response,
+            // Commands run instantly, so the microcontroller is always ready
+            0xD => 0xFF,
+            0xE => 0xC0 | self.ir.receiving() as u8,
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        match self.mode {
+            0xA => {
+                if let Some(i) = ram_index(&self.ram, self.ram_bank as usize, addr) {
+                    self.ram[i] = val;
+                }
+            },
+            0xB => self.command = val & 0x7F,
+            // Clearing the semaphore runs the command
+            0xD if val & 0x01 == 0 => self.run_command(),
+            0xE => self.ir.set_led(val & 0x01 != 0),
+            _ => {},
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 { 0 } else { mapped_bank(&self.rom, self.rom_bank as usize) as u16 }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.mode == 0xA
+    }
+
+    fn write_save_footer(&mut self, save: &mut Vec<u8>) {
+        self.clock.write_footer(save);
+    }
+
+    fn load_save_footer(&mut self, footer: &[u8]) {
+        self.clock.read_footer(footer);
+    }
+
+    fn tick(&mut self, t_cycles: u32) {
+        self.clock.elapsed.tick(t_cycles);
+    }
+
+    fn set_time_source(&mut self, source: Box<dyn TimeSource>) {
+        self.clock.set_source(source);
+    }
+
+    fn set_tone_callback(&mut self, callback: ToneCallback) {
+        self.tone_callback = Some(callback);
+    }
+
+    fn set_infrared_link(&mut self, link: Box<dyn InfraredLink>) {
+        self.ir.connect(link);
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+        state.write_bool(self.ir.led());
+        self.clock.save_state(state);
+        state.write_u8(self.mode);
+        state.write_u8(self.rom_bank);
+        state.write_u8(self.ram_bank);
+        state.write_u8(self.command);
+        state.write_u8(self.response);
+        state.write_u8(self.address);
+        state.write_bytes(&self.memory);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.ram)?;
+        self.ir.set_led(state.read_bool()?);
+        self.clock.load_state(state)?;
+        self.mode = state.read_u8()?;
+        self.rom_bank = state.read_u8()?;
+        self.ram_bank = state.read_u8()?;
+        self.command = state.read_u8()?;
+        self.response = state.read_u8()?;
+        self.address = state.read_u8()?;
+        state.read_bytes(&mut self.memory)
+    }
+}
+
+// The HuC3 clock counts minutes of the day and days (12 bits), and can't be stopped.
+// Like the MBC3 clock, it is brought up to date from its time source when accessed
+struct Clock {
+    elapsed: Elapsed,
+    subminute: Duration, // time counted since the last increment of the minutes
+    minutes: u16,
+    days: u16,
+}
+
+impl Clock {
+    fn new(source: Box<dyn TimeSource>) -> Self {
+        Self { elapsed: Elapsed::new(source), subminute: Duration::ZERO, minutes: 0, days: 0 }
+    }
+
+    fn set_source(&mut self, source: Box<dyn TimeSource>) {
+        self.update();
+        self.elapsed.set_source(source);
+    }
+
+    fn time(&mut self) -> (u16, u16) {
+        self.update();
+        (self.minutes, self.days)
+    }
+
+    fn set_time(&mut self, minutes: u16, days: u16) {
+        self.update();
+        self.minutes = minutes;
+        self.days = days;
+        self.subminute = Duration::ZERO;
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        self.update();
+        state.write_u64(self.subminute.as_nanos() as u64);
+        state.write_u16(self.minutes);
+        state.write_u16(self.days);
+    }
+
+    // The clock goes on from the saved time, ignoring the time elapsed meanwhile
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.subminute = Duration::from_nanos(state.read_u64()?);
+        self.minutes = state.read_u16()?;
+        self.days = state.read_u16()?;
+        self.elapsed.take();
+        Ok(())
+    }
+
+    // The alarm isn't emulated, so it is saved as disabled
+    fn write_footer(&mut self, save: &mut Vec<u8>) {
+        self.update();
+        save.extend_from_slice(&unix_time().to_le_bytes());
+        save.extend_from_slice(&self.minutes.to_le_bytes());
+        save.extend_from_slice(&self.days.to_le_bytes());
+        save.extend_from_slice(&[0; 5]);
+    }
+
+    // Restores the counters, and moves them forward by the time elapsed since the save was written
+    fn read_footer(&mut self, footer: &[u8]) {
+        if footer.len() != FOOTER_SIZE {
+            return;
+        }
+        let timestamp = u64::from_le_bytes(footer[0..8].try_into().unwrap());
+        let minutes = u16::from_le_bytes([footer[8], footer[9]]);
+        let days = u16::from_le_bytes([footer[10], footer[11]]);
+        self.set_time(minutes % MINUTES_PER_DAY, days & 0xFFF);
+        self.subminute += Duration::from_secs(unix_time().saturating_sub(timestamp));
+        self.update();
+    }
+
+    fn update(&mut self) {
+        self.subminute += self.elapsed.take();
+
+        let minutes = self.subminute.as_secs() / 60;
+        self.subminute -= Duration::from_secs(minutes * 60);
+        let total = self.minutes as u64 + minutes;
+        if total >= MINUTES_PER_DAY as u64 {
+            let days = total / MINUTES_PER_DAY as u64;
+            self.days = ((self.days as u64 + days) & 0xFFF) as u16;
+            self.minutes = (total % MINUTES_PER_DAY as u64) as u16;
+        } else {
+            self.minutes = total as u16;
+        }
+    }
+}
+
+fn write_nibbles(nibbles: &mut [u8], val: u16) {
+    for (i, nibble) in nibbles.iter_mut().enumerate() {
+        *nibble = (val >> (4 * i)) as u8 & 0x0F;
+    }
+}
+
+fn read_nibbles(nibbles: &[u8]) -> u16 {
+    nibbles.iter().enumerate().fold(0, |val, (i, &nibble)| val | (nibble as u16) << (4 * i))
+}
@@ -0,0 +1,60 @@
+use crate::cartridge::mbc5::Mbc5;
+use crate::cartridge::Cartridge;
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::error::StateError;
+
+/// Li Cheng: unlicensed MBC5 clone, which ignores ROM bank writes to 0x2101 - 0x2FFF. Games rely on
+/// it, writing to these addresses to break on genuine MBC5s.
+pub(crate) struct LiCheng {
+    mbc5: Mbc5,
+}
+
+impl LiCheng {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self { mbc5: Mbc5::new(rom, ram_size, false) }
+    }
+}
+
+impl Cartridge for LiCheng {
+    fn read_rom(&self, addr: u16) -> u8 {
+        self.mbc5.read_rom(addr)
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        if !(0x2101..=0x2FFF).contains(&addr) {
+            self.mbc5.write_rom(addr, val);
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        self.mbc5.read_ram(addr)
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        self.mbc5.write_ram(addr, val);
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        self.mbc5.rom_bank(addr)
+    }
+
+    fn ram(&self) -> &[u8] {
+        self.mbc5.ram()
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        self.mbc5.load_ram(data);
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.mbc5.ram_enabled()
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        self.mbc5.save_state(state);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.mbc5.load_state(state)
+    }
+}
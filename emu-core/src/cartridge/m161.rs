@@ -0,0 +1,62 @@
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{mapped_bank, rom_byte, Cartridge};
+use crate::error::StateError;
+
+/// M161: unlicensed mapper of the Mani 4 in 1 multicarts. The menu, in the first 32KB of ROM,
+/// selects the 32KB game to map by writing to 0x4000 - 0x5FFF, which only works once until power-off.
+pub(crate) struct M161 {
+    rom: Vec<u8>,
+    bank: u8, // 32KB bank, 3 bits
+    locked: bool,
+}
+
+impl M161 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Self { rom, bank: 0, locked: false }
+    }
+
+    // 16KB bank mapped at an address
+    fn bank_at(&self, addr: u16) -> usize {
+        2 * self.bank as usize + (addr >= 0x4000) as usize
+    }
+}
+
+impl Cartridge for M161 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        rom_byte(&self.rom, self.bank_at(addr), addr)
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        if (0x4000..=0x5FFF).contains(&addr) && !self.locked {
+            self.bank = val & 0x07;
+            self.locked = true;
+        }
+    }
+
+    fn read_ram(&self, _addr: u16) -> u8 {
+        0xFF
+    }
+
+    fn write_ram(&mut self, _addr: u16, _val: u8) {}
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        mapped_bank(&self.rom, self.bank_at(addr)) as u16
+    }
+
+    fn ram(&self) -> &[u8] {
+        &[]
+    }
+
+    fn load_ram(&mut self, _data: &[u8]) {}
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_u8(self.bank);
+        state.write_bool(self.locked);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.bank = state.read_u8()?;
+        self.locked = state.read_bool()?;
+        Ok(())
+    }
+}
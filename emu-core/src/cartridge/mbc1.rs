@@ -0,0 +1,121 @@
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{load_ram, mapped_bank, ram_index, rom_byte, Cartridge};
+use crate::error::StateError;
+
+/// MBC1: up to 2MB of ROM and 32KB of RAM (see: https://gbdev.io/pandocs/MBC1.html).
+/// BANK2 provides either the upper ROM bank bits or the RAM bank: in mode 0 it only applies to
+/// the 0x4000 - 0x7FFF area, while in mode 1 it also applies to 0x0000 - 0x3FFF and to RAM.
+pub(crate) struct Mbc1 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool, // 0x0000 - 0x1FFF: RAMG, 0x0A in the low nibble enables RAM
+    bank1: u8,         // 0x2000 - 0x3FFF: 5 bits, 0 being mapped as 1
+    bank2: u8,         // 0x4000 - 0x5FFF: 2 bits
+    mode: bool,        // 0x6000 - 0x7FFF: banking mode
+
+    // MBC1M multicarts don't connect the upper bit of BANK1, so that BANK2 selects
+    // one of four 256KB games (A18 - A19 instead of A19 - A20)
+    multicart: bool,
+}
+
+impl Mbc1 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, multicart: bool) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            bank1: 1,
+            bank2: 0,
+            mode: false,
+            multicart,
+        }
+    }
+
+    // Position of BANK2 in the ROM bank number
+    fn bank2_shift(&self) -> u8 {
+        if self.multicart { 4 } else { 5 }
+    }
+
+    // The zero check is done on all 5 bits of BANK1, so banks 0x20, 0x40 and 0x60 can't
+    // be mapped at 0x4000 (0x21, 0x41 and 0x61 are instead), but only at 0x0000 in mode 1
+    fn high_bank(&self) -> usize {
+        let bank1 = if self.multicart { self.bank1 & 0x0F } else { self.bank1 };
+        ((self.bank2 << self.bank2_shift()) | bank1) as usize
+    }
+
+    fn low_bank(&self) -> usize {
+        if self.mode { (self.bank2 << self.bank2_shift()) as usize } else { 0 }
+    }
+
+    fn ram_bank(&self) -> usize {
+        if self.mode { self.bank2 as usize } else { 0 }
+    }
+}
+
+impl Cartridge for Mbc1 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            rom_byte(&self.rom, self.low_bank(), addr)
+        } else {
+            rom_byte(&self.rom, self.high_bank(), addr)
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = val & 0x0F == 0x0A,
+            0x2000..=0x3FFF => self.bank1 = if val & 0x1F == 0 { 1 } else { val & 0x1F },
+            0x4000..=0x5FFF => self.bank2 = val & 0x03,
+            _ => self.mode = val & 0x01 != 0,
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match ram_index(&self.ram, self.ram_bank(), addr) {
+            Some(i) if self.ram_enabled => self.ram[i],
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        if let Some(i) = ram_index(&self.ram, self.ram_bank(), addr)
+            && self.ram_enabled
+        {
+            self.ram[i] = val;
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        let bank = if addr < 0x4000 { self.low_bank() } else { self.high_bank() };
+        mapped_bank(&self.rom, bank) as u16
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+        state.write_bool(self.ram_enabled);
+        state.write_u8(self.bank1);
+        state.write_u8(self.bank2);
+        state.write_bool(self.mode);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.ram)?;
+        self.ram_enabled = state.read_bool()?;
+        self.bank1 = state.read_u8()?;
+        self.bank2 = state.read_u8()?;
+        self.mode = state.read_bool()?;
+        Ok(())
+    }
+}
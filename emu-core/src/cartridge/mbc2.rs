@@ -0,0 +1,89 @@
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{mapped_bank, rom_byte, Cartridge};
+use crate::error::StateError;
+
+// 512 half-byte cells, mirrored across 0xA000 - 0xBFFF
+const RAM_SIZE: usize = 0x200;
+
+/// MBC2: up to 256KB of ROM, and a built-in RAM of 512x4 bits (see: https://gbdev.io/pandocs/MBC2.html).
+/// Both registers are mapped at 0x0000 - 0x3FFF, and told apart by bit 8 of the address.
+pub(crate) struct Mbc2 {
+    rom: Vec<u8>,
+    ram: [u8; RAM_SIZE], // only the lower nibbles are stored
+    ram_enabled: bool,   // bit 8 clear: RAMG, 0x0A in the low nibble enables RAM
+    rom_bank: u8,        // bit 8 set: ROMB, 4 bits, 0 being mapped as 1
+}
+
+impl Mbc2 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Self {
+            rom,
+            ram: [0; RAM_SIZE],
+            ram_enabled: false,
+            rom_bank: 1,
+        }
+    }
+}
+
+impl Cartridge for Mbc2 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            rom_byte(&self.rom, 0, addr)
+        } else {
+            rom_byte(&self.rom, self.rom_bank as usize, addr)
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x3FFF if addr & 0x0100 == 0 => self.ram_enabled = val & 0x0F == 0x0A,
+            0x0000..=0x3FFF => self.rom_bank = if val & 0x0F == 0 { 1 } else { val & 0x0F },
+            _ => {},
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        // The upper nibble is not connected, and reads as 1s
+        self.ram[addr as usize & (RAM_SIZE - 1)] | 0xF0
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        if self.ram_enabled {
+            self.ram[addr as usize & (RAM_SIZE - 1)] = val & 0x0F;
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 { 0 } else { mapped_bank(&self.rom, self.rom_bank as usize) as u16 }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        for (cell, &val) in self.ram.iter_mut().zip(data) {
+            *cell = val & 0x0F;
+        }
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+        state.write_bool(self.ram_enabled);
+        state.write_u8(self.rom_bank);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.ram)?;
+        self.ram_enabled = state.read_bool()?;
+        self.rom_bank = state.read_u8()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,147 @@
+use crate::cartridge::rtc::{Rtc, TimeSource};
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{load_ram, mapped_bank, ram_index, rom_byte, Cartridge};
+use crate::error::StateError;
+
+/// MBC3: up to 2MB of ROM, 32KB of RAM and an optional real-time clock (see: https://gbdev.io/pandocs/MBC3.html).
+/// MBC30 is the same chip with an extra ROM and RAM bank bit, for up to 4MB of ROM and 64KB of RAM.
+pub(crate) struct Mbc3 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    rtc: Option<Rtc>,
+    ram_enabled: bool, // 0x0000 - 0x1FFF: 0x0A in the low nibble enables RAM and the clock
+    rom_bank: u8,      // 0x2000 - 0x3FFF: 7 bits (8 on MBC30), 0 being mapped as 1
+    ram_select: u8,    // 0x4000 - 0x5FFF: RAM bank (0x00 - 0x07) or clock register (0x08 - 0x0C)
+    mbc30: bool,
+}
+
+impl Mbc3 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, rtc: Option<Rtc>, mbc30: bool) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            rtc,
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_select: 0,
+            mbc30,
+        }
+    }
+}
+
+impl Cartridge for Mbc3 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            rom_byte(&self.rom, 0, addr)
+        } else {
+            rom_byte(&self.rom, self.rom_bank as usize, addr)
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = val & 0x0F == 0x0A,
+            0x2000..=0x3FFF => {
+                let bank = if self.mbc30 { val } else { val & 0x7F };
+                self.rom_bank = if bank == 0 { 1 } else { bank };
+            },
+            0x4000..=0x5FFF => self.ram_select = val,
+            _ => {
+                if let Some(rtc) = &mut self.rtc {
+                    rtc.write_latch(val);
+                }
+            },
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.ram_enabled {
+            return 0xFF;
+        }
+        match (self.ram_select, &self.rtc) {
+            (0x00..=0x07, _) => ram_index(&self.ram, self.ram_select as usize, addr).map_or(0xFF, |i| self.ram[i]),
+            (0x08..=0x0C, Some(rtc)) => rtc.read_register(self.ram_select),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        if !self.ram_enabled {
+            return;
+        }
+        match (self.ram_select, &mut self.rtc) {
+            (0x00..=0x07, _) => {
+                if let Some(i) = ram_index(&self.ram, self.ram_select as usize, addr) {
+                    self.ram[i] = val;
+                }
+            },
+            (0x08..=0x0C, Some(rtc)) => rtc.write_register(self.ram_select, val),
+            _ => {},
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 { 0 } else { mapped_bank(&self.rom, self.rom_bank as usize) as u16 }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn tick(&mut self, t_cycles: u32) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.tick(t_cycles);
+        }
+    }
+
+    fn rtc(&mut self) -> Option<&mut Rtc> {
+        self.rtc.as_mut()
+    }
+
+    fn write_save_footer(&mut self, save: &mut Vec<u8>) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.write_footer(save);
+        }
+    }
+
+    fn load_save_footer(&mut self, footer: &[u8]) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.read_footer(footer);
+        }
+    }
+
+    fn set_time_source(&mut self, source: Box<dyn TimeSource>) {
+        if let Some(rtc) = &mut self.rtc {
+            rtc.set_source(source);
+        }
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+        state.write_bool(self.ram_enabled);
+        state.write_u8(self.rom_bank);
+        state.write_u8(self.ram_select);
+        if let Some(rtc) = &mut self.rtc {
+            rtc.save_state(state);
+        }
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.ram)?;
+        self.ram_enabled = state.read_bool()?;
+        self.rom_bank = state.read_u8()?;
+        self.ram_select = state.read_u8()?;
+        if let Some(rtc) = &mut self.rtc {
+            rtc.load_state(state)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,121 @@
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{load_ram, mapped_bank, ram_index, rom_byte, Cartridge, RumbleCallback};
+use crate::error::StateError;
+
+/// MBC5: up to 8MB of ROM and 128KB of RAM (see: https://gbdev.io/pandocs/MBC5.html).
+/// Unlike older MBCs, bank 0 can be mapped at 0x4000 - 0x7FFF.
+pub(crate) struct Mbc5 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    ram_enabled: bool, // 0x0000 - 0x1FFF: RAMG, only 0x0A enables RAM
+    rom_bank: u16,     // 0x2000 - 0x2FFF: lower 8 bits, 0x3000 - 0x3FFF: bit 8
+    ram_bank: u8,      // 0x4000 - 0x5FFF: 4 bits, of which bit 3 drives the motor on rumble cartridges
+
+    rumble: bool,
+    motor_on: bool,
+    on_rumble: Option<RumbleCallback>,
+}
+
+impl Mbc5 {
+    pub fn new(rom: Vec<u8>, ram_size: usize, rumble: bool) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            ram_enabled: false,
+            rom_bank: 1,
+            ram_bank: 0,
+            rumble,
+            motor_on: false,
+            on_rumble: None,
+        }
+    }
+
+    fn ram_bank(&self) -> usize {
+        let mask = if self.rumble { 0x07 } else { 0x0F };
+        (self.ram_bank & mask) as usize
+    }
+
+    fn update_motor(&mut self) {
+        let motor_on = self.rumble && self.ram_bank & 0x08 != 0;
+        if motor_on != self.motor_on {
+            self.motor_on = motor_on;
+            if let Some(on_rumble) = &mut self.on_rumble {
+                on_rumble(motor_on);
+            }
+        }
+    }
+}
+
+impl Cartridge for Mbc5 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            rom_byte(&self.rom, 0, addr)
+        } else {
+            rom_byte(&self.rom, self.rom_bank as usize, addr)
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled = val == 0x0A,
+            0x2000..=0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | val as u16,
+            0x3000..=0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | ((val as u16 & 0x01) << 8),
+            0x4000..=0x5FFF => {
+                self.ram_bank = val & 0x0F;
+                self.update_motor();
+            },
+            _ => {},
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match ram_index(&self.ram, self.ram_bank(), addr) {
+            Some(i) if self.ram_enabled => self.ram[i],
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        if let Some(i) = ram_index(&self.ram, self.ram_bank(), addr)
+            && self.ram_enabled
+        {
+            self.ram[i] = val;
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 { 0 } else { mapped_bank(&self.rom, self.rom_bank as usize) as u16 }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn set_rumble_callback(&mut self, callback: RumbleCallback) {
+        self.on_rumble = Some(callback);
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+        state.write_bool(self.ram_enabled);
+        state.write_u16(self.rom_bank);
+        state.write_u8(self.ram_bank);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.ram)?;
+        self.ram_enabled = state.read_bool()?;
+        self.rom_bank = state.read_u16()?;
+        self.ram_bank = state.read_u8()?;
+        self.update_motor();
+        Ok(())
+    }
+}
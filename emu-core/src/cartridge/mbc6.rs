@@ -0,0 +1,252 @@
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{load_ram, Cartridge};
+use crate::error::StateError;
+
+// MBC6 banks are half the usual size: 8KB of ROM or flash, 4KB of RAM
+const ROM_BANK_SIZE: usize = 0x2000;
+const RAM_BANK_SIZE: usize = 0x1000;
+
+const FLASH_SIZE: usize = 0x100000;
+// Erasable sectors (the last one is subdivided on the chip, which is not emulated)
+const FLASH_SECTOR_SIZE: usize = 0x10000;
+// Manufacturer (Macronix) and device (MX29F008TC) IDs
+const FLASH_ID: [u8; 2] = [0xC2, 0x81];
+
+/// MBC6: up to 1MB of ROM, 32KB of RAM and 1MB of flash memory (see: https://gbdev.io/pandocs/MBC6.html).
+/// 0x4000 - 0x5FFF and 0x6000 - 0x7FFF are two independent windows on ROM or flash, and
+/// 0xA000 - 0xAFFF and 0xB000 - 0xBFFF two independent windows on RAM. Both RAM and flash are saved.
+pub(crate) struct Mbc6 {
+    rom: Vec<u8>,
+    save: Vec<u8>, // RAM followed by flash, as stored in save files
+    ram_size: usize,
+    flash: Flash,
+
+    ram_enabled: bool,        // 0x0000 - 0x03FF: only 0x0A enables RAM
+    ram_banks: [u8; 2],       // 0x0400 - 0x07FF and 0x0800 - 0x0BFF: 3 bits
+    flash_enabled: bool,      // 0x0C00 - 0x0FFF bit 0, only writable while flash writes are enabled
+    flash_writable: bool,     // 0x1000 - 0x1FFF bit 0: flash commands are accepted
+    rom_banks: [u8; 2],       // 0x2000 - 0x27FF and 0x3000 - 0x37FF: 7 bits
+    flash_selected: [bool; 2], // 0x2800 - 0x2FFF and 0x3800 - 0x3FFF: 0x08 maps flash instead of ROM
+
+    flash_written: bool, // flash was programmed or erased since `take_save_written`
+}
+
+impl Mbc6 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        let mut save = vec![0; ram_size];
+        // Flash memory is erased to 0xFF
+        save.resize(ram_size + FLASH_SIZE, 0xFF);
+        Self {
+            rom,
+            save,
+            ram_size,
+            flash: Flash::ReadArray,
+            ram_enabled: false,
+            ram_banks: [0; 2],
+            flash_enabled: false,
+            flash_writable: false,
+            rom_banks: [0; 2],
+            flash_selected: [false; 2],
+            flash_written: false,
+        }
+    }
+
+    fn flash(&mut self) -> &mut [u8] {
+        &mut self.save[self.ram_size..]
+    }
+
+    // Offset in the flash memory of an address in a window
+    fn flash_offset(&self, window: usize, addr: u16) -> usize {
+        (self.rom_banks[window] as usize * ROM_BANK_SIZE + (addr as usize & (ROM_BANK_SIZE - 1))) % FLASH_SIZE
+    }
+
+    // Index in `save` of an address in a RAM window
+    fn ram_index(&self, addr: u16) -> Option<usize> {
+        if self.ram_size == 0 {
+            return None;
+        }
+        let bank = self.ram_banks[(addr as usize >> 12) & 1] as usize;
+        Some((bank * RAM_BANK_SIZE + (addr as usize & (RAM_BANK_SIZE - 1))) % self.ram_size)
+    }
+
+    fn write_flash(&mut self, offset: usize, val: u8) {
+        // Reset is accepted at any time
+        if val == 0xF0 {
+            self.flash = Flash::ReadArray;
+            return;
+        }
+        self.flash = match (self.flash, offset, val) {
+            (Flash::ReadArray | Flash::Id, 0x5555, 0xAA) => Flash::Unlocked1 { erase: false },
+            (Flash::EraseSetup, 0x5555, 0xAA) => Flash::Unlocked1 { erase: true },
+            (Flash::Unlocked1 { erase }, 0x2AAA, 0x55) => Flash::Unlocked2 { erase },
+            (Flash::Unlocked2 { erase: false }, 0x5555, 0x90) => Flash::Id,
+            (Flash::Unlocked2 { erase: false }, 0x5555, 0xA0) => Flash::Program,
+            (Flash::Unlocked2 { erase: false }, 0x5555, 0x80) => Flash::EraseSetup,
+            (Flash::Unlocked2 { erase: true }, _, 0x30) => {
+                let sector = offset & !(FLASH_SECTOR_SIZE - 1);
+                self.flash()[sector..sector + FLASH_SECTOR_SIZE].fill(0xFF);
+                self.flash_written = true;
+                Flash::ReadArray
+            },
+            (Flash::Unlocked2 { erase: true }, 0x5555, 0x10) => {
+                self.flash().fill(0xFF);
+                self.flash_written = true;
+                Flash::ReadArray
+            },
+            // Programming can only clear bits, erasing sets them back
+            (Flash::Program, _, _) => {
+                self.flash()[offset] &= val;
+                self.flash_written = true;
+                Flash::ReadArray
+            },
+            (Flash::Id, _, _) => Flash::Id,
+            _ => Flash::ReadArray,
+        };
+    }
+}
+
+impl Cartridge for Mbc6 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            return self.rom.get(addr as usize).copied().unwrap_or(0xFF);
+        }
+        let window = (addr as usize >> 13) & 1;
+        if self.flash_selected[window] {
+            let offset = self.flash_offset(window, addr);
+            return match self.flash {
+                Flash::Id => FLASH_ID[offset & 1],
+                _ => self.save[self.ram_size + offset],
+            };
+        }
+        if self.rom.is_empty() {
+            return 0xFF;
+        }
+        let offset = self.rom_banks[window] as usize * ROM_BANK_SIZE + (addr as usize & (ROM_BANK_SIZE - 1));
+        self.rom[offset % self.rom.len()]
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x03FF => self.ram_enabled = val == 0x0A,
+            0x0400..=0x07FF => self.ram_banks[0] = val & 0x07,
+            0x0800..=0x0BFF => self.ram_banks[1] = val & 0x07,
+            0x0C00..=0x0FFF => {
+                if self.flash_writable {
+                    self.flash_enabled = val & 0x01 != 0;
+                }
+            },
+            0x1000..=0x1FFF => self.flash_writable = val & 0x01 != 0,
+            0x2000..=0x27FF => self.rom_banks[0] = val & 0x7F,
+            0x2800..=0x2FFF => self.flash_selected[0] = val == 0x08,
+            0x3000..=0x37FF => self.rom_banks[1] = val & 0x7F,
+            0x3800..=0x3FFF => self.flash_selected[1] = val == 0x08,
+            _ => {
+                let window = (addr as usize >> 13) & 1;
+                if self.flash_selected[window] && self.flash_enabled && self.flash_writable {
+                    let offset = self.flash_offset(window, addr);
+                    self.write_flash(offset, val);
+                }
+            },
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match self.ram_index(addr) {
+            Some(i) if self.ram_enabled => self.save[i],
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        if let Some(i) = self.ram_index(addr)
+            && self.ram_enabled
+        {
+            self.save[i] = val;
+        }
+    }
+
+    // Numbers of the 8KB banks, as MBC6 software counts them
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 { addr / ROM_BANK_SIZE as u16 } else { self.rom_banks[(addr as usize >> 13) & 1] as u16 }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.save
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        load_ram(&mut self.save, data);
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn take_save_written(&mut self) -> bool {
+        std::mem::take(&mut self.flash_written)
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.save);
+        state.write_u8(self.flash.to_u8());
+        state.write_bool(self.ram_enabled);
+        state.write_u8(self.ram_banks[0]);
+        state.write_u8(self.ram_banks[1]);
+        state.write_bool(self.flash_enabled);
+        state.write_bool(self.flash_writable);
+        state.write_u8(self.rom_banks[0]);
+        state.write_u8(self.rom_banks[1]);
+        state.write_bool(self.flash_selected[0]);
+        state.write_bool(self.flash_selected[1]);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.save)?;
+        self.flash = Flash::from_u8(state.read_u8()?);
+        self.ram_enabled = state.read_bool()?;
+        self.ram_banks = [state.read_u8()?, state.read_u8()?];
+        self.flash_enabled = state.read_bool()?;
+        self.flash_writable = state.read_bool()?;
+        self.rom_banks = [state.read_u8()?, state.read_u8()?];
+        self.flash_selected = [state.read_bool()?, state.read_bool()?];
+        Ok(())
+    }
+}
+
+// Command state of the flash chip, which takes commands as unlock sequences written to
+// 0x5555 and 0x2AAA (addresses in the flash memory) followed by a command byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Flash {
+    ReadArray,
+    Unlocked1 { erase: bool }, // 0xAA was written, after an erase setup command or not
+    Unlocked2 { erase: bool }, // then 0x55: a command comes next
+    Id,                        // reads return the chip IDs
+    Program,                   // the next write programs a byte
+    EraseSetup,                // an unlock sequence and the erase command come next
+}
+
+impl Flash {
+    fn to_u8(self) -> u8 {
+        match self {
+            Flash::ReadArray => 0,
+            Flash::Unlocked1 { erase } => 1 | (erase as u8) << 4,
+            Flash::Unlocked2 { erase } => 2 | (erase as u8) << 4,
+            Flash::Id => 3,
+            Flash::Program => 4,
+            Flash::EraseSetup => 5,
+        }
+    }
+
+    // Unknown states reset the chip, as a reset command would
+    fn from_u8(val: u8) -> Self {
+        let erase = val & 0x10 != 0;
+        match val & 0x0F {
+            1 => Flash::Unlocked1 { erase },
+            2 => Flash::Unlocked2 { erase },
+            3 => Flash::Id,
+            4 => Flash::Program,
+            5 => Flash::EraseSetup,
+            _ => Flash::ReadArray,
+        }
+    }
+}
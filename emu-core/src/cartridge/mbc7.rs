@@ -0,0 +1,318 @@
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{load_ram, mapped_bank, rom_byte, Cartridge};
+use crate::error::StateError;
+
+// Accelerometer reading when flat, and change per g of acceleration
+const ACCEL_CENTER: f32 = 0x81D0 as f32;
+const ACCEL_PER_G: f32 = 0x70 as f32;
+
+// Accelerometer registers after being erased
+const ACCEL_ERASED: u16 = 0x8000;
+
+// 93LC56 EEPROM, organized as 128 16-bit words
+const EEPROM_WORDS: usize = 128;
+
+/// MBC7: up to 2MB of ROM, a two-axis accelerometer and a 256 bytes serial EEPROM
+/// (see: https://gbdev.io/pandocs/MBC7.html). Both are mapped at 0xA000 - 0xAFFF once enabled
+/// by two registers, each register being selected by bits 4-7 of the address.
+pub(crate) struct Mbc7 {
+    rom: Vec<u8>,
+    rom_bank: u8,          // 0x2000 - 0x3FFF: 7 bits
+    ram_enabled: [bool; 2], // 0x0000 - 0x1FFF: 0x0A enables, and 0x4000 - 0x5FFF: 0x40 enables
+
+    tilt: (u16, u16),    // current accelerometer readings, set by the host
+    latched: (u16, u16), // readings visible to software
+    latch_ready: bool,   // the latched values were erased, and can be latched again
+
+    eeprom: Eeprom,
+}
+
+impl Mbc7 {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Self {
+            rom,
+            rom_bank: 1,
+            ram_enabled: [false; 2],
+            tilt: (ACCEL_CENTER as u16, ACCEL_CENTER as u16),
+            latched: (ACCEL_ERASED, ACCEL_ERASED),
+            latch_ready: false,
+            eeprom: Eeprom::new(),
+        }
+    }
+
+    fn registers_enabled(&self, addr: u16) -> bool {
+        self.ram_enabled == [true; 2] && addr < 0xB000
+    }
+}
+
+impl Cartridge for Mbc7 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            rom_byte(&self.rom, 0, addr)
+        } else {
+            rom_byte(&self.rom, self.rom_bank as usize, addr)
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1FFF => self.ram_enabled[0] = val == 0x0A,
+            0x2000..=0x3FFF => self.rom_bank = val & 0x7F,
+            0x4000..=0x5FFF => self.ram_enabled[1] = val == 0x40,
+            _ => {},
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        if !self.registers_enabled(addr) {
+            return 0xFF;
+        }
+        match (addr >> 4) & 0x0F {
+            0x2 => self.latched.0 as u8,
+            0x3 => (self.latched.0 >> 8) as u8,
+            0x4 => self.latched.1 as u8,
+            0x5 => (self.latched.1 >> 8) as u8,
+            0x6 => 0x00, // no Z axis
+            0x8 => self.eeprom.read(),
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        if !self.registers_enabled(addr) {
+            return;
+        }
+        match (addr >> 4) & 0x0F {
+            0x0 if val == 0x55 => {
+                self.latched = (ACCEL_ERASED, ACCEL_ERASED);
+                self.latch_ready = true;
+            },
+            0x1 if val == 0xAA && self.latch_ready => {
+                self.latched = self.tilt;
+                self.latch_ready = false;
+            },
+            0x8 => self.eeprom.write(val),
+            _ => {},
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 { 0 } else { mapped_bank(&self.rom, self.rom_bank as usize) as u16 }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.eeprom.data
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        load_ram(&mut self.eeprom.data, data);
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled == [true; 2]
+    }
+
+    fn set_tilt(&mut self, x: f32, y: f32) {
+        let reading = |g: f32| (ACCEL_CENTER + g * ACCEL_PER_G).clamp(0.0, u16::MAX as f32) as u16;
+        self.tilt = (reading(x), reading(y));
+    }
+
+    // The accelerometer readings are input from the host, only the latched ones are saved
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_u8(self.rom_bank);
+        state.write_bool(self.ram_enabled[0]);
+        state.write_bool(self.ram_enabled[1]);
+        state.write_u16(self.latched.0);
+        state.write_u16(self.latched.1);
+        state.write_bool(self.latch_ready);
+        self.eeprom.save_state(state);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.rom_bank = state.read_u8()?;
+        self.ram_enabled = [state.read_bool()?, state.read_bool()?];
+        self.latched = (state.read_u16()?, state.read_u16()?);
+        self.latch_ready = state.read_bool()?;
+        self.eeprom.load_state(state)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EepromState {
+    Idle,                                       // waiting for a start bit
+    Command { bits: u16, count: u8 },           // receiving the opcode and address (10 bits)
+    Reading { word: u16, count: u8 },           // shifting a word out on DO
+    Writing { addr: Option<u8>, bits: u16, count: u8 }, // receiving a word, for one address or all of them
+}
+
+/// 93LC56 serial EEPROM in 16-bit mode, driven by software through its CS, CLK, DI and DO lines
+/// (bits 7, 6, 1 and 0 of the register). Bits are shifted in and out on rising CLK edges, MSB first.
+struct Eeprom {
+    data: [u8; EEPROM_WORDS * 2], // little-endian words, as stored in save files
+    cs: bool,
+    clk: bool,
+    di: bool,
+    do_: bool,
+    write_enabled: bool, // EWEN/EWDS, programming is disabled at power-on
+    state: EepromState,
+}
+
+impl Eeprom {
+    fn new() -> Self {
+        Self {
+            data: [0xFF; EEPROM_WORDS * 2],
+            cs: false,
+            clk: false,
+            di: false,
+            do_: true,
+            write_enabled: false,
+            state: EepromState::Idle,
+        }
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_bytes(&self.data);
+        state.write_bool(self.cs);
+        state.write_bool(self.clk);
+        state.write_bool(self.di);
+        state.write_bool(self.do_);
+        state.write_bool(self.write_enabled);
+        // Command state, as a tag followed by its shift register, bit count and address (0xFF for all of them)
+        let (tag, bits, count, addr) = match self.state {
+            EepromState::Idle => (0, 0, 0, 0),
+            EepromState::Command { bits, count } => (1, bits, count, 0),
+            EepromState::Reading { word, count } => (2, word, count, 0),
+            EepromState::Writing { addr, bits, count } => (3, bits, count, addr.unwrap_or(0xFF)),
+        };
+        state.write_u8(tag);
+        state.write_u16(bits);
+        state.write_u8(count);
+        state.write_u8(addr);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.data)?;
+        self.cs = state.read_bool()?;
+        self.clk = state.read_bool()?;
+        self.di = state.read_bool()?;
+        self.do_ = state.read_bool()?;
+        self.write_enabled = state.read_bool()?;
+        let (tag, bits, count, addr) = (state.read_u8()?, state.read_u16()?, state.read_u8()?, state.read_u8()?);
+        // Addresses and bit counts are checked against what `clock_bit` can reach
+        let addr = match addr {
+            0xFF => None,
+            addr if (addr as usize) < EEPROM_WORDS => Some(addr),
+            addr => return Err(StateError::InvalidValue(addr)),
+        };
+        self.state = match (tag, count) {
+            (0, _) => EepromState::Idle,
+            (1, 0..=9) => EepromState::Command { bits, count },
+            (2, 1..=16) => EepromState::Reading { word: bits, count },
+            (3, 0..=15) => EepromState::Writing { addr, bits, count },
+            (0..=3, count) => return Err(StateError::InvalidValue(count)),
+            (tag, _) => return Err(StateError::InvalidValue(tag)),
+        };
+        Ok(())
+    }
+
+    fn read(&self) -> u8 {
+        (self.cs as u8) << 7 | (self.clk as u8) << 6 | (self.di as u8) << 1 | self.do_ as u8
+    }
+
+    fn write(&mut self, val: u8) {
+        let cs = val & 0x80 != 0;
+        let clk = val & 0x40 != 0;
+        let rising_edge = clk && !self.clk;
+        self.cs = cs;
+        self.clk = clk;
+        self.di = val & 0x02 != 0;
+
+        if !cs {
+            // Deselecting the chip aborts any command
+            self.state = EepromState::Idle;
+            self.do_ = true;
+            return;
+        }
+        if rising_edge {
+            self.clock_bit();
+        }
+    }
+
+    fn clock_bit(&mut self) {
+        let di = self.di as u16;
+        self.state = match self.state {
+            EepromState::Idle if self.di => EepromState::Command { bits: 0, count: 0 },
+            EepromState::Idle => EepromState::Idle, // leading zeros are ignored
+            EepromState::Command { bits, count: 9 } => self.run_command((bits << 1) | di),
+            EepromState::Command { bits, count } => EepromState::Command { bits: (bits << 1) | di, count: count + 1 },
+            EepromState::Reading { word, count } => {
+                self.do_ = word & 0x8000 != 0;
+                match count {
+                    1 => EepromState::Idle,
+                    _ => EepromState::Reading { word: word << 1, count: count - 1 },
+                }
+            },
+            EepromState::Writing { addr, bits, count: 15 } => {
+                self.program(addr, (bits << 1) | di);
+                EepromState::Idle
+            },
+            EepromState::Writing { addr, bits, count } => {
+                EepromState::Writing { addr, bits: (bits << 1) | di, count: count + 1 }
+            },
+        };
+    }
+
+    // Runs a command (2-bit opcode and 8-bit address), and returns the state to go on with
+    fn run_command(&mut self, command: u16) -> EepromState {
+        let addr = (command & 0x7F) as u8;
+        match (command >> 8, (command >> 6) & 0x03) {
+            // READ: a dummy 0 bit, then the word
+            (0b10, _) => {
+                self.do_ = false;
+                EepromState::Reading { word: self.word(addr), count: 16 }
+            },
+            (0b01, _) => EepromState::Writing { addr: Some(addr), bits: 0, count: 0 }, // WRITE
+            (0b11, _) => {
+                self.program(Some(addr), 0xFFFF); // ERASE
+                EepromState::Idle
+            },
+            (_, 0b11) => {
+                self.write_enabled = true; // EWEN
+                EepromState::Idle
+            },
+            (_, 0b00) => {
+                self.write_enabled = false; // EWDS
+                EepromState::Idle
+            },
+            (_, 0b10) => {
+                self.program(None, 0xFFFF); // ERAL
+                EepromState::Idle
+            },
+            _ => EepromState::Writing { addr: None, bits: 0, count: 0 }, // WRAL
+        }
+    }
+
+    fn word(&self, addr: u8) -> u16 {
+        let i = addr as usize * 2;
+        u16::from_le_bytes([self.data[i], self.data[i + 1]])
+    }
+
+    // Writes a word to an address, or to all of them. Programming is instant, so DO is always ready (high)
+    fn program(&mut self, addr: Option<u8>, word: u16) {
+        self.do_ = true;
+        if !self.write_enabled {
+            return;
+        }
+        match addr {
+            Some(addr) => {
+                let i = addr as usize * 2;
+                self.data[i..i + 2].copy_from_slice(&word.to_le_bytes());
+            },
+            None => {
+                for chunk in self.data.chunks_exact_mut(2) {
+                    chunk.copy_from_slice(&word.to_le_bytes());
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{load_ram, mapped_bank, ram_index, rom_byte, Cartridge};
+use crate::error::StateError;
+
+/// MMM01: mapper of multicarts (see: https://gbdev.io/pandocs/MMM01.html). At power-on the menu, found
+/// in the last 32KB of ROM, is mapped. It selects the part of the ROM and RAM a game occupies, then locks
+/// the mapping: the game sees an MBC1 restricted to its part, until the next power cycle.
+pub(crate) struct Mmm01 {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    locked: bool,      // 0x0000 - 0x1FFF bit 6: the game is mapped, and the menu settings are read-only
+    ram_enabled: bool, // 0x0000 - 0x1FFF: 0x0A in the low nibble enables RAM
+    ram_mask: u8,      // 0x0000 - 0x1FFF bits 4 - 5: RAM bank bits the game can't change
+    rom_bank: u16,     // 0x2000 - 0x3FFF bits 0 - 6, 0x4000 - 0x5FFF bits 4 - 5: 9 bits
+    ram_bank: u8,      // 0x4000 - 0x5FFF bits 0 - 3
+    rom_mask: u8,      // 0x6000 - 0x7FFF bits 2 - 5: ROM bank bits 1 - 4 the game can't change
+    mode: bool,        // 0x6000 - 0x7FFF bit 0: MBC1 banking mode, which only applies to RAM
+}
+
+impl Mmm01 {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self {
+            rom,
+            ram: vec![0; ram_size],
+            locked: false,
+            ram_enabled: false,
+            ram_mask: 0,
+            rom_bank: 0,
+            ram_bank: 0,
+            rom_mask: 0,
+            mode: false,
+        }
+    }
+
+    // Like on MBC1, the zero check only looks at the lower 5 bits
+    fn high_bank(&self) -> usize {
+        let low = if self.rom_bank & 0x1F == 0 { 1 } else { self.rom_bank & 0x1F };
+        if self.locked {
+            ((self.rom_bank & !0x1F) | low) as usize
+        } else {
+            // Until the mapping is locked, all bank bits but the lowest are set: the last 32KB are mapped
+            0x1FE | (low & 0x01) as usize
+        }
+    }
+
+    // The first bank of the game, made of the bits set by the menu
+    fn low_bank(&self) -> usize {
+        if self.locked { (self.rom_bank & (0x1E0 | (self.rom_mask as u16) << 1)) as usize } else { 0x1FE }
+    }
+
+    fn ram_bank(&self) -> usize {
+        let mask = if self.mode { 0x0F } else { 0x0C | self.ram_mask };
+        (self.ram_bank & mask) as usize
+    }
+}
+
+impl Cartridge for Mmm01 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            rom_byte(&self.rom, self.low_bank(), addr)
+        } else {
+            rom_byte(&self.rom, self.high_bank(), addr)
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1FFF => {
+                self.ram_enabled = val & 0x0F == 0x0A;
+                if !self.locked {
+                    self.ram_mask = val >> 4 & 0x03;
+                    self.locked = val & 0x40 != 0;
+                }
+            },
+            0x2000..=0x3FFF => {
+                let writable = if self.locked { 0x1F & !(self.rom_mask << 1) } else { 0x7F };
+                self.rom_bank = (self.rom_bank & !(writable as u16)) | (val & writable) as u16;
+            },
+            0x4000..=0x5FFF => {
+                let writable = if self.locked { 0x03 & !self.ram_mask } else { 0x0F };
+                self.ram_bank = (self.ram_bank & !writable) | (val & writable);
+                if !self.locked {
+                    self.rom_bank = (self.rom_bank & 0x7F) | (val as u16 >> 4 & 0x03) << 7;
+                }
+            },
+            _ => {
+                self.mode = val & 0x01 != 0;
+                if !self.locked {
+                    self.rom_mask = val >> 2 & 0x0F;
+                }
+            },
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        match ram_index(&self.ram, self.ram_bank(), addr) {
+            Some(i) if self.ram_enabled => self.ram[i],
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        if let Some(i) = ram_index(&self.ram, self.ram_bank(), addr)
+            && self.ram_enabled
+        {
+            self.ram[i] = val;
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        let bank = if addr < 0x4000 { self.low_bank() } else { self.high_bank() };
+        mapped_bank(&self.rom, bank) as u16
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.ram_enabled
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+        state.write_bool(self.locked);
+        state.write_bool(self.ram_enabled);
+        state.write_u8(self.ram_mask);
+        state.write_u16(self.rom_bank);
+        state.write_u8(self.ram_bank);
+        state.write_u8(self.rom_mask);
+        state.write_bool(self.mode);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.ram)?;
+        self.locked = state.read_bool()?;
+        self.ram_enabled = state.read_bool()?;
+        self.ram_mask = state.read_u8()?;
+        self.rom_bank = state.read_u16()?;
+        self.ram_bank = state.read_u8()?;
+        self.rom_mask = state.read_u8()?;
+        self.mode = state.read_bool()?;
+        Ok(())
+    }
+}
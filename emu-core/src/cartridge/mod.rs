@@ -0,0 +1,346 @@
+pub mod camera;
+pub mod header;
+mod huc1;
+mod huc3;
+mod li_cheng;
+mod m161;
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod mbc6;
+mod mbc7;
+mod mmm01;
+mod rom_only;
+pub mod rtc;
+mod sachen;
+pub mod state;
+mod tama5;
+mod wisdom_tree;
+
+pub use header::{CartridgeType, CgbSupport, Header, Licensee, Mapper};
+
+use camera::{CameraImage, PocketCamera};
+use header::ROM_BANK_SIZE;
+use huc1::HuC1;
+use huc3::HuC3;
+use li_cheng::LiCheng;
+use m161::M161;
+use mbc1::Mbc1;
+use mbc2::Mbc2;
+use mbc3::Mbc3;
+use mbc5::Mbc5;
+use mbc6::Mbc6;
+use mbc7::Mbc7;
+use mmm01::Mmm01;
+use rom_only::RomOnly;
+use rtc::{HostClock, Rtc, TimeSource};
+use sachen::Sachen;
+use state::{StateReader, StateWriter};
+use tama5::Tama5;
+use wisdom_tree::WisdomTree;
+
+use crate::error::StateError;
+use crate::infrared::InfraredLink;
+
+/// Size of an external RAM bank (8KB)
+pub const RAM_BANK_SIZE: usize = 0x2000;
+
+/// Called with the new state of the rumble motor (true when running) whenever it changes
+pub type RumbleCallback = Box<dyn FnMut(bool)>;
+
+/// Called with the contents of a save file whenever the game is done writing to battery-backed RAM
+pub type SaveCallback = Box<dyn FnMut(&[u8])>;
+
+/// Called with the number of the tone to play whenever software rings the cartridge speaker
+pub type ToneCallback = Box<dyn FnMut(u8)>;
+
+// Largest ROM addressable without banking, and by an MBC1 or MBC3
+const ROM_ONLY_MAX: usize = 2 * ROM_BANK_SIZE;
+const MBC1_MAX: usize = 128 * ROM_BANK_SIZE;
+const MBC3_MAX: usize = 128 * ROM_BANK_SIZE;
+
+// RAM size code of the 64KB RAM found on MBC30 cartridges
+const RAM_SIZE_64KB: u8 = 0x05;
+
+// MBC1 multicarts are 1MB, with a game (and its own header) every 16 banks
+const MBC1M_SIZE: usize = 64 * ROM_BANK_SIZE;
+
+// MMM01 multicarts boot the menu found in their last 32KB
+const MMM01_MENU_SIZE: usize = 2 * ROM_BANK_SIZE;
+
+// Cartridge type of the Mani 4 in 1 multicarts, which have an M161 but claim an MBC3 with a clock
+const M161_TYPE: u8 = 0x10;
+const M161_SIZE: usize = 16 * ROM_BANK_SIZE;
+
+/// A cartridge and its memory bank controller: maps the ROM (0x0000 - 0x7FFF) and external RAM
+/// (0xA000 - 0xBFFF) into the address space. Its registers are written through the ROM area.
+///
+/// The built-in mappers are created by `Mmu::new`; other cartridges can implement this trait and be
+/// plugged in with `Mmu::with_cartridge`.
+pub trait Cartridge {
+    /// Reads the ROM area (0x0000 - 0x7FFF).
+    fn read_rom(&self, addr: u16) -> u8;
+
+    /// Writes to the ROM area, which sets the MBC registers.
+    fn write_rom(&mut self, addr: u16, val: u8);
+
+    /// Reads external RAM (0xA000 - 0xBFFF). Reads 0xFF while disabled or absent.
+    fn read_ram(&self, addr: u16) -> u8;
+
+    fn write_ram(&mut self, addr: u16, val: u8);
+
+    /// Returns the ROM bank currently mapped at a ROM address.
+    fn rom_bank(&self, addr: u16) -> u16;
+
+    /// Returns the whole contents of the RAM, as stored in save files.
+    fn ram(&self) -> &[u8];
+
+    /// Restores the RAM from a save file. Extra bytes are ignored, and missing ones are left untouched.
+    fn load_ram(&mut self, data: &[u8]);
+
+    /// Returns true while the RAM can be written, as set by the RAM enable register. Games disable RAM
+    /// when they are done saving, which is when `Mmu` autosaves. Always true for cartridges without one.
+    fn ram_enabled(&self) -> bool {
+        true
+    }
+
+    /// Returns true if battery-backed memory was changed by writes other than to enabled RAM, like flash
+    /// programmed through the ROM area, since the last call. `Mmu` then marks the save as dirty.
+    fn take_save_written(&mut self) -> bool {
+        false
+    }
+
+    /// Appends what follows the RAM in save files, like the state of the clock. Nothing for most cartridges.
+    fn write_save_footer(&mut self, _save: &mut Vec<u8>) {}
+
+    /// Restores the state stored after the RAM in a save file, by `write_save_footer` or other emulators.
+    fn load_save_footer(&mut self, _footer: &[u8]) {}
+
+    /// Writes the state of the cartridge (registers, RAM and clocks, but not the ROM) for a save state.
+    fn save_state(&mut self, state: &mut StateWriter);
+
+    /// Restores a state written by `save_state`.
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError>;
+
+    /// Called as emulated time elapses, in T-cycles of the normal speed clock, for cartridges with a clock.
+    fn tick(&mut self, _t_cycles: u32) {}
+
+    /// Returns the real-time clock of the cartridge, if it has one.
+    fn rtc(&mut self) -> Option<&mut Rtc> {
+        None
+    }
+
+    /// Replaces the time source of the cartridge clock. Ignored by cartridges without one.
+    fn set_time_source(&mut self, _source: Box<dyn TimeSource>) {}
+
+    /// Sets the callback driving the rumble motor. Ignored by cartridges without one.
+    fn set_rumble_callback(&mut self, _callback: RumbleCallback) {}
+
+    /// Sets the acceleration measured by the accelerometer, in g. Ignored by cartridges without one.
+    fn set_tilt(&mut self, _x: f32, _y: f32) {}
+
+    /// Replaces the image seen by the camera sensor. Ignored by cartridges without one.
+    fn set_camera_image(&mut self, _image: CameraImage) {}
+
+    /// Sets the callback playing the tones of the cartridge speaker. Ignored by cartridges without one.
+    fn set_tone_callback(&mut self, _callback: ToneCallback) {}
+
+    /// Connects the other end of the cartridge infrared port. Ignored by cartridges without one.
+    fn set_infrared_link(&mut self, _link: Box<dyn InfraredLink>) {}
+}
+
+/// Creates the MBC of a cartridge.
+pub(crate) fn create(rom: Vec<u8>, kind: CartridgeType) -> Box<dyn Cartridge> {
+    match kind.mapper {
+        Mapper::RomOnly => {
+            let ram_size = ram_size(&rom, kind, RAM_BANK_SIZE);
+            Box::new(RomOnly::new(rom, ram_size))
+        },
+        Mapper::Mbc1 | Mapper::Mbc1Multicart => {
+            let ram_size = ram_size(&rom, kind, 4 * RAM_BANK_SIZE);
+            Box::new(Mbc1::new(rom, ram_size, kind.mapper == Mapper::Mbc1Multicart))
+        },
+        Mapper::Mbc2 => Box::new(Mbc2::new(rom)),
+        Mapper::Mmm01 => {
+            // The header describing the cartridge is the menu's
+            let menu = rom.len().saturating_sub(MMM01_MENU_SIZE);
+            let ram_size = ram_size(&rom[menu..], kind, 16 * RAM_BANK_SIZE);
+            Box::new(Mmm01::new(rom, ram_size))
+        },
+        Mapper::Mbc3 | Mapper::Mbc30 => {
+            let mbc30 = kind.mapper == Mapper::Mbc30;
+            let ram_size = ram_size(&rom, kind, if mbc30 { 8 } else { 4 } * RAM_BANK_SIZE);
+            let rtc = kind.timer.then(|| Rtc::new(Box::new(HostClock::new())));
+            Box::new(Mbc3::new(rom, ram_size, rtc, mbc30))
+        },
+        Mapper::Mbc5 => {
+            let ram_size = ram_size(&rom, kind, 16 * RAM_BANK_SIZE);
+            Box::new(Mbc5::new(rom, ram_size, kind.rumble))
+        },
+        Mapper::Mbc6 => {
+            let ram_size = ram_size(&rom, kind, 4 * RAM_BANK_SIZE);
+            Box::new(Mbc6::new(rom, ram_size))
+        },
+        Mapper::Mbc7 => Box::new(Mbc7::new(rom)),
+        Mapper::PocketCamera => Box::new(PocketCamera::new(rom)),
+        Mapper::Tama5 => Box::new(Tama5::new(rom, Box::new(HostClock::new()))),
+        Mapper::HuC1 => {
+            let ram_size = ram_size(&rom, kind, 4 * RAM_BANK_SIZE);
+            Box::new(HuC1::new(rom, ram_size))
+        },
+        Mapper::HuC3 => {
+            let ram_size = ram_size(&rom, kind, 4 * RAM_BANK_SIZE);
+            Box::new(HuC3::new(rom, ram_size, Box::new(HostClock::new())))
+        },
+        Mapper::WisdomTree => Box::new(WisdomTree::new(rom)),
+        Mapper::SachenMmc1 | Mapper::SachenMmc2 => Box::new(Sachen::new(rom)),
+        Mapper::M161 => Box::new(M161::new(rom)),
+        Mapper::LiCheng => {
+            let ram_size = ram_size(&rom, kind, 16 * RAM_BANK_SIZE);
+            Box::new(LiCheng::new(rom, ram_size))
+        },
+    }
+}
+
+/// Picks the hardware to emulate for a ROM image. The header is trusted when it is consistent
+/// with the ROM, but homebrew ROMs often have wrong ones (or none at all), in which case the
+/// mapper is guessed from the ROM size, with RAM and a battery to be on the safe side.
+pub fn detect_type(rom: &[u8]) -> CartridgeType {
+    let guessed = |mapper| CartridgeType {
+        mapper,
+        ram: true,
+        battery: true,
+        timer: false,
+        rumble: false,
+        sensor: false,
+    };
+    // MBC5 is the only mapper addressing 8MB, and MBC1 the most common one below 2MB
+    let banked = if rom.len() > MBC1_MAX { Mapper::Mbc5 } else { Mapper::Mbc1 };
+
+    if let Some(kind) = mmm01_kind(rom) {
+        return kind;
+    }
+
+    let header = Header::parse(rom).ok();
+    if let Some(mapper) = header.as_ref().and_then(|header| unlicensed_mapper(rom, header)) {
+        let kind = header.as_ref().and_then(|header| header.kind());
+        return match kind {
+            // Li Cheng cartridges are MBC5 clones, and describe their RAM like them
+            Some(kind) if mapper == Mapper::LiCheng => CartridgeType { mapper, ..kind },
+            _ => CartridgeType { mapper, ram: false, battery: false, timer: false, rumble: false, sensor: false },
+        };
+    }
+
+    let kind = header.as_ref().and_then(|header| header.kind());
+    match kind {
+        Some(kind) if kind.mapper == Mapper::RomOnly && rom.len() > ROM_ONLY_MAX => guessed(banked),
+        Some(kind) if kind.mapper == Mapper::Mbc1 && rom.len() > MBC1_MAX => CartridgeType { mapper: Mapper::Mbc5, ..kind },
+        Some(kind) if kind.mapper == Mapper::Mbc1 && is_mbc1_multicart(rom) => {
+            CartridgeType { mapper: Mapper::Mbc1Multicart, ..kind }
+        },
+        Some(kind) if kind.mapper == Mapper::Mbc3 && is_mbc30(rom, header.as_ref()) => {
+            CartridgeType { mapper: Mapper::Mbc30, ..kind }
+        },
+        Some(kind) => kind,
+        None if rom.len() <= ROM_ONLY_MAX => guessed(Mapper::RomOnly),
+        None => guessed(banked),
+    }
+}
+
+// Multicarts are told apart by the header of their second game, found in bank 0x10
+// (like the menu's in bank 0x00): regular cartridges have no reason to contain a logo there
+fn is_mbc1_multicart(rom: &[u8]) -> bool {
+    let logo = 0x10 * ROM_BANK_SIZE + 0x0104;
+    rom.len() == MBC1M_SIZE && rom[logo..logo + header::NINTENDO_LOGO.len()] == header::NINTENDO_LOGO
+}
+
+// MMM01 multicarts are told apart by the header of their menu, which is in the last 32KB:
+// the one at the start of the ROM belongs to the first game
+fn mmm01_kind(rom: &[u8]) -> Option<CartridgeType> {
+    let menu = rom.len().checked_sub(MMM01_MENU_SIZE).filter(|&menu| menu > 0)?;
+    let kind = Header::parse(&rom[menu..]).ok()?.kind()?;
+    (kind.mapper == Mapper::Mmm01).then_some(kind)
+}
+
+// Unlicensed cartridges have no cartridge type of their own: they reuse a licensed one,
+// and are told apart by what sets them apart from licensed cartridges
+fn unlicensed_mapper(rom: &[u8], header: &Header) -> Option<Mapper> {
+    if !header.logo_valid() {
+        // Sachen cartridges hide the Nintendo logo from the game, as it is only visible to the boot ROM
+        if is_sachen(rom) {
+            let cgb = header.cgb != CgbSupport::Dmg;
+            return Some(if cgb { Mapper::SachenMmc2 } else { Mapper::SachenMmc1 });
+        }
+        // Li Cheng cartridges get past the logo check some other way, and no licensed MBC5 cartridge lacks it
+        if header.kind().is_some_and(|kind| kind.mapper == Mapper::Mbc5) {
+            return Some(Mapper::LiCheng);
+        }
+    }
+    if header.cartridge_type == 0x00 && rom.len() > ROM_ONLY_MAX && is_wisdom_tree(rom) {
+        return Some(Mapper::WisdomTree);
+    }
+    if header.cartridge_type == M161_TYPE && rom.len() == M161_SIZE && header.title.starts_with("TETRIS SET") {
+        return Some(Mapper::M161);
+    }
+    None
+}
+
+// The Nintendo logo is where the boot ROM reads it while the cartridge is locked, scrambled or not
+fn is_sachen(rom: &[u8]) -> bool {
+    [false, true].into_iter().any(|scrambled| {
+        header::NINTENDO_LOGO.iter().enumerate().all(|(i, &byte)| {
+            let addr = sachen::locked_address(0x0104 + i as u16, scrambled) as usize;
+            rom.get(addr) == Some(&byte)
+        })
+    })
+}
+
+// Wisdom Tree games claim to be 32KB ROM-only cartridges, and credit the publisher
+fn is_wisdom_tree(rom: &[u8]) -> bool {
+    rom.windows(11).any(|text| text == b"WISDOM TREE" || text == b"WISDOM\0TREE")
+}
+
+// MBC30 shares the cartridge types of MBC3, but addresses more ROM or RAM than it can
+fn is_mbc30(rom: &[u8], header: Option<&Header>) -> bool {
+    rom.len() > MBC3_MAX || header.is_some_and(|header| header.ram_size == RAM_SIZE_64KB)
+}
+
+// Size of the external RAM: as advertised by the header, or the largest the MBC supports
+// if the cartridge type has RAM but the header doesn't tell its size
+fn ram_size(rom: &[u8], kind: CartridgeType, max: usize) -> usize {
+    if !kind.ram {
+        return 0;
+    }
+    match Header::parse(rom).ok().and_then(|header| header.ram_bytes()) {
+        Some(size) if size > 0 => size.min(max),
+        _ => max,
+    }
+}
+
+// Reads a byte of a ROM bank. Banks past the end of the ROM wrap around, as the upper bank
+// bits are not connected to the ROM chip (all ROM sizes are powers of two)
+fn rom_byte(rom: &[u8], bank: usize, addr: u16) -> u8 {
+    let offset = mapped_bank(rom, bank) * ROM_BANK_SIZE + (addr as usize & (ROM_BANK_SIZE - 1));
+    rom.get(offset).copied().unwrap_or(0xFF)
+}
+
+// ROM bank actually selected by a bank number
+fn mapped_bank(rom: &[u8], bank: usize) -> usize {
+    bank % rom.len().div_ceil(ROM_BANK_SIZE).max(1)
+}
+
+// Index of a byte of an external RAM bank, wrapping around like ROM banks, or None without RAM.
+// RAM smaller than a bank (2KB) is mirrored across it
+fn ram_index(ram: &[u8], bank: usize, addr: u16) -> Option<usize> {
+    if ram.is_empty() {
+        return None;
+    }
+    Some((bank * RAM_BANK_SIZE + (addr as usize & (RAM_BANK_SIZE - 1))) % ram.len())
+}
+
+// Shared implementation of `Cartridge::load_ram`, for RAM stored as is
+fn load_ram(ram: &mut [u8], data: &[u8]) {
+    let len = ram.len().min(data.len());
+    ram[..len].copy_from_slice(&data[..len]);
+}
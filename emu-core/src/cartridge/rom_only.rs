@@ -0,0 +1,53 @@
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{load_ram, ram_index, rom_byte, Cartridge};
+use crate::error::StateError;
+
+/// Cartridge without an MBC: 32KB of ROM, and optionally up to 8KB of RAM
+pub(crate) struct RomOnly {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+}
+
+impl RomOnly {
+    pub fn new(rom: Vec<u8>, ram_size: usize) -> Self {
+        Self { rom, ram: vec![0; ram_size] }
+    }
+}
+
+impl Cartridge for RomOnly {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 { rom_byte(&self.rom, 0, addr) } else { rom_byte(&self.rom, 1, addr) }
+    }
+
+    fn write_rom(&mut self, _addr: u16, _val: u8) {}
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        ram_index(&self.ram, 0, addr).map_or(0xFF, |i| self.ram[i])
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        if let Some(i) = ram_index(&self.ram, 0, addr) {
+            self.ram[i] = val;
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 { 0 } else { 1 }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.ram)
+    }
+}
@@ -0,0 +1,375 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::error::StateError;
+
+// Frequency of the clock driving the CPU at normal speed, and of cartridge clocks
+const CPU_CLOCK_HZ: u128 = 4_194_304;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+/// Size of the clock footer appended to MBC3 save files by VBA-M, BGB and SameBoy: the counters and
+/// latched counters as 32-bit registers, then the time of saving as a 64-bit UNIX timestamp.
+/// Older saves have a 32-bit timestamp instead.
+pub const RTC_FOOTER_SIZE: usize = 48;
+pub const RTC_FOOTER_SIZE_32: usize = 44;
+
+/// Where real-time clocks get the time from
+pub trait TimeSource {
+    /// Returns the time elapsed since an arbitrary epoch. It must never go backwards.
+    fn now(&self) -> Duration;
+
+    /// Called as emulated time elapses, in T-cycles of the normal speed clock (4.194304 MHz),
+    /// whatever the CPU speed. Sources following the host clock ignore it.
+    fn tick(&mut self, _t_cycles: u32) {}
+}
+
+/// Follows the host clock, so that the time keeps going while the emulator is paused or closed,
+/// like on a real cartridge. This is the default for cartridges with a clock.
+pub struct HostClock {
+    start: Instant,
+}
+
+impl HostClock {
+    pub fn new() -> Self {
+        Self { start: Instant::now() }
+    }
+}
+
+impl Default for HostClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for HostClock {
+    fn now(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+/// Follows emulated time only, so that runs are reproducible (tests, movies, netplay...)
+pub struct CycleClock {
+    t_cycles: u64,
+}
+
+impl CycleClock {
+    pub fn new() -> Self {
+        Self { t_cycles: 0 }
+    }
+}
+
+impl Default for CycleClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for CycleClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos((self.t_cycles as u128 * 1_000_000_000 / CPU_CLOCK_HZ) as u64)
+    }
+
+    fn tick(&mut self, t_cycles: u32) {
+        self.t_cycles += t_cycles as u64;
+    }
+}
+
+// Measures the time elapsed on a time source between updates of a clock
+pub(crate) struct Elapsed {
+    source: Box<dyn TimeSource>,
+    last_update: Duration, // time of the source when the clock was last brought up to date
+}
+
+impl Elapsed {
+    pub fn new(source: Box<dyn TimeSource>) -> Self {
+        Self { last_update: source.now(), source }
+    }
+
+    // Returns the time elapsed since the previous call
+    pub fn take(&mut self) -> Duration {
+        let now = self.source.now();
+        let elapsed = now.saturating_sub(self.last_update);
+        self.last_update = now;
+        elapsed
+    }
+
+    // Replaces the source: the time elapsed on the previous one must have been taken
+    pub fn set_source(&mut self, source: Box<dyn TimeSource>) {
+        self.last_update = source.now();
+        self.source = source;
+    }
+
+    pub fn tick(&mut self, t_cycles: u32) {
+        self.source.tick(t_cycles);
+    }
+}
+
+/// Contents of the clock counters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RtcTime {
+    pub seconds: u8,    // 6 bits
+    pub minutes: u8,    // 6 bits
+    pub hours: u8,      // 5 bits
+    pub days: u16,      // 9 bits
+    pub halted: bool,   // the clock is stopped
+    pub day_carry: bool, // the day counter overflowed, until cleared by software
+}
+
+impl RtcTime {
+    /// Advances the counters by a number of seconds.
+    pub fn advance(&mut self, mut seconds: u64) {
+        // Out of range values (which can be written) count up to their bit width and wrap around to 0
+        // without carrying into the next counter, so the counters are stepped until they are back in range
+        while seconds > 0 && !self.in_range() {
+            self.tick_second();
+            seconds -= 1;
+        }
+        if seconds == 0 {
+            return;
+        }
+
+        let total = self.seconds as u64
+            + 60 * self.minutes as u64
+            + 3600 * self.hours as u64
+            + SECONDS_PER_DAY * self.days as u64
+            + seconds;
+        let days = total / SECONDS_PER_DAY;
+        if days >= 512 {
+            self.day_carry = true;
+        }
+        self.days = (days % 512) as u16;
+        self.hours = (total % SECONDS_PER_DAY / 3600) as u8;
+        self.minutes = (total % 3600 / 60) as u8;
+        self.seconds = (total % 60) as u8;
+    }
+
+    /// Returns the value of the clock registers, from 0x08 (seconds) to 0x0C (upper day bit and flags).
+    pub fn registers(&self) -> [u8; 5] {
+        [
+            self.seconds,
+            self.minutes,
+            self.hours,
+            self.days as u8,
+            (self.days >> 8) as u8 | (self.halted as u8) << 6 | (self.day_carry as u8) << 7,
+        ]
+    }
+
+    /// Builds the counters from the value of the clock registers, as returned by `registers`.
+    pub fn from_registers(registers: [u8; 5]) -> Self {
+        Self {
+            seconds: registers[0] & 0x3F,
+            minutes: registers[1] & 0x3F,
+            hours: registers[2] & 0x1F,
+            days: registers[3] as u16 | (registers[4] as u16 & 0x01) << 8,
+            halted: registers[4] & 0x40 != 0,
+            day_carry: registers[4] & 0x80 != 0,
+        }
+    }
+
+    fn save_state(&self, state: &mut StateWriter) {
+        state.write_u8(self.seconds);
+        state.write_u8(self.minutes);
+        state.write_u8(self.hours);
+        state.write_u16(self.days);
+        state.write_bool(self.halted);
+        state.write_bool(self.day_carry);
+    }
+
+    fn load_state(state: &mut StateReader) -> Result<Self, StateError> {
+        Ok(Self {
+            seconds: state.read_u8()?,
+            minutes: state.read_u8()?,
+            hours: state.read_u8()?,
+            days: state.read_u16()?,
+            halted: state.read_bool()?,
+            day_carry: state.read_bool()?,
+        })
+    }
+
+    fn in_range(&self) -> bool {
+        self.seconds < 60 && self.minutes < 60 && self.hours < 24
+    }
+
+    fn tick_second(&mut self) {
+        self.seconds = (self.seconds + 1) & 0x3F;
+        if self.seconds != 60 {
+            return;
+        }
+        self.seconds = 0;
+        self.minutes = (self.minutes + 1) & 0x3F;
+        if self.minutes != 60 {
+            return;
+        }
+        self.minutes = 0;
+        self.hours = (self.hours + 1) & 0x1F;
+        if self.hours != 24 {
+            return;
+        }
+        self.hours = 0;
+        self.days += 1;
+        if self.days == 512 {
+            self.days = 0;
+            self.day_carry = true;
+        }
+    }
+}
+
+/// MBC3 real-time clock (see: https://gbdev.io/pandocs/MBC3.html#the-clock-counter-registers).
+/// The counters are brought up to date from the time source whenever they are accessed.
+/// Software reads a copy of them, latched by writing 0x00 then 0x01 to 0x6000 - 0x7FFF.
+pub struct Rtc {
+    elapsed: Elapsed,
+    subsecond: Duration,   // time counted since the last increment of the seconds
+    live: RtcTime,
+    latched: RtcTime,
+    latch_armed: bool, // 0x00 was written to the latch register
+}
+
+impl Rtc {
+    pub fn new(source: Box<dyn TimeSource>) -> Self {
+        Self {
+            elapsed: Elapsed::new(source),
+            subsecond: Duration::ZERO,
+            live: RtcTime::default(),
+            latched: RtcTime::default(),
+            latch_armed: false,
+        }
+    }
+
+    /// Replaces the time source. The counters keep their values, and go on from there.
+    pub fn set_source(&mut self, source: Box<dyn TimeSource>) {
+        self.update();
+        self.elapsed.set_source(source);
+    }
+
+    /// Returns the current value of the counters (not the latched ones).
+    pub fn time(&mut self) -> RtcTime {
+        self.update();
+        self.live
+    }
+
+    /// Sets the counters, restarting the current second.
+    pub fn set_time(&mut self, time: RtcTime) {
+        self.update();
+        self.live = time;
+        self.subsecond = Duration::ZERO;
+    }
+
+    /// Moves the clock forward, like when the game is left off for a while. Ignored while halted.
+    pub fn advance(&mut self, duration: Duration) {
+        self.update();
+        if !self.live.halted {
+            self.count(duration);
+        }
+    }
+
+    /// Returns the latched counters, as read by software.
+    pub fn latched(&self) -> RtcTime {
+        self.latched
+    }
+
+    pub(crate) fn tick(&mut self, t_cycles: u32) {
+        self.elapsed.tick(t_cycles);
+    }
+
+    /// Writes the latch register: a 0x00 then 0x01 sequence copies the counters to the latched registers.
+    pub(crate) fn write_latch(&mut self, val: u8) {
+        if self.latch_armed && val == 0x01 {
+            self.update();
+            self.latched = self.live;
+        }
+        self.latch_armed = val == 0x00;
+    }
+
+    /// Reads a latched register, selected by 0x08 (seconds) to 0x0C (upper day bit and flags).
+    pub(crate) fn read_register(&self, reg: u8) -> u8 {
+        match reg {
+            0x08..=0x0C => self.latched.registers()[reg as usize - 0x08],
+            _ => 0xFF,
+        }
+    }
+
+    /// Writes a counter, selected like in `read_register`.
+    pub(crate) fn write_register(&mut self, reg: u8, val: u8) {
+        self.update();
+        let time = &mut self.live;
+        match reg {
+            0x08 => {
+                time.seconds = val & 0x3F;
+                self.subsecond = Duration::ZERO;
+            },
+            0x09 => time.minutes = val & 0x3F,
+            0x0A => time.hours = val & 0x1F,
+            0x0B => time.days = (time.days & 0x100) | val as u16,
+            0x0C => {
+                time.days = (time.days & 0xFF) | ((val as u16 & 0x01) << 8);
+                time.halted = val & 0x40 != 0;
+                time.day_carry = val & 0x80 != 0;
+            },
+            _ => {},
+        }
+    }
+
+    pub(crate) fn save_state(&mut self, state: &mut StateWriter) {
+        self.update();
+        state.write_u64(self.subsecond.as_nanos() as u64);
+        self.live.save_state(state);
+        self.latched.save_state(state);
+        state.write_bool(self.latch_armed);
+    }
+
+    /// Restores the counters saved by `save_state`. They go on from there, ignoring the time elapsed meanwhile.
+    pub(crate) fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.subsecond = Duration::from_nanos(state.read_u64()?);
+        self.live = RtcTime::load_state(state)?;
+        self.latched = RtcTime::load_state(state)?;
+        self.latch_armed = state.read_bool()?;
+        self.elapsed.take();
+        Ok(())
+    }
+
+    /// Appends the clock footer of save files (see `RTC_FOOTER_SIZE`).
+    pub(crate) fn write_footer(&mut self, save: &mut Vec<u8>) {
+        self.update();
+        for reg in self.live.registers().into_iter().chain(self.latched.registers()) {
+            save.extend_from_slice(&(reg as u32).to_le_bytes());
+        }
+        save.extend_from_slice(&unix_time().to_le_bytes());
+    }
+
+    /// Restores the clock from a save file footer, and moves it forward by the time elapsed since the
+    /// save was written. Footers of an unknown size are ignored.
+    pub(crate) fn read_footer(&mut self, footer: &[u8]) {
+        let timestamp = match footer.len() {
+            RTC_FOOTER_SIZE => u64::from_le_bytes(footer[40..48].try_into().unwrap()),
+            RTC_FOOTER_SIZE_32 => u32::from_le_bytes(footer[40..44].try_into().unwrap()) as u64,
+            _ => return,
+        };
+        // Registers are stored in the low byte of 32-bit values
+        let registers = |first: usize| std::array::from_fn(|i| footer[(first + i) * 4]);
+        self.set_time(RtcTime::from_registers(registers(0)));
+        self.latched = RtcTime::from_registers(registers(5));
+        self.advance(Duration::from_secs(unix_time().saturating_sub(timestamp)));
+    }
+
+    // Counts the time elapsed on the source since the last update
+    fn update(&mut self) {
+        let elapsed = self.elapsed.take();
+        if !self.live.halted {
+            self.count(elapsed);
+        }
+    }
+
+    fn count(&mut self, elapsed: Duration) {
+        self.subsecond += elapsed;
+        let seconds = self.subsecond.as_secs();
+        self.subsecond -= Duration::from_secs(seconds);
+        self.live.advance(seconds);
+    }
+}
+
+/// Current host time, as stored in save file footers
+pub(crate) fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |time| time.as_secs())
+}
@@ -0,0 +1,96 @@
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{mapped_bank, rom_byte, Cartridge};
+use crate::error::StateError;
+
+/// Sachen MMC1 and MMC2: unlicensed mappers of Sachen games and multicarts.
+/// The 0x4000 - 0x7FFF bank is made of the base bank, for the bits set in the mask, and of the
+/// ROM bank for the others. The base bank and mask can only be changed while bits 4 and 5 of the
+/// base bank are set, which lets multicart menus lock them.
+///
+/// Until unlocked, both chips remap the header so that the boot ROM finds the Nintendo logo at 0x0184
+/// (see `locked_address`) while the game displays its own at 0x0104. The boot ROM isn't run, so
+/// cartridges start unlocked, the way it leaves them.
+pub(crate) struct Sachen {
+    rom: Vec<u8>,
+    base_bank: u8, // 0x0000 - 0x1FFF
+    rom_bank: u8,  // 0x2000 - 0x3FFF: 0 being mapped as 1
+    mask: u8,      // 0x4000 - 0x5FFF: bits taken from the base bank
+}
+
+impl Sachen {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Self { rom, base_bank: 0xFF, rom_bank: 1, mask: 0x00 }
+    }
+
+    fn unlocked(&self) -> bool {
+        self.base_bank & 0x30 == 0x30
+    }
+
+    fn low_bank(&self) -> usize {
+        (self.base_bank & self.mask) as usize
+    }
+
+    fn high_bank(&self) -> usize {
+        ((self.base_bank & self.mask) | (self.rom_bank & !self.mask)) as usize
+    }
+}
+
+impl Cartridge for Sachen {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            rom_byte(&self.rom, self.low_bank(), addr)
+        } else {
+            rom_byte(&self.rom, self.high_bank(), addr)
+        }
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x0000..=0x1FFF if self.unlocked() => self.base_bank = val,
+            0x2000..=0x3FFF => self.rom_bank = if val == 0 { 1 } else { val },
+            0x4000..=0x5FFF if self.unlocked() => self.mask = val,
+            _ => {},
+        }
+    }
+
+    fn read_ram(&self, _addr: u16) -> u8 {
+        0xFF
+    }
+
+    fn write_ram(&mut self, _addr: u16, _val: u8) {}
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        let bank = if addr < 0x4000 { self.low_bank() } else { self.high_bank() };
+        mapped_bank(&self.rom, bank) as u16
+    }
+
+    fn ram(&self) -> &[u8] {
+        &[]
+    }
+
+    fn load_ram(&mut self, _data: &[u8]) {}
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_u8(self.base_bank);
+        state.write_u8(self.rom_bank);
+        state.write_u8(self.mask);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.base_bank = state.read_u8()?;
+        self.rom_bank = state.read_u8()?;
+        self.mask = state.read_u8()?;
+        Ok(())
+    }
+}
+
+/// Address of the header byte seen by the boot ROM at an address of 0x0100 - 0x01FF while
+/// the cartridge is locked: A7 is forced high, so the logo is read from 0x0184 instead of 0x0104.
+/// Some cartridges also swap address lines A0 with A6 and A1 with A4, which scrambles that logo.
+pub(crate) fn locked_address(addr: u16, scrambled: bool) -> u16 {
+    let addr = addr | 0x80;
+    if !scrambled {
+        return addr;
+    }
+    (addr & 0xFFAC) | (addr & 0x40) >> 6 | (addr & 0x10) >> 3 | (addr & 0x02) << 3 | (addr & 0x01) << 6
+}
@@ -0,0 +1,107 @@
+use crate::error::StateError;
+
+/// Serializes the state of a cartridge, for save states. Values are stored in order, little-endian,
+/// and must be read back in the same order by `StateReader`.
+#[derive(Debug, Default)]
+pub struct StateWriter {
+    data: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn new() -> Self {
+        Self { data: Vec::new() }
+    }
+
+    pub fn write_u8(&mut self, val: u8) {
+        self.data.push(val);
+    }
+
+    pub fn write_bool(&mut self, val: bool) {
+        self.data.push(val as u8);
+    }
+
+    pub fn write_u16(&mut self, val: u16) {
+        self.data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, val: u32) {
+        self.data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, val: u64) {
+        self.data.extend_from_slice(&val.to_le_bytes());
+    }
+
+    /// Writes a memory, preceded by its size.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.data.extend_from_slice(bytes);
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+/// Reads back a state written by `StateWriter`.
+#[derive(Debug)]
+pub struct StateReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, StateError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    /// Reads a byte that must be at most `max`, such as a bank or register number.
+    pub fn read_u8_max(&mut self, max: u8) -> Result<u8, StateError> {
+        match self.read_u8()? {
+            val if val > max => Err(StateError::InvalidValue(val)),
+            val => Ok(val),
+        }
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, StateError> {
+        Ok(self.read_u8()? != 0)
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, StateError> {
+        Ok(u16::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, StateError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, StateError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    /// Reads a memory written by `write_bytes`, which must have the size of the destination.
+    pub fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<(), StateError> {
+        let len = self.read_u32()? as usize;
+        if len != bytes.len() {
+            return Err(StateError::SizeMismatch { expected: bytes.len(), actual: len });
+        }
+        let (data, rest) = self.data.split_at_checked(len).ok_or(StateError::Truncated)?;
+        bytes.copy_from_slice(data);
+        self.data = rest;
+        Ok(())
+    }
+
+    /// Checks that the whole state was read.
+    pub fn finish(&self) -> Result<(), StateError> {
+        if self.data.is_empty() { Ok(()) } else { Err(StateError::TrailingData(self.data.len())) }
+    }
+
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], StateError> {
+        let (data, rest) = self.data.split_first_chunk::<N>().ok_or(StateError::Truncated)?;
+        self.data = rest;
+        Ok(*data)
+    }
+}
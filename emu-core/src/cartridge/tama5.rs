@@ -0,0 +1,316 @@
+use std::time::Duration;
+
+use crate::cartridge::rtc::{unix_time, Elapsed, TimeSource};
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{load_ram, mapped_bank, rom_byte, Cartridge};
+use crate::error::StateError;
+
+// Registers, selected by writing their number to 0xA001
+const REG_ROM_BANK_LOW: u8 = 0x0;
+const REG_ROM_BANK_HIGH: u8 = 0x1;
+const REG_DATA_LOW: u8 = 0x4;
+const REG_DATA_HIGH: u8 = 0x5;
+const REG_COMMAND: u8 = 0x6; // bit 0: bit 4 of the address, bits 1 - 3: command
+const REG_ADDRESS: u8 = 0x7; // writing the low 4 bits of the address runs the command
+const REG_READY: u8 = 0xA;
+const REG_READ_LOW: u8 = 0xC;
+const REG_READ_HIGH: u8 = 0xD;
+
+// Commands, in bits 1 - 3 of REG_COMMAND
+const CMD_WRITE_RAM: u8 = 0x0;
+const CMD_READ_RAM: u8 = 0x1;
+const CMD_WRITE_RTC: u8 = 0x2;
+const CMD_READ_RTC: u8 = 0x3;
+
+const RAM_SIZE: usize = 0x20;
+// Size of the clock footer of save files: the time of saving as a 64-bit UNIX timestamp, then the
+// seconds, minutes, hours, day of the week, day, month and year counters
+const FOOTER_SIZE: usize = 15;
+
+/// TAMA5: mapper of Tamagotchi 3, made of a TAMA5 chip, a TAMA6 microcontroller with 32 bytes of
+/// battery-backed RAM and a TC8521 real-time clock (see: https://gbdev.io/pandocs/TAMA5.html).
+/// Everything goes through 4-bit registers: 0xA001 selects one, and 0xA000 reads or writes it.
+pub(crate) struct Tama5 {
+    rom: Vec<u8>,
+    ram: [u8; RAM_SIZE],
+    clock: Clock,
+    selected: u8,        // register accessed at 0xA000
+    registers: [u8; 16], // values written to the registers
+    read: u8,            // result of the last read command
+}
+
+impl Tama5 {
+    pub fn new(rom: Vec<u8>, source: Box<dyn TimeSource>) -> Self {
+        Self { rom, ram: [0; RAM_SIZE], clock: Clock::new(source), selected: 0, registers: [0; 16], read: 0 }
+    }
+
+    fn selected_bank(&self) -> usize {
+        ((self.registers[REG_ROM_BANK_HIGH as usize] & 0x01) << 4 | self.registers[REG_ROM_BANK_LOW as usize]) as usize
+    }
+
+    fn run_command(&mut self) {
+        let command = self.registers[REG_COMMAND as usize];
+        let addr = ((command & 0x01) << 4 | self.registers[REG_ADDRESS as usize]) as usize;
+        let data = self.registers[REG_DATA_HIGH as usize] << 4 | self.registers[REG_DATA_LOW as usize];
+        match command >> 1 {
+            CMD_WRITE_RAM => self.ram[addr] = data,
+            CMD_READ_RAM => self.read = self.ram[addr],
+            CMD_WRITE_RTC => self.clock.write_digit(addr as u8 & 0x0F, data & 0x0F),
+            CMD_READ_RTC => self.read = self.clock.read_digit(addr as u8 & 0x0F),
+            _ => {},
+        }
+    }
+}
+
+impl Cartridge for Tama5 {
+    fn read_rom(&self, addr: u16) -> u8 {
+        if addr < 0x4000 {
+            rom_byte(&self.rom, 0, addr)
+        } else {
+            rom_byte(&self.rom, self.selected_bank(), addr)
+        }
+    }
+
+    // The mapper has no registers in the ROM area
+    fn write_rom(&mut self, _addr: u16, _val: u8) {}
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        // Only 0xA000 is readable (and mirrored), with the upper nibble set
+        if addr & 0x01 != 0 {
+            return 0xFF;
+        }
+        match self.selected {
+            // The microcontroller answers commands instantly
+            REG_READY => 0xF1,
+            REG_READ_LOW => 0xF0 | (self.read & 0x0F),
+            REG_READ_HIGH => 0xF0 | self.read >> 4,
+            _ => 0xFF,
+        }
+    }
+
+    fn write_ram(&mut self, addr: u16, val: u8) {
+        if addr & 0x01 != 0 {
+            self.selected = val & 0x0F;
+            return;
+        }
+        self.registers[self.selected as usize] = val & 0x0F;
+        if self.selected == REG_ADDRESS {
+            self.run_command();
+        }
+    }
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        if addr < 0x4000 { 0 } else { mapped_bank(&self.rom, self.selected_bank()) as u16 }
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        load_ram(&mut self.ram, data);
+    }
+
+    fn write_save_footer(&mut self, save: &mut Vec<u8>) {
+        self.clock.write_footer(save);
+    }
+
+    fn load_save_footer(&mut self, footer: &[u8]) {
+        self.clock.read_footer(footer);
+    }
+
+    fn tick(&mut self, t_cycles: u32) {
+        self.clock.elapsed.tick(t_cycles);
+    }
+
+    fn set_time_source(&mut self, source: Box<dyn TimeSource>) {
+        self.clock.set_source(source);
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+        self.clock.save_state(state);
+        state.write_u8(self.selected);
+        state.write_bytes(&self.registers);
+        state.write_u8(self.read);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.ram)?;
+        self.clock.load_state(state)?;
+        self.selected = state.read_u8_max(0x0F)?;
+        // Registers are 4 bits, and the address and bank registers index the RAM and ROM
+        let mut registers = [0; 16];
+        state.read_bytes(&mut registers)?;
+        if let Some(&val) = registers.iter().find(|&&val| val > 0x0F) {
+            return Err(StateError::InvalidValue(val));
+        }
+        self.registers = registers;
+        self.read = state.read_u8()?;
+        Ok(())
+    }
+}
+
+// The TC8521 counts the date and time in BCD digits: seconds, minutes, hours, day of the week,
+// day, month and year (2 digits, every multiple of 4 being a leap year).
+// Like the other clocks, it is brought up to date from its time source when accessed
+struct Clock {
+    elapsed: Elapsed,
+    subsecond: Duration, // time counted since the last increment of the seconds
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    weekday: u8, // 0 - 6
+    day: u8,     // 1 - 31
+    month: u8,   // 1 - 12
+    year: u8,    // 0 - 99
+}
+
+impl Clock {
+    fn new(source: Box<dyn TimeSource>) -> Self {
+        Self {
+            elapsed: Elapsed::new(source),
+            subsecond: Duration::ZERO,
+            seconds: 0,
+            minutes: 0,
+            hours: 0,
+            weekday: 0,
+            day: 1,
+            month: 1,
+            year: 0,
+        }
+    }
+
+    fn set_source(&mut self, source: Box<dyn TimeSource>) {
+        self.update();
+        self.elapsed.set_source(source);
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        self.update();
+        state.write_u64(self.subsecond.as_nanos() as u64);
+        for counter in [self.seconds, self.minutes, self.hours, self.weekday, self.day, self.month, self.year] {
+            state.write_u8(counter);
+        }
+    }
+
+    // The clock goes on from the saved time, ignoring the time elapsed meanwhile
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.subsecond = Duration::from_nanos(state.read_u64()?);
+        for counter in [
+            &mut self.seconds,
+            &mut self.minutes,
+            &mut self.hours,
+            &mut self.weekday,
+            &mut self.day,
+            &mut self.month,
+            &mut self.year,
+        ] {
+            *counter = state.read_u8()?;
+        }
+        self.elapsed.take();
+        Ok(())
+    }
+
+    fn write_footer(&mut self, save: &mut Vec<u8>) {
+        self.update();
+        save.extend_from_slice(&unix_time().to_le_bytes());
+        let counters = [self.seconds, self.minutes, self.hours, self.weekday, self.day, self.month, self.year];
+        save.extend_from_slice(&counters);
+    }
+
+    // Restores the counters, and moves them forward by the time elapsed since the save was written.
+    // Footers with a date or time out of range are ignored
+    fn read_footer(&mut self, footer: &[u8]) {
+        if footer.len() != FOOTER_SIZE {
+            return;
+        }
+        let timestamp = u64::from_le_bytes(footer[0..8].try_into().unwrap());
+        let [seconds, minutes, hours, weekday, day, month, year] = footer[8..].try_into().unwrap();
+        if seconds >= 60 || minutes >= 60 || hours >= 24 || weekday >= 7 || !(1..=31).contains(&day)
+            || !(1..=12).contains(&month) || year >= 100
+        {
+            return;
+        }
+        self.update();
+        (self.seconds, self.minutes, self.hours, self.weekday) = (seconds, minutes, hours, weekday);
+        (self.day, self.month, self.year) = (day, month, year);
+        self.subsecond = Duration::from_secs(unix_time().saturating_sub(timestamp));
+        self.update();
+    }
+
+    // Counter holding the digit in a register, and whether it is the tens digit
+    fn counter(&mut self, reg: u8) -> Option<(&mut u8, bool)> {
+        Some(match reg {
+            0x0 => (&mut self.seconds, false),
+            0x1 => (&mut self.seconds, true),
+            0x2 => (&mut self.minutes, false),
+            0x3 => (&mut self.minutes, true),
+            0x4 => (&mut self.hours, false),
+            0x5 => (&mut self.hours, true),
+            0x6 => (&mut self.weekday, false),
+            0x7 => (&mut self.day, false),
+            0x8 => (&mut self.day, true),
+            0x9 => (&mut self.month, false),
+            0xA => (&mut self.month, true),
+            0xB => (&mut self.year, false),
+            0xC => (&mut self.year, true),
+            _ => return None,
+        })
+    }
+
+    fn read_digit(&mut self, reg: u8) -> u8 {
+        self.update();
+        match self.counter(reg) {
+            Some((counter, true)) => *counter / 10,
+            Some((counter, false)) => *counter % 10,
+            None => 0,
+        }
+    }
+
+    fn write_digit(&mut self, reg: u8, digit: u8) {
+        self.update();
+        if reg == 0x0 {
+            self.subsecond = Duration::ZERO;
+        }
+        if let Some((counter, tens)) = self.counter(reg) {
+            *counter = if tens { digit * 10 + *counter % 10 } else { *counter / 10 * 10 + digit };
+        }
+    }
+
+    fn update(&mut self) {
+        self.subsecond += self.elapsed.take();
+        let seconds = self.subsecond.as_secs();
+        self.subsecond -= Duration::from_secs(seconds);
+
+        let total = self.seconds as u64 + 60 * self.minutes as u64 + 3600 * self.hours as u64 + seconds;
+        self.seconds = (total % 60) as u8;
+        self.minutes = (total / 60 % 60) as u8;
+        self.hours = (total / 3600 % 24) as u8;
+        for _ in 0..total / (24 * 3600) {
+            self.next_day();
+        }
+    }
+
+    fn next_day(&mut self) {
+        self.weekday = (self.weekday + 1) % 7;
+        self.day += 1;
+        if self.day > self.month_length() {
+            self.day = 1;
+            self.month += 1;
+            if self.month > 12 {
+                self.month = 1;
+                self.year = (self.year + 1) % 100;
+            }
+        }
+    }
+
+    fn month_length(&self) -> u8 {
+        match self.month {
+            2 if self.year.is_multiple_of(4) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{mapped_bank, rom_byte, Cartridge};
+use crate::error::StateError;
+
+/// Wisdom Tree: unlicensed mapper switching the whole 32KB of ROM at once. The bank is selected by
+/// the lower byte of the address written to in 0x0000 - 0x3FFF, whatever the value written.
+pub(crate) struct WisdomTree {
+    rom: Vec<u8>,
+    bank: u8, // 32KB bank
+}
+
+impl WisdomTree {
+    pub fn new(rom: Vec<u8>) -> Self {
+        Self { rom, bank: 0 }
+    }
+
+    // 16KB bank mapped at an address
+    fn bank_at(&self, addr: u16) -> usize {
+        2 * self.bank as usize + (addr >= 0x4000) as usize
+    }
+}
+
+impl Cartridge for WisdomTree {
+    fn read_rom(&self, addr: u16) -> u8 {
+        rom_byte(&self.rom, self.bank_at(addr), addr)
+    }
+
+    fn write_rom(&mut self, addr: u16, _val: u8) {
+        if addr < 0x4000 {
+            self.bank = addr as u8;
+        }
+    }
+
+    fn read_ram(&self, _addr: u16) -> u8 {
+        0xFF
+    }
+
+    fn write_ram(&mut self, _addr: u16, _val: u8) {}
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        mapped_bank(&self.rom, self.bank_at(addr)) as u16
+    }
+
+    fn ram(&self) -> &[u8] {
+        &[]
+    }
+
+    fn load_ram(&mut self, _data: &[u8]) {}
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_u8(self.bank);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        self.bank = state.read_u8()?;
+        Ok(())
+    }
+}
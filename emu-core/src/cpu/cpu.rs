@@ -2,54 +2,60 @@ use crate::cpu::ld;
 use crate::cpu::alu;
 use crate::cpu::stack;
 use crate::cpu::jumps;
+use crate::cpu::opcodes::{Mnemonic, OpcodeInfo, Operand, Reg16, CB_OPCODES, OPCODES};
+use crate::error::EmuError;
+use crate::interrupts::Interrupt;
 use crate::memory::MemoryBus;
 use crate::cpu::registers::{CpuFlag, Registers};
 
-use core::panic;
-use std::convert::TryFrom;
+/// Power state of the CPU, as seen by frontends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CpuState {
+    Running,
+    Halted,  // low-power HALT mode, woken up by any pending interrupt
+    Stopped, // STOP mode: the system clock is stopped until a joypad line goes low
+    Locked(LockReason), // hung until power-off, only the rest of the system keeps running
+}
+
+/// Why the CPU locked up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockReason {
+    // One of the unused opcodes (0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD)
+    IllegalOpcode { pc: u16, opcode: u8 },
+}
+
+/// What happened during a step (see `Cpu::step`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepInfo {
+    // Address and opcode of the instruction executed by the step. When no instruction was executed
+    // (interrupt dispatch, HALT...), the instruction that will run once the CPU resumes (0xCB for prefixed ones)
+    pub pc: u16,
+    pub opcode: u8,
+    pub cycles: u32,     // M-cycles taken by the step
+    pub executed: bool,  // false if the step dispatched an interrupt or idled
+    pub interrupt: Option<Interrupt>, // interrupt dispatched by the step, if any
+}
 
 pub struct Cpu<M: MemoryBus> {
     pub reg: Registers,
-    halted: bool,
+    state: CpuState,
+    halt_bug: bool,
     ime: bool,
-    setei: u32,
-    setdi: u32,
+    ime_scheduled: bool,
+    cycles: u64,
     pub prefetched: u8,
     pub mmu: M,
 }
 
-#[repr(u8)]
-enum InterruptFlag {
-    VBlank = 0,
-    LCD = 1,
-    Timer = 2,
-    Serial = 3,
-    Joypad = 4,
-}
-
-impl TryFrom<u8> for InterruptFlag {
-    type Error = ();
-
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            0 => Ok(InterruptFlag::VBlank),
-            1 => Ok(InterruptFlag::LCD),
-            2 => Ok(InterruptFlag::Timer),
-            3 => Ok(InterruptFlag::Serial),
-            4 => Ok(InterruptFlag::Joypad),
-            _ => Err(()),
-        }
-    }
-}
-
 impl<M: MemoryBus> Cpu<M> {
     pub fn new(mmu: M) -> Self {
         Self {
             reg: Registers::new(),
-            halted: false,
+            state: CpuState::Running,
+            halt_bug: false, // true while the prefetched opcode was read without advancing PC
             ime: false, // true if interrupts are enabled
-            setei: 0,   // same
-            setdi: 0,   // same
+            ime_scheduled: false, // true if EI was executed, IME being set after the next instruction
+            cycles: 0, // M-cycles elapsed since power-on
             prefetched: 0,
             mmu,
         }
@@ -71,7 +77,7 @@ impl<M: MemoryBus> Cpu<M> {
         cpu.reg.l = 0x4D;
         cpu.reg.sp = 0xFFFE;
         cpu.reg.pc = 0x100;
-        cpu.prefetched = cpu.mmu.read_byte(0x100);
+        cpu.prefetched = cpu.mmu.peek_byte(0x100);
 
         cpu
     }
@@ -88,129 +94,262 @@ impl<M: MemoryBus> Cpu<M> {
             self.reg.h,
             self.reg.l,
             self.reg.sp,
-            self.reg.pc-1, // -1 because prefetched contains the current instruction
-                           // and PC contains the adress of the next instruction
+            self.current_instr_addr(),
             self.prefetched,
-            self.mmu.read_byte(self.reg.pc),
-            self.mmu.read_byte(self.reg.pc+1),
-            self.mmu.read_byte(self.reg.pc+2),
+            self.mmu.peek_byte(self.reg.pc),
+            self.mmu.peek_byte(self.reg.pc.wrapping_add(1)),
+            self.mmu.peek_byte(self.reg.pc.wrapping_add(2)),
         )
     }
 
-    pub fn tick(&mut self) {
-        if self.halted {
-            // Check if there are any pending interrupts to wake from HALT
-            let interrupt_enabled = self.mmu.read_byte(0xFFFF);
-            let interrupt_flags = self.mmu.read_byte(0xFF0F);
-            if (interrupt_enabled & interrupt_flags & 0x1F) != 0 {
-                self.halted = false;
-            } else {
-                return;
-            }
-        }
-
-        let cycles = self.execute();
-        self.mmu.tick(cycles);
+    pub fn state(&self) -> CpuState {
+        self.state
+    }
 
-        // Prefetch next opcode
-        self.prefetched = self.read_byte();
+    /// Returns the reason why the CPU locked up, if it did.
+    pub fn lock_reason(&self) -> Option<LockReason> {
+        match self.state {
+            CpuState::Locked(reason) => Some(reason),
+            _ => None,
+        }
+    }
 
-        // Update IME after instruction execution to implement EI/DI delay
-        self.update_ime();
+    /// Returns the number of M-cycles elapsed since power-on.
+    /// Nothing is counted while in STOP mode, as the system clock is stopped.
+    pub fn m_cycles(&self) -> u64 {
+        self.cycles
+    }
 
-        // Check and handle interrupts AFTER instruction execution
-        if self.handle_interrupts() {
-            // An interrupt was serviced, PC now points to interrupt vector
-            // Fetch the first instruction of the handler
-            self.prefetched = self.read_byte();
+    /// Returns the number of T-cycles (clock ticks, 4 per M-cycle) elapsed since power-on.
+    pub fn t_cycles(&self) -> u64 {
+        self.cycles * 4
+    }
 
-            // Execute it immediately (interrupt + first instruction happen in same tick)
-            let cycles = self.execute();
-            self.mmu.tick(cycles);
+    /// Runs the CPU for one step, ignoring errors. See `try_step`.
+    pub fn tick(&mut self) {
+        let _ = self.try_step();
+    }
 
-            // Prefetch the next instruction
-            self.prefetched = self.read_byte();
+    /// Same as `try_step`, ignoring errors.
+    pub fn step(&mut self) -> StepInfo {
+        let info = self.step_inner();
+        // Drop the error, so that it isn't reported by a later `try_step`
+        let _ = self.mmu.take_error();
+        info
+    }
 
-            // Note: update_ime was already called above, so IME delay is handled
+    /// Runs the CPU for one step: executes an instruction, dispatches a pending interrupt,
+    /// or idles while halted, stopped or locked.
+    /// Dispatching an interrupt is a step of its own, the handler's first instruction being executed by the next one.
+    /// Whatever the ROM being run, errors are reported through the returned value and never by panicking.
+    pub fn try_step(&mut self) -> Result<StepInfo, EmuError> {
+        let info = self.step_inner();
+        match self.mmu.take_error() {
+            Some(err) => Err(err),
+            None => Ok(info),
         }
     }
 
-    fn update_ime(&mut self) {
-        // when updating ime (via "set di" or "set ei"), the effect is delayed by one instruction
-        // (see: https://gbdev.io/pandocs/Interrupts.html)
-        // Counter of 1 means: IME will be updated after next instruction
-        // Counter of 0 means: no pending IME update
-        if self.setei > 0 {
-            if self.setei == 1 {
-                self.ime = true;
+    /// Runs whole steps until at least `cycles` M-cycles have elapsed, ignoring errors.
+    /// Returns the number of M-cycles actually run, which overshoots by the end of the last step,
+    /// and falls short if the CPU is left in STOP mode (only joypad input can wake it up).
+    pub fn run_for_cycles(&mut self, cycles: u64) -> u64 {
+        let start = self.cycles;
+        while self.cycles - start < cycles {
+            if self.step().cycles == 0 {
+                break; // stopped
             }
-            self.setei -= 1;
         }
+        self.cycles - start
+    }
 
-        if self.setdi > 0 {
-            if self.setdi == 1 {
-                self.ime = false;
+    /// Runs steps until `done` returns true (checked before each step), ignoring errors.
+    /// Returns the number of M-cycles run. Like `run_for_cycles`, it also returns if the CPU is left
+    /// in STOP mode, where time doesn't advance until joypad input wakes it up.
+    pub fn run_until<F: FnMut(&Self) -> bool>(&mut self, mut done: F) -> u64 {
+        let start = self.cycles;
+        while !done(self) {
+            if self.step().cycles == 0 {
+                break; // stopped
             }
-            self.setdi -= 1;
         }
+        self.cycles - start
+    }
+
+    fn step_inner(&mut self) -> StepInfo {
+        let start = self.cycles;
+        let mut info = StepInfo {
+            pc: self.current_instr_addr(),
+            opcode: self.prefetched,
+            cycles: 0,
+            executed: false,
+            interrupt: None,
+        };
+
+        self.run_step(&mut info);
+        info.cycles = (self.cycles - start) as u32;
+        info
     }
 
-    fn handle_interrupts(&mut self) -> bool {
-        // don't handle interrupts if ime is disabled
-        if !self.ime {
-            return false;
+    fn run_step(&mut self, info: &mut StepInfo) {
+        if let CpuState::Locked(_) = self.state {
+            // The CPU never recovers (even interrupts are ignored), but the bus keeps being clocked
+            self.idle();
+            return;
+        }
+
+        if self.state == CpuState::Stopped {
+            // The system clock is stopped: nothing runs until a joypad line goes low
+            if !self.mmu.joypad_line_low() {
+                return;
+            }
+            self.state = CpuState::Running;
+            self.mmu.resume();
         }
 
-        let interrupt_enabled = self.mmu.read_byte(0xFFFF);
+        if self.state == CpuState::Halted {
+            // Any enabled and requested interrupt ends HALT, whether IME is set or not
+            if self.pending_interrupts() == 0 {
+                self.idle();
+                return;
+            }
+            self.state = CpuState::Running;
 
-        if (interrupt_enabled & 0x1F) == 0 {
-            return false; // no interrupts enabled
+            // With IME cleared, execution simply resumes after HALT without any dispatch.
+            // Otherwise the interrupt is dispatched right away, and returns to the instruction after HALT.
         }
 
-        let interrupt_flags = self.mmu.read_byte(0xFF0F);
-        let pending_interrupts = interrupt_enabled & interrupt_flags;
+        // Interrupts are checked between instructions
+        if self.ime && self.mmu.interrupts().pending() != 0 {
+            info.interrupt = self.dispatch_interrupt();
+            return;
+        }
 
-        // look for the highest priority interrupt that is requested and enabled
-        // (lowest set byte in 0xFFFF & 0xFF0F)
-        for i in 0..5 {
-            if pending_interrupts & (1 << i) > 0 {
-                // IME is set, interrupt i is enabled and requested: handle it
-                let flag = InterruptFlag::try_from(i as u8).unwrap();
+        // EI takes effect after the instruction following it
+        // (see: https://gbdev.io/pandocs/Interrupts.html#ei)
+        let enable_ime = self.ime_scheduled;
 
-                // Two internal cycles for interrupt dispatch
-                self.mmu.tick_internal();
-                self.mmu.tick_internal();
+        // Every M-cycle of the instruction is clocked by its own bus accesses
+        self.execute();
+        info.executed = true;
 
-                // unset the corresponding IF flag
-                self.mmu.write_byte(0xFF0F, interrupt_flags ^ (1 << i));
-                // unset IME to disable other interrupts in the meantime
-                self.ime = false; // TODO: shoud we handle nested interrupts?
+        if let CpuState::Locked(_) = self.state {
+            return;
+        }
 
-                // Call the associated interrupt handler (this pushes PC and jumps)
-                let cycles = self.call_interrupt_handler(flag);
-                self.mmu.tick(cycles);
+        // Prefetch next opcode
+        self.prefetch();
 
-                return true; // Interrupt was serviced
-            }
+        // DI (executed right after EI) cancels the scheduled enable
+        if enable_ime && self.ime_scheduled {
+            self.ime = true;
+            self.ime_scheduled = false;
         }
+    }
 
-        false // No interrupt was serviced
+    /// Keeps the peripherals running while halted or locked. Instead of spinning one M-cycle at a time,
+    /// skips straight to the next event scheduled on the bus.
+    fn idle(&mut self) {
+        let mut cycles = self.mmu.next_event().unwrap_or(1).max(1);
+        while cycles > 0 {
+            let step = cycles.min(u8::MAX as u32);
+            self.mmu.tick(step as u8);
+            self.cycles += step as u64;
+            cycles -= step;
+        }
     }
 
-    fn call_interrupt_handler(&mut self, flag: InterruptFlag) -> u8 {
-        match flag {
-            InterruptFlag::VBlank => self.direct_call(0x40),
-            InterruptFlag::LCD    => self.direct_call(0x48),
-            InterruptFlag::Timer  => self.direct_call(0x50),
-            InterruptFlag::Serial => self.direct_call(0x58),
-            InterruptFlag::Joypad => self.direct_call(0x60),
+    /// Returns the address of the prefetched instruction.
+    fn current_instr_addr(&self) -> u16 {
+        if self.halt_bug {
+            // PC was not incremented when fetching the current instruction
+            self.reg.pc
+        } else {
+            // -1 because prefetched contains the current instruction
+            // and PC contains the adress of the next instruction
+            self.reg.pc.wrapping_sub(1)
         }
     }
 
+    fn prefetch(&mut self) {
+        if self.halt_bug {
+            // HALT bug: the opcode following HALT is read, but PC fails to increment,
+            // so this byte is read a second time by the next fetch
+            self.prefetched = self.read_mem(self.reg.pc);
+        } else {
+            self.prefetched = self.read_byte();
+        }
+    }
+
+    /// Returns the interrupts that are both enabled (IE) and requested (IF).
+    fn pending_interrupts(&self) -> u8 {
+        // IE and IF are checked by the CPU internally, this does not take any bus cycle
+        self.mmu.interrupts().pending()
+    }
+
+    /// Dispatches the highest priority pending interrupt, in 5 M-cycles
+    /// (see: https://gbdev.io/pandocs/Interrupts.html#interrupt-handling).
+    /// Returns the interrupt dispatched, or None if the dispatch was cancelled.
+    fn dispatch_interrupt(&mut self) -> Option<Interrupt> {
+        self.ime = false;
+        self.ime_scheduled = false;
+
+        // M1: the prefetched opcode is discarded and PC is decremented, so that the handler returns to it
+        // (or to HALT itself after a HALT bug, as PC was not incremented by the prefetch)
+        self.reg.pc = self.reg.pc.wrapping_sub(1);
+        self.halt_bug = false;
+        self.tick_internal();
+
+        // M2: SP is decremented
+        self.reg.sp = self.reg.sp.wrapping_sub(1);
+        self.tick_internal();
+
+        // M3: the high byte of PC is pushed
+        self.write_mem(self.reg.sp, (self.reg.pc >> 8) as u8);
+        self.reg.sp = self.reg.sp.wrapping_sub(1);
+
+        // The interrupt is only picked now: if the push overwrote IE (SP was 0x0000) and no enabled
+        // interrupt is requested anymore, the dispatch is cancelled and jumps to 0x0000 instead
+        let interrupt = self.mmu.interrupts().highest_pending();
+
+        // M4: the low byte of PC is pushed
+        self.write_mem(self.reg.sp, (self.reg.pc & 0xFF) as u8);
+
+        self.reg.pc = match interrupt {
+            Some(interrupt) => {
+                self.mmu.interrupts_mut().acknowledge(interrupt);
+                interrupt.vector()
+            },
+            None => 0x0000,
+        };
+
+        // M5: the first opcode of the handler is fetched
+        self.prefetched = self.read_byte();
+        interrupt
+    }
+
+    /// Reads memory, taking one M-cycle.
+    pub(crate) fn read_mem(&mut self, addr: u16) -> u8 {
+        self.cycles += 1;
+        self.mmu.read_byte(addr)
+    }
+
+    /// Writes memory, taking one M-cycle.
+    pub(crate) fn write_mem(&mut self, addr: u16, val: u8) {
+        self.cycles += 1;
+        self.mmu.write_byte(addr, val);
+    }
+
+    /// Spends one M-cycle on an internal operation, without accessing memory.
+    pub(crate) fn tick_internal(&mut self) {
+        self.cycles += 1;
+        self.mmu.tick_internal();
+    }
+
     pub fn read_byte(&mut self) -> u8 {
         // At this point, the next instruction is not yet prefetched
         // so self.prefetched contains the current instruction
-        let val = self.mmu.read_byte(self.reg.pc);
+        let val = self.read_mem(self.reg.pc);
         self.reg.pc = self.reg.pc.wrapping_add(1); // ensure wrapping on overflow
 
         val
@@ -222,186 +361,136 @@ impl<M: MemoryBus> Cpu<M> {
         (high << 8) | low
     }
 
-    /// Executes the instructions at mem[pc].
-    /// Returns the number of cycles
-    fn execute(&mut self) -> u8 {
-        let opcode = self.prefetched;
-        let high = (opcode & 0xF0) >> 4;
-        let low = opcode & 0x0F;
-        match (high, low) {
-            (0, 0) => 1, // NOP
-            (0x7, 0x6) => { // HALT
-                self.halted = true;
-                1
+    /// Reads an 8-bit operand: a register, an immediate value or memory.
+    /// Immediate bytes are fetched first, so the memory access comes last like on hardware.
+    pub(crate) fn read_operand(&mut self, operand: Operand) -> u8 {
+        match operand {
+            Operand::Reg8(reg) => self.reg.get8(reg),
+            Operand::Imm8 => self.read_byte(),
+            _ => {
+                let addr = self.operand_addr(operand);
+                self.read_mem(addr)
             },
-            (0x1, 0x0) => { // STOP
-                self.read_byte(); // STOP is a 2 bytes instruction
-                self.halted = true; //TODO: add a separate flag as wake up conditoin is different from halt
-                2
+        }
+    }
+
+    /// Writes an 8-bit operand: a register or memory.
+    pub(crate) fn write_operand(&mut self, operand: Operand, val: u8) {
+        match operand {
+            Operand::Reg8(reg) => self.reg.set8(reg, val),
+            _ => {
+                let addr = self.operand_addr(operand);
+                self.write_mem(addr, val);
+            },
+        }
+    }
+
+    // Address of a memory operand, fetching its immediate bytes if any
+    fn operand_addr(&mut self, operand: Operand) -> u16 {
+        match operand {
+            Operand::Indirect(reg) => self.reg.get16(reg),
+            Operand::HlInc => {
+                let hl = self.reg.hl();
+                self.reg.set_hl(hl.wrapping_add(1));
+                hl
+            },
+            Operand::HlDec => {
+                let hl = self.reg.hl();
+                self.reg.set_hl(hl.wrapping_sub(1));
+                hl
             },
-            (0xF, 0x3) => self.set_di(),
-            (0xF, 0xB) => self.set_ei(),
+            Operand::HighC => 0xFF00 | self.reg.c as u16,
+            Operand::HighAddr8 => 0xFF00 | self.read_byte() as u16,
+            Operand::Addr16 => self.read_word(),
+            _ => unreachable!("not a memory operand: {:?}", operand),
+        }
+    }
+
+    /// Executes the prefetched instruction, as described by the opcode table.
+    fn execute(&mut self) {
+        let opcode = self.prefetched;
+        let opcode_addr = self.current_instr_addr();
+        // Operands of the current instruction are read from PC as usual, even after a HALT bug
+        self.halt_bug = false;
+
+        let info = &OPCODES[opcode as usize];
+        let [first, second] = info.operands;
+        match info.mnemonic {
+            Mnemonic::Nop => {},
+            Mnemonic::Halt => self.halt(),
+            Mnemonic::Stop => self.stop(),
+            Mnemonic::Di => self.set_di(),
+            Mnemonic::Ei => self.set_ei(),
 
             // -- register manipulations
-            (0x2, 0x7) => self.daa(),
-            (0x3, 0x7) => self.scf(),
-            (0x2, 0xF) => self.cpl(),
-            (0x3, 0xF) => self.ccf(),
-
-            // -- 8-bit loads
-            // register <- constant
-            (0x0..=0x2, 0x6) => ld::ld_cst_to_reg(self, opcode),
-            (0x0..=0x3, 0xE) => ld::ld_cst_to_reg(self, opcode),
-
-            // register <- register
-            (0x4..=0x6, 0x0..=0x5) => ld::ld_reg_to_reg(self, opcode),
-            (0x4..=0x6, 0x7..=0xD) => ld::ld_reg_to_reg(self, opcode),
-            (0x7, 0x8..=0xD) => ld::ld_reg_to_reg(self, opcode),
-            (0x4..=0x7, 0xF) => ld::ld_reg_to_reg(self, opcode),
-
-            // register <- memory
-            (0x4..=0x6, 0x6) => ld::ld_mem_to_reg(self, opcode),
-            (0x4..=0x7, 0xE) => ld::ld_mem_to_reg(self, opcode),
-            (0x0..=0x3, 0xA) => ld::ld_mem_to_reg(self, opcode),
-            (0xF, 0x0) => ld::ld_mem_to_reg(self, opcode),
-            (0xF, 0x2) => ld::ld_mem_to_reg(self, opcode),
-            (0xF, 0xA) => ld::ld_mem_to_reg(self, opcode),
-
-            // -- 16-bit loads
-            // register <- register
-            (0xF, 0x8) => ld::ld_reg_to_reg(self, opcode),
-            (0xF, 0x9) => ld::ld_reg_to_reg(self, opcode),
-
-            // 16-bit register <- constant
-            (0x0..=0x03, 0x1) => ld::ld_cst16_to_reg(self, opcode),
-
-            // memory <- register
-            (0x0..=0x3, 0x2) => ld::ld_reg_to_mem(self, opcode),
-            (0x7, 0x0..=0x5) => ld::ld_reg_to_mem(self, opcode),
-            (0x7, 0x7) => ld::ld_reg_to_mem(self, opcode),
-            (0xE, 0x0) => ld::ld_reg_to_mem(self, opcode),
-            (0xE, 0x2) => ld::ld_reg_to_mem(self, opcode),
-            (0xE, 0xA) => ld::ld_reg_to_mem(self, opcode),
-            (0x0, 0x8) => ld::ld_reg_to_mem(self, opcode),
-
-            // memory <- constant
-            (0x3, 0x6) => ld::ld_cst_to_mem(self),
-
-            // -- 8-bit alu
-            // increment 8-bit registers
-            (0x0..=0x2, 0x4) => alu::incr(self, opcode),
-            (0x0..=0x3, 0xC) => alu::incr(self, opcode),
-            // decrement 8-bit registers
-            (0x0..=0x2, 0x5) => alu::decr(self, opcode),
-            (0x0..=0x3, 0xD) => alu::decr(self, opcode),
-
-            // add
-            (0x8, 0x0..=0x7) => alu::add(self, opcode),
-            (0xc, 0x6) => alu::add(self, opcode),
-            (0xe, 0x8) => alu::add(self, opcode),
-            (0x0..=0x3, 0x9) => alu::add(self, opcode),
-
-            // sub
-            (0x9, 0x0..=0x7) => alu::sub(self, opcode),
-            (0xd, 0x6) => alu::sub(self, opcode),
-
-            // adc
-            (0x8, 0x8..=0xF) => alu::adc(self, opcode),
-            (0xc, 0xE) => alu::adc(self, opcode),
-
-            // sbc
-            (0x9, 0x8..=0xF) => alu::sbc(self, opcode),
-            (0xd, 0xE) => alu::sbc(self, opcode),
-
-            // and
-            (0xA, 0x0..=0x7) => alu::and(self, opcode),
-            (0xE, 0x6) => alu::and(self, opcode),
-
-            // or
-            (0xB, 0x0..=0x7) => alu::or(self, opcode),
-            (0xF, 0x6) => alu::or(self, opcode),
-
-            // xor
-            (0xA, 0x8..=0xF) => alu::xor(self, opcode),
-            (0xE, 0xE) => alu::xor(self, opcode),
-
-            // cp
-            (0xB, 0x8..=0xF) => alu::cp(self, opcode),
-            (0xF, 0xE) => alu::cp(self, opcode),
-
-            // rlca/rrca & rla/rra
-            (0x0, 0x7) => alu::rlca(self),
-            (0x0, 0xF) => alu::rrca(self),
-            (0x1, 0x7) => alu::rla(self),
-            (0x1, 0xF) => alu::rra(self),
-
-            // -- 16-bit alu
-            // increment 16-bit registers
-            (0x0..=0x3, 0x3) => alu::incr(self, opcode),
-            // increment memory pointed by 16-bit registers
-            (0x3, 0x4) => alu::incr(self, opcode),
-            // decrement 16-bit registers
-            (0x0..=0x3, 0xB) => alu::decr(self, opcode),
-            // decrement memory pointed by 16-bit registers
-            (0x3, 0x5) => alu::decr(self, opcode),
+            Mnemonic::Daa => self.daa(),
+            Mnemonic::Scf => self.scf(),
+            Mnemonic::Cpl => self.cpl(),
+            Mnemonic::Ccf => self.ccf(),
+            Mnemonic::Rlca => alu::rlca(self),
+            Mnemonic::Rrca => alu::rrca(self),
+            Mnemonic::Rla => alu::rla(self),
+            Mnemonic::Rra => alu::rra(self),
+
+            // -- loads
+            Mnemonic::Ld | Mnemonic::Ldh => ld::ld(self, info),
+
+            // -- alu
+            Mnemonic::Inc => alu::incr(self, operand(first)),
+            Mnemonic::Dec => alu::decr(self, operand(first)),
+            Mnemonic::Add => match first {
+                Some(Operand::Reg16(Reg16::HL)) => alu::add_hl(self, operand(second)),
+                Some(Operand::Reg16(Reg16::SP)) => alu::add_sp(self),
+                _ => alu::alu8(self, Mnemonic::Add, operand(second)),
+            },
+            Mnemonic::Adc | Mnemonic::Sub | Mnemonic::Sbc | Mnemonic::And |
+            Mnemonic::Xor | Mnemonic::Or | Mnemonic::Cp => alu::alu8(self, info.mnemonic, operand(second)),
 
             // -- jumps
-            // relative jumps
-            (0x2..=0x3, 0x0) => jumps::jr(self, opcode),
-            (0x1..=0x3, 0x8) => jumps::jr(self, opcode),
-            // absolute jumps
-            (0xC..=0xD, 0x2) => jumps::jp(self, opcode),
-            (0xC, 0x3) => jumps::jp(self, opcode),
-            (0xE, 0x9) => jumps::jp(self, opcode),
-            (0xC..=0xD, 0xA) => jumps::jp(self, opcode),
-            
+            Mnemonic::Jr => jumps::jr(self, info),
+            Mnemonic::Jp => jumps::jp(self, info),
 
             // -- stack operations
-            // pop & push
-            (0xC..=0xF, 0x1) => stack::pop(self, opcode),
-            (0xC..=0xF, 0x5) => stack::push(self, opcode),
-
-            // call
-            (0xC..=0xD, 0x4) => self.call(opcode),
-            (0xC..=0xD, 0xC) => self.call(opcode),
-            (0xC, 0xD) => self.call(opcode),
+            Mnemonic::Pop => stack::pop(self, reg16(first)),
+            Mnemonic::Push => stack::push(self, reg16(first)),
+            Mnemonic::Call => self.call(info),
+            Mnemonic::Ret => self.ret(info),
+            Mnemonic::Reti => {
+                // RETI enables interrupts immediately, without EI's delay
+                self.ime = true;
+                self.ret(info);
+            },
+            Mnemonic::Rst => match first {
+                Some(Operand::Vector(addr)) => self.direct_call(addr as u16),
+                _ => unreachable!("malformed RST in the opcode table: {:?}", info),
+            },
 
-            // ret
-            (0xC..=0xD, 0x0) => self.ret(opcode),
-            (0xC..=0xD, 0x8..=0x9) => self.ret(opcode),
+            // -- cb prefix
+            Mnemonic::Prefix => self.execute_cb(),
 
-            // rst
-            (0xC..=0xF, 0x7) => self.rst(opcode),
-            (0xC..=0xF, 0xF) => self.rst(opcode),
+            // -- illegal opcodes hang the CPU
+            Mnemonic::Illegal => {
+                self.state = CpuState::Locked(LockReason::IllegalOpcode { pc: opcode_addr, opcode });
+            },
 
-            // -- cb prefix
-            (0xC, 0xB) => self.execute_cb(),
-            _ => panic!("Unknown instruction opcode: Ox{:02X}", opcode),
+            _ => unreachable!("CB-prefixed instruction in the main opcode table: {:?}", info),
         }
     }
 
     /// Executes instructions prefixed by 0xCB
-    fn execute_cb(&mut self) -> u8 {
+    fn execute_cb(&mut self) {
         let opcode = self.read_byte();
-        let high = (opcode & 0xF0) >> 4;
-        let low = opcode & 0x0F;
-        match (high, low) {
-            (0x0, 0x0..=0x7) => alu::rlc(self, opcode), // RLC r
-            (0x0, 0x8..=0xF) => alu::rrc(self, opcode), // RRC r
-            (0x1, 0x0..=0x7) => alu::rl(self, opcode), // RL r
-            (0x1, 0x8..=0xF) => alu::rr(self, opcode), // RR r
-            (0x2, 0x0..=0x7) => alu::sla(self, opcode), // SLA r
-            (0x2, 0x8..=0xF) => alu::sra(self, opcode), // SRA r
-            (0x3, 0x0..=0x7) => alu::swap(self, opcode), // SWAP r
-            (0x3, 0x8..=0xF) => alu::srl(self, opcode), // SRL r
-            (0x4..=0x7, 0x0..=0xF) => alu::bit(self, opcode), // BIT b, r
-            (0x8..=0xB, 0x0..=0xF) => alu::res(self, opcode), // RES b, r
-            (0xC..=0xF, 0x0..=0xF) => alu::set(self, opcode), // SET b, r
-            _ => panic!("Unkown CB prefix instruction opcode: 0xCB{:02X}", opcode),
+        let info = &CB_OPCODES[opcode as usize];
+        match (info.mnemonic, info.operands) {
+            (Mnemonic::Bit, [Some(Operand::Bit(bit)), Some(target)]) => alu::bit(self, bit, target),
+            (Mnemonic::Res, [Some(Operand::Bit(bit)), Some(target)]) => alu::res(self, bit, target),
+            (Mnemonic::Set, [Some(Operand::Bit(bit)), Some(target)]) => alu::set(self, bit, target),
+            (mnemonic, [Some(target), None]) => alu::shift(self, mnemonic, target),
+            _ => unreachable!("malformed CB-prefixed instruction in the opcode table: {:?}", info),
         }
     }
 
-    fn daa(&mut self) -> u8 {
+    fn daa(&mut self) {
         let mut a = self.reg.a;
         let mut adjust = 0;
         let mut carry = false;
@@ -442,131 +531,117 @@ impl<M: MemoryBus> Cpu<M> {
         if carry {
             self.reg.f |= 0x10; // Set C flag
         }
-
-        1
     }
 
-    fn scf(&mut self) -> u8 {
+    fn scf(&mut self) {
         self.reg.f &= 0x90; // Clear N and H flags
         self.reg.f |= 0x10; // Set C flag
-
-        1
     }
 
-    fn cpl(&mut self) -> u8 {
+    fn cpl(&mut self) {
         self.reg.a = !self.reg.a;
         self.reg.f |= 0x60; // Set N and H flags
-
-        1
     }
 
-    fn ccf(&mut self) -> u8 {
+    fn ccf(&mut self) {
         self.reg.f &= 0x90; // Clear N and H flags
         self.reg.f ^= 0x10; // Toggle C flag
+    }
 
-        1
+    fn halt(&mut self) {
+        if !self.ime && self.pending_interrupts() != 0 {
+            // HALT bug: with IME cleared and an interrupt already pending, the CPU
+            // does not halt and the byte following HALT is read twice
+            // (see: https://gbdev.io/pandocs/halt.html#halt-bug)
+            self.halt_bug = true;
+        } else {
+            self.state = CpuState::Halted;
+        }
     }
 
-    fn set_ei(&mut self) -> u8 {
-        self.setei = 2;  // Enable interrupts after next instruction
-        1
+    fn stop(&mut self) {
+        self.read_byte(); // STOP is a 2 bytes instruction
+
+        if self.mmu.joypad_line_low() {
+            // A button is already held: STOP mode is not entered and the CPU halts instead
+            // (see: https://gbdev.io/pandocs/Reducing_Power_Consumption.html#the-bizarre-case-of-the-game-boy-stop-instruction-before-even-considering-timing)
+            self.state = CpuState::Halted;
+            return;
+        }
+
+        // The bus resets the divider, and performs the CGB speed switch if one is armed
+        if !self.mmu.stop() {
+            self.state = CpuState::Stopped;
+        }
     }
 
-    fn set_di(&mut self) -> u8 {
-        self.setdi = 2;  // Disable interrupts after next instruction
-        1
+    fn set_ei(&mut self) {
+        self.ime_scheduled = true; // Enable interrupts after next instruction
     }
 
-    fn call(&mut self, opcode: u8) -> u8 {
-        let cst = self.read_word();
-        match opcode {
-            0xC4 => self.conditional_call(!self.reg.get_flag(CpuFlag::Z), cst),
-            0xCC => self.conditional_call(self.reg.get_flag(CpuFlag::Z), cst),
-            0xD4 => self.conditional_call(!self.reg.get_flag(CpuFlag::C), cst),
-            0xDC => self.conditional_call(self.reg.get_flag(CpuFlag::C), cst),
-            0xCD => self.direct_call(cst),
-            _ => panic!("Not a CALL indstruction: 0x{:02X}", opcode),
-        }
+    fn set_di(&mut self) {
+        // Unlike EI, DI takes effect immediately
+        self.ime = false;
+        self.ime_scheduled = false;
     }
 
-    fn conditional_call(&mut self, condition: bool, addr: u16) -> u8 {
+    fn call(&mut self, info: &OpcodeInfo) {
+        // CALL n16 or CALL cc, n16
+        let condition = jumps::condition(self, info.operands[0]);
+        let addr = self.read_word();
         if condition {
             self.direct_call(addr);
-            return 6;
         }
-        3
     }
 
-    fn direct_call(&mut self, addr: u16) -> u8 {
+    fn direct_call(&mut self, addr: u16) {
+        // PC already points to the instruction following the call
+        self.tick_internal();
+        self.push_pc_and_jump(self.reg.pc, addr);
+    }
+
+    fn push_pc_and_jump(&mut self, return_addr: u16, addr: u16) {
         //TODO: rewrite using macro from stack.rs
-        // PUSH PC
-        let current_instr_addr = self.reg.pc - 1; // -1 because PC points to next instruction
-        self.mmu.tick_internal();
         self.reg.sp = self.reg.sp.wrapping_sub(1);
-        let high_addr = ((current_instr_addr & 0xFF00) >> 8) as u8;
-        self.mmu.write_byte(self.reg.sp, high_addr);
+        let high_addr = ((return_addr & 0xFF00) >> 8) as u8;
+        self.write_mem(self.reg.sp, high_addr);
 
         self.reg.sp = self.reg.sp.wrapping_sub(1);
-        let low_addr = (current_instr_addr & 0x00FF) as u8;
-        self.mmu.write_byte(self.reg.sp, low_addr);
+        let low_addr = (return_addr & 0x00FF) as u8;
+        self.write_mem(self.reg.sp, low_addr);
 
         // PC = ADDR
         self.reg.pc = addr;
-
-        4
     }
 
-    fn ret(&mut self, opcode: u8) -> u8 {
-        match opcode {
-            0xC9 => self.conditional_ret(true),
-            0xD9 => {
-                self.ime = true;
-                self.conditional_ret(true)
-            },
-            0xC0 => self.conditional_ret_initial_tick(!self.reg.get_flag(CpuFlag::Z)),
-            0xC8 => self.conditional_ret_initial_tick(self.reg.get_flag(CpuFlag::Z)),
-            0xD0 => self.conditional_ret_initial_tick(!self.reg.get_flag(CpuFlag::C)),
-            0xD8 => self.conditional_ret_initial_tick(self.reg.get_flag(CpuFlag::C)),
-            _ => panic!("Not a RET indstruction: 0x{:02X}", opcode),
+    fn ret(&mut self, info: &OpcodeInfo) {
+        if let Some(condition) = info.operands[0] {
+            // Evaluating the condition takes an extra cycle
+            self.tick_internal();
+            if !jumps::condition(self, Some(condition)) {
+                return;
+            }
         }
-    }
 
-    fn conditional_ret(&mut self, condition: bool) -> u8 {
-        if condition {
-            let low = self.mmu.read_byte(self.reg.sp) as u16;
-            self.reg.sp = self.reg.sp.wrapping_add(1);
+        let low = self.read_mem(self.reg.sp) as u16;
+        self.reg.sp = self.reg.sp.wrapping_add(1);
 
-            let high = self.mmu.read_byte(self.reg.sp) as u16;
-            self.reg.sp = self.reg.sp.wrapping_add(1);
+        let high = self.read_mem(self.reg.sp) as u16;
+        self.reg.sp = self.reg.sp.wrapping_add(1);
 
-            self.mmu.tick_internal();
-            self.reg.pc = (high << 8) | low;
-
-            self.prefetched = self.read_byte(); // Pre-fetch next instruction and increment PC
-
-            return 5;
-        }
-        2
+        self.tick_internal();
+        self.reg.pc = (high << 8) | low;
     }
+}
 
-    fn conditional_ret_initial_tick(&mut self, condition: bool) -> u8 {
-        self.mmu.tick_internal();
-        self.conditional_ret(condition)
-    }
-
-    fn rst(&mut self, opcode: u8) -> u8 {
-        let addr = match opcode {
-            0xC7 => 0x00,
-            0xCF => 0x08,
-            0xD7 => 0x10,
-            0xDF => 0x18,
-            0xE7 => 0x20,
-            0xEF => 0x28,
-            0xF7 => 0x30,
-            0xFF => 0x38,
-            _ => panic!("Not a RST instruction: 0x{:02X}", opcode),
-        };
+// Operands of well-formed table entries, for the instructions that always have them
+fn operand(operand: Option<Operand>) -> Operand {
+    operand.expect("missing operand in the opcode table")
+}
 
-        self.direct_call(addr)
+fn reg16(operand: Option<Operand>) -> Reg16 {
+    match operand {
+        Some(Operand::Reg16(reg)) => reg,
+        _ => unreachable!("not a 16-bit register: {:?}", operand),
     }
 }
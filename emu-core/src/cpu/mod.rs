@@ -1,4 +1,6 @@
+#[allow(clippy::module_inception)]
 pub mod cpu;
+pub mod opcodes;
 mod alu;
 mod jumps;
 mod ld;
@@ -0,0 +1,475 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mnemonic {
+    Nop,
+    Ld,
+    Ldh,
+    Inc,
+    Dec,
+    Add,
+    Adc,
+    Sub,
+    Sbc,
+    And,
+    Xor,
+    Or,
+    Cp,
+    Rlca,
+    Rrca,
+    Rla,
+    Rra,
+    Daa,
+    Cpl,
+    Scf,
+    Ccf,
+    Jr,
+    Jp,
+    Call,
+    Ret,
+    Reti,
+    Rst,
+    Push,
+    Pop,
+    Halt,
+    Stop,
+    Di,
+    Ei,
+    Prefix, // 0xCB, the actual instruction is described by CB_OPCODES
+    Rlc,
+    Rrc,
+    Rl,
+    Rr,
+    Sla,
+    Sra,
+    Swap,
+    Srl,
+    Bit,
+    Res,
+    Set,
+    Illegal, // unused opcode, which locks the CPU up
+}
+
+impl Mnemonic {
+    /// Returns the mnemonic in RGBDS syntax.
+    pub fn name(self) -> &'static str {
+        match self {
+            Mnemonic::Nop => "nop",
+            Mnemonic::Ld => "ld",
+            Mnemonic::Ldh => "ldh",
+            Mnemonic::Inc => "inc",
+            Mnemonic::Dec => "dec",
+            Mnemonic::Add => "add",
+            Mnemonic::Adc => "adc",
+            Mnemonic::Sub => "sub",
+            Mnemonic::Sbc => "sbc",
+            Mnemonic::And => "and",
+            Mnemonic::Xor => "xor",
+            Mnemonic::Or => "or",
+            Mnemonic::Cp => "cp",
+            Mnemonic::Rlca => "rlca",
+            Mnemonic::Rrca => "rrca",
+            Mnemonic::Rla => "rla",
+            Mnemonic::Rra => "rra",
+            Mnemonic::Daa => "daa",
+            Mnemonic::Cpl => "cpl",
+            Mnemonic::Scf => "scf",
+            Mnemonic::Ccf => "ccf",
+            Mnemonic::Jr => "jr",
+            Mnemonic::Jp => "jp",
+            Mnemonic::Call => "call",
+            Mnemonic::Ret => "ret",
+            Mnemonic::Reti => "reti",
+            Mnemonic::Rst => "rst",
+            Mnemonic::Push => "push",
+            Mnemonic::Pop => "pop",
+            Mnemonic::Halt => "halt",
+            Mnemonic::Stop => "stop",
+            Mnemonic::Di => "di",
+            Mnemonic::Ei => "ei",
+            Mnemonic::Prefix => "prefix",
+            Mnemonic::Rlc => "rlc",
+            Mnemonic::Rrc => "rrc",
+            Mnemonic::Rl => "rl",
+            Mnemonic::Rr => "rr",
+            Mnemonic::Sla => "sla",
+            Mnemonic::Sra => "sra",
+            Mnemonic::Swap => "swap",
+            Mnemonic::Srl => "srl",
+            Mnemonic::Bit => "bit",
+            Mnemonic::Res => "res",
+            Mnemonic::Set => "set",
+            Mnemonic::Illegal => "db",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg8 {
+    A,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+}
+
+impl Reg8 {
+    pub fn name(self) -> &'static str {
+        match self {
+            Reg8::A => "a",
+            Reg8::B => "b",
+            Reg8::C => "c",
+            Reg8::D => "d",
+            Reg8::E => "e",
+            Reg8::H => "h",
+            Reg8::L => "l",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reg16 {
+    AF,
+    BC,
+    DE,
+    HL,
+    SP,
+}
+
+impl Reg16 {
+    pub fn name(self) -> &'static str {
+        match self {
+            Reg16::AF => "af",
+            Reg16::BC => "bc",
+            Reg16::DE => "de",
+            Reg16::HL => "hl",
+            Reg16::SP => "sp",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    NZ,
+    Z,
+    NC,
+    C,
+}
+
+impl Condition {
+    pub fn name(self) -> &'static str {
+        match self {
+            Condition::NZ => "nz",
+            Condition::Z => "z",
+            Condition::NC => "nc",
+            Condition::C => "c",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    Reg8(Reg8),
+    Reg16(Reg16),
+    Indirect(Reg16), // [bc], [de] or [hl]
+    HlInc,           // [hl+]
+    HlDec,           // [hl-]
+    HighC,           // [c], that is 0xFF00 + C
+    Imm8,            // n8
+    Imm16,           // n16
+    Offset8,         // e8, a signed offset (JR, ADD SP)
+    SpOffset8,       // sp + e8
+    Addr16,          // [n16]
+    HighAddr8,       // [n8], that is 0xFF00 + n8
+    Cond(Condition),
+    Bit(u8),
+    Vector(u8), // RST target
+}
+
+impl Operand {
+    /// Returns the number of immediate bytes following the opcode for this operand.
+    pub const fn immediate_len(self) -> u8 {
+        match self {
+            Operand::Imm8 | Operand::Offset8 | Operand::SpOffset8 | Operand::HighAddr8 => 1,
+            Operand::Imm16 | Operand::Addr16 => 2,
+            _ => 0,
+        }
+    }
+}
+
+/// How an instruction affects one flag
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlagEffect {
+    Unaffected,
+    Reset,
+    Set,
+    Affected, // depends on the result
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FlagEffects {
+    pub z: FlagEffect,
+    pub n: FlagEffect,
+    pub h: FlagEffect,
+    pub c: FlagEffect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpcodeInfo {
+    pub mnemonic: Mnemonic,
+    pub operands: [Option<Operand>; 2],
+    pub length: u8,       // in bytes, including the opcode (and the 0xCB prefix)
+    pub cycles: u8,       // in M-cycles, including the opcode fetch (branch not taken)
+    pub cycles_taken: u8, // in M-cycles, when a conditional branch is taken
+    pub flags: FlagEffects,
+}
+
+impl OpcodeInfo {
+    /// Iterates over the operands of the instruction.
+    pub fn operands(&self) -> impl Iterator<Item = Operand> + '_ {
+        self.operands.iter().flatten().copied()
+    }
+}
+
+/// Metadata of every opcode, shared by the CPU (which dispatches through it), the disassembler and debuggers.
+/// The tables are built at compile time from the regular structure of the SM83 instruction set
+/// (see: https://gbdev.io/gb-opcodes/optables/ and https://rgbds.gbdev.io/docs/gbz80.7).
+pub static OPCODES: [OpcodeInfo; 256] = build_table(false);
+
+/// Instructions prefixed by 0xCB (lengths and cycles include the prefix)
+pub static CB_OPCODES: [OpcodeInfo; 256] = build_table(true);
+
+const fn build_table(cb: bool) -> [OpcodeInfo; 256] {
+    let mut table = [op(Mnemonic::Illegal, None, None, 1, b"----"); 256];
+    let mut opcode = 0;
+    while opcode < 256 {
+        table[opcode] = if cb { decode_cb(opcode as u8) } else { decode(opcode as u8) };
+        opcode += 1;
+    }
+    table
+}
+
+// Flag effects, written like in the opcode tables: "Z0H-" means Z and H depend on the result,
+// N is reset and C is left untouched
+const fn flags(spec: &[u8; 4]) -> FlagEffects {
+    const fn effect(c: u8) -> FlagEffect {
+        match c {
+            b'-' => FlagEffect::Unaffected,
+            b'0' => FlagEffect::Reset,
+            b'1' => FlagEffect::Set,
+            _ => FlagEffect::Affected,
+        }
+    }
+    FlagEffects { z: effect(spec[0]), n: effect(spec[1]), h: effect(spec[2]), c: effect(spec[3]) }
+}
+
+const fn op(mnemonic: Mnemonic, a: Option<Operand>, b: Option<Operand>, cycles: u8, spec: &[u8; 4]) -> OpcodeInfo {
+    let mut length = 1;
+    if let Some(a) = a {
+        length += a.immediate_len();
+    }
+    if let Some(b) = b {
+        length += b.immediate_len();
+    }
+    OpcodeInfo { mnemonic, operands: [a, b], length, cycles, cycles_taken: cycles, flags: flags(spec) }
+}
+
+const fn op0(mnemonic: Mnemonic, cycles: u8, spec: &[u8; 4]) -> OpcodeInfo {
+    op(mnemonic, None, None, cycles, spec)
+}
+
+const fn op1(mnemonic: Mnemonic, a: Operand, cycles: u8, spec: &[u8; 4]) -> OpcodeInfo {
+    op(mnemonic, Some(a), None, cycles, spec)
+}
+
+const fn op2(mnemonic: Mnemonic, a: Operand, b: Operand, cycles: u8, spec: &[u8; 4]) -> OpcodeInfo {
+    op(mnemonic, Some(a), Some(b), cycles, spec)
+}
+
+const fn taken(mut info: OpcodeInfo, cycles_taken: u8) -> OpcodeInfo {
+    info.cycles_taken = cycles_taken;
+    info
+}
+
+// Opcodes are split as xx yyy zzz, with yyy = ppq
+
+// 8-bit operand encoded in 3 bits: B, C, D, E, H, L, [HL], A
+const fn r(i: u8) -> Operand {
+    match i & 7 {
+        0 => Operand::Reg8(Reg8::B),
+        1 => Operand::Reg8(Reg8::C),
+        2 => Operand::Reg8(Reg8::D),
+        3 => Operand::Reg8(Reg8::E),
+        4 => Operand::Reg8(Reg8::H),
+        5 => Operand::Reg8(Reg8::L),
+        6 => Operand::Indirect(Reg16::HL),
+        _ => Operand::Reg8(Reg8::A),
+    }
+}
+
+const fn is_hl(i: u8) -> bool {
+    i & 7 == 6
+}
+
+// 16-bit register encoded in 2 bits, with SP (loads and arithmetic) or AF (stack)
+const fn rp(p: u8, with_af: bool) -> Operand {
+    Operand::Reg16(match p & 3 {
+        0 => Reg16::BC,
+        1 => Reg16::DE,
+        2 => Reg16::HL,
+        _ if with_af => Reg16::AF,
+        _ => Reg16::SP,
+    })
+}
+
+const fn cc(i: u8) -> Operand {
+    Operand::Cond(match i & 3 {
+        0 => Condition::NZ,
+        1 => Condition::Z,
+        2 => Condition::NC,
+        _ => Condition::C,
+    })
+}
+
+const A: Operand = Operand::Reg8(Reg8::A);
+const HL: Operand = Operand::Reg16(Reg16::HL);
+const SP: Operand = Operand::Reg16(Reg16::SP);
+
+// ALU operation on A, encoded in 3 bits
+const fn alu(y: u8, src: Operand, cycles: u8) -> OpcodeInfo {
+    match y & 7 {
+        0 => op2(Mnemonic::Add, A, src, cycles, b"Z0HC"),
+        1 => op2(Mnemonic::Adc, A, src, cycles, b"Z0HC"),
+        2 => op2(Mnemonic::Sub, A, src, cycles, b"Z1HC"),
+        3 => op2(Mnemonic::Sbc, A, src, cycles, b"Z1HC"),
+        4 => op2(Mnemonic::And, A, src, cycles, b"Z010"),
+        5 => op2(Mnemonic::Xor, A, src, cycles, b"Z000"),
+        6 => op2(Mnemonic::Or, A, src, cycles, b"Z000"),
+        _ => op2(Mnemonic::Cp, A, src, cycles, b"Z1HC"),
+    }
+}
+
+const fn decode(opcode: u8) -> OpcodeInfo {
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 7;
+    let z = opcode & 7;
+    let p = y >> 1;
+    let q = y & 1;
+
+    match (x, z) {
+        (0, 0) => match y {
+            0 => op0(Mnemonic::Nop, 1, b"----"),
+            1 => op2(Mnemonic::Ld, Operand::Addr16, SP, 5, b"----"),
+            2 => op1(Mnemonic::Stop, Operand::Imm8, 1, b"----"),
+            3 => op1(Mnemonic::Jr, Operand::Offset8, 3, b"----"),
+            _ => taken(op2(Mnemonic::Jr, cc(y - 4), Operand::Offset8, 2, b"----"), 3),
+        },
+        (0, 1) if q == 0 => op2(Mnemonic::Ld, rp(p, false), Operand::Imm16, 3, b"----"),
+        (0, 1) => op2(Mnemonic::Add, HL, rp(p, false), 2, b"-0HC"),
+        (0, 2) => {
+            let mem = match p {
+                0 => Operand::Indirect(Reg16::BC),
+                1 => Operand::Indirect(Reg16::DE),
+                2 => Operand::HlInc,
+                _ => Operand::HlDec,
+            };
+            if q == 0 {
+                op2(Mnemonic::Ld, mem, A, 2, b"----")
+            } else {
+                op2(Mnemonic::Ld, A, mem, 2, b"----")
+            }
+        },
+        (0, 3) if q == 0 => op1(Mnemonic::Inc, rp(p, false), 2, b"----"),
+        (0, 3) => op1(Mnemonic::Dec, rp(p, false), 2, b"----"),
+        (0, 4) => op1(Mnemonic::Inc, r(y), if is_hl(y) { 3 } else { 1 }, b"Z0H-"),
+        (0, 5) => op1(Mnemonic::Dec, r(y), if is_hl(y) { 3 } else { 1 }, b"Z1H-"),
+        (0, 6) => op2(Mnemonic::Ld, r(y), Operand::Imm8, if is_hl(y) { 3 } else { 2 }, b"----"),
+        (0, _) => match y {
+            0 => op0(Mnemonic::Rlca, 1, b"000C"),
+            1 => op0(Mnemonic::Rrca, 1, b"000C"),
+            2 => op0(Mnemonic::Rla, 1, b"000C"),
+            3 => op0(Mnemonic::Rra, 1, b"000C"),
+            4 => op0(Mnemonic::Daa, 1, b"Z-0C"),
+            5 => op0(Mnemonic::Cpl, 1, b"-11-"),
+            6 => op0(Mnemonic::Scf, 1, b"-001"),
+            _ => op0(Mnemonic::Ccf, 1, b"-00C"),
+        },
+
+        (1, 6) if y == 6 => op0(Mnemonic::Halt, 1, b"----"),
+        (1, _) => op2(Mnemonic::Ld, r(y), r(z), if is_hl(y) || is_hl(z) { 2 } else { 1 }, b"----"),
+
+        (2, _) => alu(y, r(z), if is_hl(z) { 2 } else { 1 }),
+
+        (_, 0) => match y {
+            0..=3 => taken(op1(Mnemonic::Ret, cc(y), 2, b"----"), 5),
+            4 => op2(Mnemonic::Ldh, Operand::HighAddr8, A, 3, b"----"),
+            5 => op2(Mnemonic::Add, SP, Operand::Offset8, 4, b"00HC"),
+            6 => op2(Mnemonic::Ldh, A, Operand::HighAddr8, 3, b"----"),
+            _ => op2(Mnemonic::Ld, HL, Operand::SpOffset8, 3, b"00HC"),
+        },
+        (_, 1) if q == 0 => {
+            let spec = if p == 3 { b"ZNHC" } else { b"----" }; // POP AF loads the flags
+            op1(Mnemonic::Pop, rp(p, true), 3, spec)
+        },
+        (_, 1) => match p {
+            0 => op0(Mnemonic::Ret, 4, b"----"),
+            1 => op0(Mnemonic::Reti, 4, b"----"),
+            2 => op1(Mnemonic::Jp, HL, 1, b"----"),
+            _ => op2(Mnemonic::Ld, SP, HL, 2, b"----"),
+        },
+        (_, 2) => match y {
+            0..=3 => taken(op2(Mnemonic::Jp, cc(y), Operand::Imm16, 3, b"----"), 4),
+            4 => op2(Mnemonic::Ldh, Operand::HighC, A, 2, b"----"),
+            5 => op2(Mnemonic::Ld, Operand::Addr16, A, 4, b"----"),
+            6 => op2(Mnemonic::Ldh, A, Operand::HighC, 2, b"----"),
+            _ => op2(Mnemonic::Ld, A, Operand::Addr16, 4, b"----"),
+        },
+        (_, 3) => match y {
+            0 => op1(Mnemonic::Jp, Operand::Imm16, 4, b"----"),
+            1 => op0(Mnemonic::Prefix, 1, b"----"),
+            6 => op0(Mnemonic::Di, 1, b"----"),
+            7 => op0(Mnemonic::Ei, 1, b"----"),
+            _ => op0(Mnemonic::Illegal, 1, b"----"),
+        },
+        (_, 4) => match y {
+            0..=3 => taken(op2(Mnemonic::Call, cc(y), Operand::Imm16, 3, b"----"), 6),
+            _ => op0(Mnemonic::Illegal, 1, b"----"),
+        },
+        (_, 5) if q == 0 => op1(Mnemonic::Push, rp(p, true), 4, b"----"),
+        (_, 5) => match p {
+            0 => op1(Mnemonic::Call, Operand::Imm16, 6, b"----"),
+            _ => op0(Mnemonic::Illegal, 1, b"----"),
+        },
+        (_, 6) => alu(y, Operand::Imm8, 2),
+        _ => op1(Mnemonic::Rst, Operand::Vector(y * 8), 4, b"----"),
+    }
+}
+
+const fn decode_cb(opcode: u8) -> OpcodeInfo {
+    let x = opcode >> 6;
+    let y = (opcode >> 3) & 7;
+    let z = opcode & 7;
+
+    // Register operations take 2 M-cycles (prefix and opcode fetches), [HL] ones also read and write it back
+    let mut info = match x {
+        0 => {
+            let (mnemonic, spec) = match y {
+                0 => (Mnemonic::Rlc, b"Z00C"),
+                1 => (Mnemonic::Rrc, b"Z00C"),
+                2 => (Mnemonic::Rl, b"Z00C"),
+                3 => (Mnemonic::Rr, b"Z00C"),
+                4 => (Mnemonic::Sla, b"Z00C"),
+                5 => (Mnemonic::Sra, b"Z00C"),
+                6 => (Mnemonic::Swap, b"Z000"),
+                _ => (Mnemonic::Srl, b"Z00C"),
+            };
+            op1(mnemonic, r(z), if is_hl(z) { 4 } else { 2 }, spec)
+        },
+        // BIT only reads its operand
+        1 => op2(Mnemonic::Bit, Operand::Bit(y), r(z), if is_hl(z) { 3 } else { 2 }, b"Z01-"),
+        2 => op2(Mnemonic::Res, Operand::Bit(y), r(z), if is_hl(z) { 4 } else { 2 }, b"----"),
+        _ => op2(Mnemonic::Set, Operand::Bit(y), r(z), if is_hl(z) { 4 } else { 2 }, b"----"),
+    };
+    info.length = 2;
+    info
+}
@@ -1,3 +1,5 @@
+use crate::cpu::opcodes::{Reg16, Reg8};
+
 pub struct Registers {
     // 8 bits registers
     pub a: u8,
@@ -97,6 +99,50 @@ impl Registers {
         self.sp = val;
     }
 
+    pub fn get8(&self, reg: Reg8) -> u8 {
+        match reg {
+            Reg8::A => self.a,
+            Reg8::B => self.b,
+            Reg8::C => self.c,
+            Reg8::D => self.d,
+            Reg8::E => self.e,
+            Reg8::H => self.h,
+            Reg8::L => self.l,
+        }
+    }
+
+    pub fn set8(&mut self, reg: Reg8, val: u8) {
+        match reg {
+            Reg8::A => self.a = val,
+            Reg8::B => self.b = val,
+            Reg8::C => self.c = val,
+            Reg8::D => self.d = val,
+            Reg8::E => self.e = val,
+            Reg8::H => self.h = val,
+            Reg8::L => self.l = val,
+        }
+    }
+
+    pub fn get16(&self, reg: Reg16) -> u16 {
+        match reg {
+            Reg16::AF => self.af(),
+            Reg16::BC => self.bc(),
+            Reg16::DE => self.de(),
+            Reg16::HL => self.hl(),
+            Reg16::SP => self.sp,
+        }
+    }
+
+    pub fn set16(&mut self, reg: Reg16, val: u16) {
+        match reg {
+            Reg16::AF => self.set_af(val),
+            Reg16::BC => self.set_bc(val),
+            Reg16::DE => self.set_de(val),
+            Reg16::HL => self.set_hl(val),
+            Reg16::SP => self.sp = val,
+        }
+    }
+
     pub fn clear_flags(&mut self) {
         self.f = 0;
     }
@@ -105,6 +151,11 @@ impl Registers {
         (self.f & (flag as u8)) != 0
     }
 
+    /// Sets all the flags at once.
+    pub fn set_flags(&mut self, z: bool, n: bool, h: bool, c: bool) {
+        self.f = (z as u8) << 7 | (n as u8) << 6 | (h as u8) << 5 | (c as u8) << 4;
+    }
+
     pub fn set_flag(&mut self, flag: CpuFlag, condition: bool) {
         if condition {
             self.f |= flag as u8;
@@ -138,18 +189,10 @@ pub fn sub8_needs_carry(a: u8, b: u8) -> bool {
    a < b
 }
 
-pub fn sub16_needs_carry(a: u16, b: u16) -> bool {
-    a < b
-}
-
 pub fn sub8_needs_half_carry(a: u8, b: u8) -> bool {
     (a & 0x0F) < (b & 0x0F)
 }
 
-pub fn sub16_needs_half_carry(a: u16, b: u16) -> bool {
-    (a & 0x0FFF) < (b & 0x0FFF)
-}
-
 pub fn adc_needs_carry(a: u8, b: u8, carry: u8) -> bool {
     (a as u16 + b as u16 + carry as u16) > 0xFF
 }
@@ -0,0 +1,166 @@
+use std::fmt;
+
+use crate::cpu::opcodes::{Condition, Mnemonic, OpcodeInfo, Operand, Reg16, Reg8, CB_OPCODES, OPCODES};
+use crate::memory::MemoryBus;
+
+/// An operand of a decoded instruction, with its immediate value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Arg {
+    Reg8(Reg8),
+    Reg16(Reg16),
+    Indirect(Reg16), // [bc], [de] or [hl]
+    HlInc,           // [hl+]
+    HlDec,           // [hl-]
+    HighC,           // [c], that is 0xFF00 + C
+    Imm8(u8),
+    Imm16(u16),
+    Offset8(i8),   // ADD SP, e8
+    SpOffset8(i8), // sp + e8
+    Addr16(u16),   // [n16]
+    HighAddr8(u8), // [n8], that is 0xFF00 + n8
+    Target(u16),   // JR destination, resolved from its relative offset
+    Cond(Condition),
+    Bit(u8),
+    Vector(u8),
+}
+
+/// A decoded instruction (see `decode`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    pub addr: u16,
+    pub bank: Option<u16>, // ROM bank the instruction was read from, if it is in ROM
+    pub bytes: [u8; 3],    // the first `length` bytes are the encoded instruction
+    pub length: u8,
+    pub mnemonic: Mnemonic,
+    pub operands: [Option<Arg>; 2],
+    pub info: &'static OpcodeInfo,
+}
+
+impl Instruction {
+    /// Returns the encoded instruction.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..self.length as usize]
+    }
+
+    /// Iterates over the operands of the instruction.
+    pub fn operands(&self) -> impl Iterator<Item = Arg> + '_ {
+        self.operands.iter().flatten().copied()
+    }
+
+    /// Returns the address of the next instruction in memory.
+    pub fn next_addr(&self) -> u16 {
+        self.addr.wrapping_add(self.length as u16)
+    }
+}
+
+/// Decodes the instruction at `addr`, without any side effect on the bus.
+pub fn decode<M: MemoryBus + ?Sized>(bus: &M, addr: u16) -> Instruction {
+    let mut bytes = [0; 3];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = bus.peek_byte(addr.wrapping_add(i as u16));
+    }
+
+    let info = if bytes[0] == 0xCB { &CB_OPCODES[bytes[1] as usize] } else { &OPCODES[bytes[0] as usize] };
+
+    // Immediate values follow the opcode, in the order of the operands
+    let mut imm = &bytes[1..info.length as usize];
+    let mut operands = [None; 2];
+    for (slot, operand) in operands.iter_mut().zip(info.operands) {
+        let Some(operand) = operand else { break };
+        if info.mnemonic == Mnemonic::Stop {
+            break; // the byte following STOP is not an actual operand
+        }
+        let (arg, rest) = decode_operand(info.mnemonic, operand, imm, addr.wrapping_add(info.length as u16));
+        *slot = Some(arg);
+        imm = rest;
+    }
+
+    Instruction {
+        addr,
+        bank: bus.rom_bank(addr),
+        bytes,
+        length: info.length,
+        mnemonic: info.mnemonic,
+        operands,
+        info,
+    }
+}
+
+// Decodes an operand from the immediate bytes, and returns the remaining ones
+fn decode_operand(mnemonic: Mnemonic, operand: Operand, imm: &[u8], next_addr: u16) -> (Arg, &[u8]) {
+    let byte = || imm[0];
+    let word = || u16::from_le_bytes([imm[0], imm[1]]);
+    let arg = match operand {
+        Operand::Reg8(reg) => Arg::Reg8(reg),
+        Operand::Reg16(reg) => Arg::Reg16(reg),
+        Operand::Indirect(reg) => Arg::Indirect(reg),
+        Operand::HlInc => Arg::HlInc,
+        Operand::HlDec => Arg::HlDec,
+        Operand::HighC => Arg::HighC,
+        Operand::Imm8 => Arg::Imm8(byte()),
+        Operand::Imm16 => Arg::Imm16(word()),
+        // Relative jumps are shown with their absolute destination, like assemblers expect them
+        Operand::Offset8 if mnemonic == Mnemonic::Jr => Arg::Target(next_addr.wrapping_add(byte() as i8 as u16)),
+        Operand::Offset8 => Arg::Offset8(byte() as i8),
+        Operand::SpOffset8 => Arg::SpOffset8(byte() as i8),
+        Operand::Addr16 => Arg::Addr16(word()),
+        Operand::HighAddr8 => Arg::HighAddr8(byte()),
+        Operand::Cond(cond) => Arg::Cond(cond),
+        Operand::Bit(bit) => Arg::Bit(bit),
+        Operand::Vector(vector) => Arg::Vector(vector),
+    };
+    (arg, &imm[operand.immediate_len() as usize..])
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Arg::Reg8(reg) => write!(f, "{}", reg.name()),
+            Arg::Reg16(reg) => write!(f, "{}", reg.name()),
+            Arg::Indirect(reg) => write!(f, "[{}]", reg.name()),
+            Arg::HlInc => write!(f, "[hl+]"),
+            Arg::HlDec => write!(f, "[hl-]"),
+            Arg::HighC => write!(f, "[c]"),
+            Arg::Imm8(val) => write!(f, "${:02X}", val),
+            Arg::Imm16(val) | Arg::Target(val) => write!(f, "${:04X}", val),
+            Arg::Offset8(offset) => write!(f, "{}", offset),
+            Arg::SpOffset8(offset) if offset < 0 => write!(f, "sp{}", offset),
+            Arg::SpOffset8(offset) => write!(f, "sp+{}", offset),
+            Arg::Addr16(addr) => write!(f, "[${:04X}]", addr),
+            Arg::HighAddr8(addr) => write!(f, "[$FF{:02X}]", addr),
+            Arg::Cond(cond) => write!(f, "{}", cond.name()),
+            Arg::Bit(bit) => write!(f, "{}", bit),
+            Arg::Vector(vector) => write!(f, "${:02X}", vector),
+        }
+    }
+}
+
+/// Formats the instruction in RGBDS syntax, such as `ld a, [hl+]` or `jr nz, $0150`.
+/// The alternate form (`{:#}`) prefixes it with its location, as `bank:addr` when the
+/// bank is known (`01:4000 call $0150`) and `addr` otherwise.
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            match self.bank {
+                Some(bank) => write!(f, "{:02X}:{:04X} ", bank, self.addr)?,
+                None => write!(f, "{:04X} ", self.addr)?,
+            }
+        }
+
+        if self.mnemonic == Mnemonic::Illegal {
+            return write!(f, "db ${:02X}", self.bytes[0]);
+        }
+
+        write!(f, "{}", self.mnemonic.name())?;
+        // The table lists A as the destination of all ALU instructions, but it is
+        // conventionally omitted except for ADD, ADC and SBC
+        let skip = match (self.mnemonic, self.operands[0]) {
+            (Mnemonic::Sub | Mnemonic::And | Mnemonic::Xor | Mnemonic::Or | Mnemonic::Cp, Some(Arg::Reg8(Reg8::A))) => 1,
+            _ => 0,
+        };
+        for (i, arg) in self.operands().skip(skip).enumerate() {
+            write!(f, "{}{}", if i == 0 { " " } else { ", " }, arg)?;
+        }
+        Ok(())
+    }
+}
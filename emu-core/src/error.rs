@@ -0,0 +1,192 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmuError {
+    // The memory bus has nothing mapped at this address
+    UnmappedAddress(u16),
+}
+
+impl fmt::Display for EmuError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EmuError::UnmappedAddress(addr) => write!(f, "Address 0x{:04X} is not mapped", addr),
+        }
+    }
+}
+
+impl std::error::Error for EmuError {}
+
+/// Problems found in a cartridge header (see `cartridge::Header`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderError {
+    // The ROM is too short to contain a header (its length is given)
+    Truncated(usize),
+    // The Nintendo logo is missing: the boot ROM would lock up
+    BadLogo,
+    HeaderChecksum { expected: u8, computed: u8 },
+    UnknownCartridgeType(u8),
+    UnknownRomSize(u8),
+    UnknownRamSize(u8),
+    // The ROM size in the header doesn't match the actual one (in bytes)
+    RomSizeMismatch { header: usize, actual: usize },
+    // The RAM size doesn't match the presence of RAM in the cartridge type
+    RamSizeMismatch { cartridge_type: u8, ram_size: u8 },
+}
+
+impl fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderError::Truncated(len) => write!(f, "ROM is too short to contain a header ({} bytes)", len),
+            HeaderError::BadLogo => write!(f, "Nintendo logo is missing from the header"),
+            HeaderError::HeaderChecksum { expected, computed } => {
+                write!(f, "Header checksum is 0x{:02X}, but the header sums to 0x{:02X}", expected, computed)
+            },
+            HeaderError::UnknownCartridgeType(code) => write!(f, "Unknown cartridge type 0x{:02X}", code),
+            HeaderError::UnknownRomSize(code) => write!(f, "Unknown ROM size code 0x{:02X}", code),
+            HeaderError::UnknownRamSize(code) => write!(f, "Unknown RAM size code 0x{:02X}", code),
+            HeaderError::RomSizeMismatch { header, actual } => {
+                write!(f, "Header declares a {} bytes ROM, but it is {} bytes", header, actual)
+            },
+            HeaderError::RamSizeMismatch { cartridge_type, ram_size } => {
+                write!(f, "RAM size code 0x{:02X} does not match cartridge type 0x{:02X}", ram_size, cartridge_type)
+            },
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {}
+
+/// Problems with an image supplied to the camera (see `cartridge::camera::CameraImage`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ImageError {
+    // The file could not be read (the I/O error is given)
+    Io(String),
+    // The PNG data could not be decoded (the decoder error is given)
+    Png(String),
+    // The number of pixels doesn't match the dimensions
+    SizeMismatch { width: usize, height: usize, len: usize },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(err) => write!(f, "Could not read image: {}", err),
+            ImageError::Png(err) => write!(f, "Could not decode PNG image: {}", err),
+            ImageError::SizeMismatch { width, height, len } => {
+                write!(f, "A {}x{} image needs {} pixels, but {} were given", width, height, width * height, len)
+            },
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+/// Problems found when restoring a save state (see `cartridge::state::StateReader`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateError {
+    // The state ends before everything was read
+    Truncated,
+    // A memory in the state doesn't have the size of the emulated one (in bytes)
+    SizeMismatch { expected: usize, actual: usize },
+    // Extra bytes were left after the state (their number is given)
+    TrailingData(usize),
+    // A register in the state holds a value the emulated hardware can't (the value is given)
+    InvalidValue(u8),
+}
+
+impl fmt::Display for StateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StateError::Truncated => write!(f, "Save state is truncated"),
+            StateError::SizeMismatch { expected, actual } => {
+                write!(f, "Save state holds a {} bytes memory, but {} bytes were expected", actual, expected)
+            },
+            StateError::TrailingData(len) => write!(f, "Save state has {} unexpected bytes at the end", len),
+            StateError::InvalidValue(val) => write!(f, "Save state holds an invalid register value: 0x{:02X}", val),
+        }
+    }
+}
+
+impl std::error::Error for StateError {}
+
+/// Problems found when applying an IPS, UPS or BPS patch (see `rom::patch`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PatchError {
+    // The patch doesn't start with the magic of a supported format
+    UnknownFormat,
+    // The patch ends in the middle of a record
+    Truncated,
+    // A record reads or writes outside of the ROM (the offset is given)
+    OutOfBounds(usize),
+    // The ROM isn't the one the patch was made for (UPS and BPS)
+    SourceSize { expected: usize, actual: usize },
+    SourceChecksum { expected: u32, computed: u32 },
+    // The patched ROM isn't the expected one, or the patch is corrupted (UPS and BPS)
+    TargetSize { expected: usize, actual: usize },
+    TargetChecksum { expected: u32, computed: u32 },
+    PatchChecksum { expected: u32, computed: u32 },
+    // The patched ROM would be larger than any Game Boy ROM (the size given by the patch is given)
+    TargetTooLarge(usize),
+}
+
+impl fmt::Display for PatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatchError::UnknownFormat => write!(f, "Patch is not an IPS, UPS or BPS patch"),
+            PatchError::Truncated => write!(f, "Patch is truncated"),
+            PatchError::OutOfBounds(offset) => write!(f, "Patch accesses offset 0x{:X}, outside of the ROM", offset),
+            PatchError::SourceSize { expected, actual } => {
+                write!(f, "Patch applies to a {} bytes ROM, but it is {} bytes", expected, actual)
+            },
+            PatchError::SourceChecksum { expected, computed } => {
+                write!(f, "Patch applies to a ROM with CRC32 {:08X}, but it is {:08X}", expected, computed)
+            },
+            PatchError::TargetSize { expected, actual } => {
+                write!(f, "Patched ROM should be {} bytes, but it is {} bytes", expected, actual)
+            },
+            PatchError::TargetChecksum { expected, computed } => {
+                write!(f, "Patched ROM should have CRC32 {:08X}, but it has {:08X}", expected, computed)
+            },
+            PatchError::PatchChecksum { expected, computed } => {
+                write!(f, "Patch should have CRC32 {:08X}, but it has {:08X}", expected, computed)
+            },
+            PatchError::TargetTooLarge(size) => write!(f, "Patched ROM would be {} bytes, more than 8 MiB", size),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {}
+
+/// Problems found when loading a ROM (see `rom::load`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RomError {
+    // A file could not be read (the I/O error is given)
+    Io(String),
+    // The archive could not be decompressed (the decoder error is given)
+    Archive(String),
+    // The zip archive holds no .gb or .gbc file
+    NoRomInArchive,
+    // The archive decompresses to more than any Game Boy ROM
+    TooLarge,
+    Patch(PatchError),
+}
+
+impl fmt::Display for RomError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RomError::Io(err) => write!(f, "Could not read ROM: {}", err),
+            RomError::Archive(err) => write!(f, "Could not decompress ROM: {}", err),
+            RomError::NoRomInArchive => write!(f, "Archive contains no .gb or .gbc file"),
+            RomError::TooLarge => write!(f, "Archive contains a file larger than any ROM"),
+            RomError::Patch(err) => write!(f, "Could not patch ROM: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for RomError {}
+
+impl From<PatchError> for RomError {
+    fn from(err: PatchError) -> Self {
+        RomError::Patch(err)
+    }
+}
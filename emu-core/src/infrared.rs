@@ -0,0 +1,51 @@
+/// Other end of an infrared link: another console, a remote, a toy...
+pub trait InfraredLink {
+    /// Called when our LED is turned on or off.
+    fn set_led(&mut self, on: bool);
+
+    /// Returns true while light from the other end is received.
+    fn receiving(&self) -> bool;
+}
+
+/// Infrared LED and sensor, as found on the CGB (RP register) and on some cartridges.
+/// Nothing is received while no link is connected.
+pub struct InfraredPort {
+    led: bool,
+    link: Option<Box<dyn InfraredLink>>,
+}
+
+impl InfraredPort {
+    pub fn new() -> Self {
+        Self { led: false, link: None }
+    }
+
+    /// Connects the other end of the link, replacing any previous one.
+    pub fn connect(&mut self, mut link: Box<dyn InfraredLink>) {
+        link.set_led(self.led);
+        self.link = Some(link);
+    }
+
+    pub fn led(&self) -> bool {
+        self.led
+    }
+
+    pub fn set_led(&mut self, on: bool) {
+        if on != self.led {
+            self.led = on;
+            if let Some(link) = &mut self.link {
+                link.set_led(on);
+            }
+        }
+    }
+
+    /// Returns true while the sensor receives light.
+    pub fn receiving(&self) -> bool {
+        self.link.as_ref().is_some_and(|link| link.receiving())
+    }
+}
+
+impl Default for InfraredPort {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,101 @@
+/// Interrupt sources, by decreasing priority
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    VBlank,
+    LcdStat,
+    Timer,
+    Serial,
+    Joypad,
+}
+
+impl Interrupt {
+    const ALL: [Interrupt; 5] = [
+        Interrupt::VBlank,
+        Interrupt::LcdStat,
+        Interrupt::Timer,
+        Interrupt::Serial,
+        Interrupt::Joypad,
+    ];
+
+    /// Returns the mask of this interrupt in IE and IF.
+    pub fn mask(self) -> u8 {
+        match self {
+            Interrupt::VBlank => 0x01,
+            Interrupt::LcdStat => 0x02,
+            Interrupt::Timer => 0x04,
+            Interrupt::Serial => 0x08,
+            Interrupt::Joypad => 0x10,
+        }
+    }
+
+    /// Returns the address of this interrupt's handler.
+    pub fn vector(self) -> u16 {
+        match self {
+            Interrupt::VBlank => 0x40,
+            Interrupt::LcdStat => 0x48,
+            Interrupt::Timer => 0x50,
+            Interrupt::Serial => 0x58,
+            Interrupt::Joypad => 0x60,
+        }
+    }
+}
+
+/// Owns the IF (0xFF0F) and IE (0xFFFF) registers (see: https://gbdev.io/pandocs/Interrupts.html).
+/// Peripherals request interrupts here, and the CPU picks the ones to dispatch.
+pub struct InterruptController {
+    if_reg: u8, // 0xFF0F - Interrupt Flag
+    ie_reg: u8, // 0xFFFF - Interrupt Enable
+}
+
+impl InterruptController {
+    pub fn new() -> Self {
+        Self {
+            if_reg: 0,
+            ie_reg: 0,
+        }
+    }
+
+    /// Requests an interrupt, by setting its bit in IF.
+    pub fn request(&mut self, interrupt: Interrupt) {
+        self.if_reg |= interrupt.mask();
+    }
+
+    /// Clears the request of an interrupt being dispatched.
+    pub fn acknowledge(&mut self, interrupt: Interrupt) {
+        self.if_reg &= !interrupt.mask();
+    }
+
+    /// Returns the interrupts that are both enabled and requested.
+    pub fn pending(&self) -> u8 {
+        self.ie_reg & self.if_reg & 0x1F
+    }
+
+    /// Returns the pending interrupt with the highest priority, if any.
+    pub fn highest_pending(&self) -> Option<Interrupt> {
+        let pending = self.pending();
+        Interrupt::ALL.into_iter().find(|interrupt| pending & interrupt.mask() != 0)
+    }
+
+    /// Reads IF, whose upper 3 bits are unused and read as 1.
+    pub fn read_if(&self) -> u8 {
+        self.if_reg | 0xE0
+    }
+
+    pub fn write_if(&mut self, val: u8) {
+        self.if_reg = val & 0x1F;
+    }
+
+    pub fn read_ie(&self) -> u8 {
+        self.ie_reg
+    }
+
+    pub fn write_ie(&mut self, val: u8) {
+        self.ie_reg = val;
+    }
+}
+
+impl Default for InterruptController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,89 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    Right,
+    Left,
+    Up,
+    Down,
+    A,
+    B,
+    Select,
+    Start,
+}
+
+/// P1/JOYP register (see: https://gbdev.io/pandocs/Joypad_Input.html)
+pub struct Joypad {
+    select: u8,  // bits 4-5 of P1 (0 = selected)
+    dpad: u8,    // pressed direction buttons (1 = pressed)
+    buttons: u8, // pressed action buttons (1 = pressed)
+}
+
+impl Joypad {
+    pub fn new() -> Self {
+        Self {
+            select: 0x30,
+            dpad: 0,
+            buttons: 0,
+        }
+    }
+
+    pub fn read_byte(&self) -> u8 {
+        0xC0 | self.select | self.lines()
+    }
+
+    /// Writes the P1 selection bits.
+    /// Returns true if an input line went low (which requests a joypad interrupt).
+    pub fn write_byte(&mut self, val: u8) -> bool {
+        let before = self.lines();
+        self.select = val & 0x30;
+        Self::falling_edge(before, self.lines())
+    }
+
+    /// Presses or releases a button.
+    /// Returns true if an input line went low (which requests a joypad interrupt).
+    pub fn set_pressed(&mut self, button: Button, pressed: bool) -> bool {
+        let before = self.lines();
+        let (group, bit) = match button {
+            Button::Right => (&mut self.dpad, 0),
+            Button::Left => (&mut self.dpad, 1),
+            Button::Up => (&mut self.dpad, 2),
+            Button::Down => (&mut self.dpad, 3),
+            Button::A => (&mut self.buttons, 0),
+            Button::B => (&mut self.buttons, 1),
+            Button::Select => (&mut self.buttons, 2),
+            Button::Start => (&mut self.buttons, 3),
+        };
+        if pressed {
+            *group |= 1 << bit;
+        } else {
+            *group &= !(1 << bit);
+        }
+        Self::falling_edge(before, self.lines())
+    }
+
+    /// Returns true while any selected input line (P10-P13) is low.
+    pub fn line_low(&self) -> bool {
+        self.lines() != 0x0F
+    }
+
+    // Lower nibble of P1: the selected button groups pull their lines low when pressed
+    fn lines(&self) -> u8 {
+        let mut pressed = 0;
+        if self.select & 0x10 == 0 {
+            pressed |= self.dpad;
+        }
+        if self.select & 0x20 == 0 {
+            pressed |= self.buttons;
+        }
+        !pressed & 0x0F
+    }
+
+    fn falling_edge(before: u8, after: u8) -> bool {
+        before & !after != 0
+    }
+}
+
+impl Default for Joypad {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,2 +1,11 @@
+pub mod cartridge;
 pub mod cpu;
-pub mod memory;
\ No newline at end of file
+pub mod disasm;
+pub mod error;
+pub mod infrared;
+pub mod interrupts;
+pub mod joypad;
+pub mod memory;
+pub mod ppu;
+pub mod rom;
+pub mod timer;
@@ -1,35 +1,191 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::cartridge::camera::CameraImage;
+use crate::cartridge::rtc::{Rtc, TimeSource};
+use crate::cartridge::state::{StateReader, StateWriter};
+use crate::cartridge::{self, Cartridge, CartridgeType, RumbleCallback, SaveCallback, ToneCallback};
+use crate::error::{EmuError, StateError};
+use crate::infrared::InfraredLink;
+use crate::interrupts::{Interrupt, InterruptController};
+use crate::joypad::{Button, Joypad};
+use crate::ppu::Ppu;
+use crate::timer::Timer;
+
+/// The system as seen from the CPU.
+/// Every memory access takes one M-cycle, during which the rest of the machine (timers, etc.)
+/// advances, so that peripherals observe each access of an instruction at its exact cycle.
 pub trait MemoryBus {
-    fn read_byte(&self, addr: u16) -> u8;
+    /// Reads a byte, advancing the machine by one M-cycle.
+    fn read_byte(&mut self, addr: u16) -> u8;
+
+    /// Writes a byte, advancing the machine by one M-cycle.
     fn write_byte(&mut self, addr: u16, val: u8);
-    fn read_word(&self, addr: u16) -> u16;
-    fn write_word(&mut self, addr: u16, val: u16);
+
+    /// Advances the machine by `num_cycles` M-cycles, without any memory access.
     fn tick(&mut self, num_cycles: u8);
 
+    /// Reads a byte without advancing the machine nor triggering any side effect.
+    /// Meant for debuggers and logs, not for the emulated CPU.
+    fn peek_byte(&self, addr: u16) -> u8;
+
+    /// Writes a byte without advancing the machine.
+    /// Meant for debuggers and test setups, and for CPU-internal updates such as acknowledging interrupts.
+    fn poke_byte(&mut self, addr: u16, val: u8);
+
+    /// Reads a little-endian word (2 M-cycles).
+    fn read_word(&mut self, addr: u16) -> u16 {
+        let low = self.read_byte(addr) as u16;
+        let high = self.read_byte(addr.wrapping_add(1)) as u16;
+        (high << 8) | low
+    }
+
+    /// Writes a little-endian word (2 M-cycles).
+    fn write_word(&mut self, addr: u16, val: u16) {
+        self.write_byte(addr, (val & 0xFF) as u8);
+        self.write_byte(addr.wrapping_add(1), (val >> 8) as u8);
+    }
+
     /// Records an internal CPU operation that doesn't access memory (1 M-cycle).
     /// This is important for cycle-accurate emulation and timing synchronization.
     /// Examples: internal ALU operations, SP increment/decrement, etc.
     fn tick_internal(&mut self) {
-        // Default implementation does nothing; test mocks can override
+        self.tick(1);
+    }
+
+    /// The interrupt controller, owning IF (0xFF0F) and IE (0xFFFF).
+    fn interrupts(&self) -> &InterruptController;
+
+    fn interrupts_mut(&mut self) -> &mut InterruptController;
+
+    /// Returns the number of M-cycles until the next scheduled peripheral event
+    /// (typically an interrupt request), or None if nothing is scheduled.
+    /// This lets the CPU skip idle time while halted instead of ticking one M-cycle at a time.
+    fn next_event(&self) -> Option<u32> {
+        None
+    }
+
+    /// Called when the CPU executes STOP: the divider is reset and the LCD is blanked.
+    /// Returns true if a CGB speed switch was armed through KEY1 and has been performed,
+    /// in which case the CPU keeps running instead of entering STOP mode.
+    fn stop(&mut self) -> bool {
+        false
+    }
+
+    /// Called when the CPU leaves STOP mode.
+    fn resume(&mut self) {}
+
+    /// Returns true while any selected joypad input line (P10-P13) is low.
+    /// This is the only way to wake the CPU up from STOP mode.
+    fn joypad_line_low(&self) -> bool {
+        false
+    }
+
+    /// Returns the ROM bank mapped at this address, or None outside of ROM.
+    /// Only used to locate code in debuggers and disassembly.
+    fn rom_bank(&self, _addr: u16) -> Option<u16> {
+        None
+    }
+
+    /// Returns (and clears) the first error raised by the bus since the last call.
+    /// Accesses cannot fail on real hardware, so bus implementations must never panic: they record
+    /// the error here and carry on (e.g. reading open bus), and the CPU reports it after the step.
+    fn take_error(&mut self) -> Option<EmuError> {
+        None
     }
 }
 
+/// Hardware model, for the behaviours that differ between revisions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Model {
+    /// Original Game Boy (and Pocket, and Super Game Boy)
+    Dmg,
+    /// Game Boy Color, CPU revisions 0 to C
+    CgbC,
+    /// Game Boy Color, CPU revision D
+    CgbD,
+    /// Game Boy Color, CPU revision E (the most common)
+    CgbE,
+    /// Game Boy Advance, running Game Boy software
+    Agb,
+}
+
+impl Model {
+    pub fn is_cgb(self) -> bool {
+        self != Model::Dmg
+    }
+}
+
+// Bits of the IO registers (0xFF00 - 0xFF7F) without storage, which read as 1. Unmapped and write-only
+// registers read as 0xFF. Registers with their own device (joypad, timer, IF, LCD and KEY1) are handled there.
+const IO_UNUSED_BITS: [u8; 0x80] = [
+    // 0xFF00 - 0xFF0F: joypad, serial (SC is patched for CGB), timer and IF
+    0xC0, 0x00, 0x7E, 0xFF, 0x00, 0x00, 0x00, 0xF8, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xE0,
+    // 0xFF10 - 0xFF1F: NR10 - NR14, NR21 - NR24, NR30 - NR34 (frequency low bytes are write-only)
+    0x80, 0x3F, 0x00, 0xFF, 0xBF, 0xFF, 0x3F, 0x00, 0xFF, 0xBF, 0x7F, 0xFF, 0x9F, 0xFF, 0xBF, 0xFF,
+    // 0xFF20 - 0xFF2F: NR41 - NR44, NR50 - NR52
+    0xFF, 0x00, 0x00, 0xBF, 0x00, 0x00, 0x70, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    // 0xFF30 - 0xFF3F: wave RAM
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+    // 0xFF40 - 0xFF4F: LCDC, STAT, SCY, SCX, LY, LYC, DMA, BGP, OBP0, OBP1, WY, WX
+    0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+    // 0xFF50 - 0xFF7F: boot ROM disable (write-only) and CGB registers
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+    0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+];
+
+// Values left in the sound registers by the DMG boot ROM, which isn't run (the startup sound
+// isn't played, so no channel is reported as playing)
+const IO_POST_BOOT: [(u16, u8); 15] = [
+    (0xFF10, 0x80), (0xFF11, 0xBF), (0xFF12, 0xF3), (0xFF14, 0xBF), (0xFF16, 0x3F), (0xFF19, 0xBF),
+    (0xFF1A, 0x7F), (0xFF1C, 0x9F), (0xFF1E, 0xBF), (0xFF20, 0xFF), (0xFF23, 0xBF), (0xFF24, 0x77),
+    (0xFF25, 0xF3), (0xFF26, 0x80), (0xFF46, 0xFF),
+];
+
+// Sound registers cleared, and made read-only, while the APU is powered off through NR52
+const SOUND_REGISTERS: std::ops::RangeInclusive<u16> = 0xFF10..=0xFF25;
+const NR52: u16 = 0xFF26;
+
+// Bytes copied to OAM by a DMA transfer, one per M-cycle
+const OAM_DMA_LENGTH: u8 = 0xA0;
+
+// LY value expected by Gameboy Doctor logs, which are made with the LCD stuck at the start of VBlank
+const DOCTOR_LY: u8 = 0x90;
+
 pub struct Mmu {
-    // Cartridge ROM
-    rom: Vec<u8>,
+    // Cartridge ROM and external RAM, and the hardware detected from its header
+    cartridge: Box<dyn Cartridge>,
+    cartridge_type: CartridgeType,
+    save_dirty: bool, // battery-backed memory was written since the save was last exported
+    autosave: Option<SaveCallback>,
 
     // RAM
     wram: [u8; 0x2000], // Working RAM (8KB: 0xC000 - 0xDFFF)
+    unusable: [u8; 0x60], // 0xFEA0 - 0xFEFF, only backed by RAM on early CGB revisions
     hram: [u8; 0x7F], // High RAM (127B: 0xFF80 - 0xFFFE) 
 
     // Memory-mapped IO registers
-    // (simply the ones needed for Blargg's tests for now)
+    joypad: Joypad, // 0xFF00 - P1/JOYP
     sb: u8,   // 0xFF01 - Serial transfer data
     sc: u8,   // 0xFF02 - Serial transfer control
-    if_reg: u8,  // 0xFF0F - Interrupt Flag
-    ie_reg: u8,   // 0xFFFF - Interrupt Enable
+    io: [u8; 0x80], // 0xFF00 - 0xFF7F - Registers of the devices not emulated yet (sound), as written
+    oam_dma: Option<u8>, // 0xFF46 - Next byte copied by the OAM DMA transfer in progress
+
+    // Peripherals
+    interrupts: InterruptController, // 0xFF0F and 0xFFFF
+    timer: Timer, // 0xFF04 - 0xFF07
+    ppu: Ppu, // VRAM, OAM and 0xFF40 - 0xFF4B (except DMA)
 
-    // For MBC1
-    rom_bank: usize,
+    // CGB mode: the cartridge header advertises CGB support, and the model has it
+    model: Model,
+    cgb: bool,
+    gameboy_doctor: bool,
+    key1: u8, // 0xFF4D - Prepare speed switch (bit 0) and current speed (bit 7)
+
+    // The LCD is blanked while the CPU is in STOP mode
+    stopped: bool,
 
     // Capture serial output for test results
     // TODO: create a proper logging mechanism and Serial device emulation
@@ -38,37 +194,376 @@ pub struct Mmu {
 
 impl Mmu {
     pub fn new(rom: Vec<u8>) -> Self {
+        let cartridge_type = cartridge::detect_type(&rom);
+        // ROMs too short to have the flag run in DMG mode, rather than reading it from open bus
+        let cgb = rom.get(0x0143).is_some_and(|flag| flag & 0x80 != 0);
+        Self::build(cartridge::create(rom, cartridge_type), cartridge_type, cgb)
+    }
+
+    /// Creates the memory map around a cartridge other than the built-in ones, like a custom flash cart.
+    /// `cartridge_type` describes its hardware to frontends (only `battery` changes how it is emulated).
+    /// Changes to battery-backed memory are tracked through `Cartridge::ram_enabled` and `take_save_written`.
+    pub fn with_cartridge(cartridge: Box<dyn Cartridge>, cartridge_type: CartridgeType) -> Self {
+        // 0x0143: CGB flag (bit 7 set for CGB enhanced or CGB only cartridges)
+        let cgb = cartridge.read_rom(0x0143) & 0x80 != 0;
+        Self::build(cartridge, cartridge_type, cgb)
+    }
+
+    fn build(cartridge: Box<dyn Cartridge>, cartridge_type: CartridgeType, cgb: bool) -> Self {
+        let mut io = [0; 0x80];
+        for (addr, val) in IO_POST_BOOT {
+            io[addr as usize - 0xFF00] = val;
+        }
         Self {
-            rom,
+            cartridge,
+            cartridge_type,
+            save_dirty: false,
+            autosave: None,
             wram: [0; 0x2000],
+            unusable: [0; 0x60],
             hram: [0; 0x7F],
+            joypad: Joypad::new(),
             sb: 0,
             sc: 0,
-            if_reg: 0,
-            ie_reg: 0,
-            rom_bank: 1,
+            io,
+            oam_dma: None,
+            interrupts: InterruptController::new(),
+            timer: Timer::new(),
+            ppu: Ppu::new(),
+            model: if cgb { Model::CgbE } else { Model::Dmg },
+            cgb,
+            gameboy_doctor: false,
+            key1: 0,
+            stopped: false,
             serial_output: Vec::new(),
         }
     }
 
+    /// Returns the hardware model being emulated: a CGB (revision E) for cartridges supporting it, a DMG otherwise.
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// Changes the hardware model. CGB mode is only enabled on CGB models, for cartridges supporting it.
+    pub fn set_model(&mut self, model: Model) {
+        self.model = model;
+        self.cgb = model.is_cgb() && self.cartridge.read_rom(0x0143) & 0x80 != 0;
+    }
+
+    /// Makes LY (0xFF44) always read 0x90, for comparing CPU logs with the ones of Gameboy Doctor,
+    /// which are made with the LCD stuck at the start of VBlank.
+    pub fn set_gameboy_doctor(&mut self, enabled: bool) {
+        self.gameboy_doctor = enabled;
+    }
+
+    /// Returns the cartridge hardware being emulated.
+    pub fn cartridge_type(&self) -> CartridgeType {
+        self.cartridge_type
+    }
+
+    /// Returns the cartridge, to access hardware the memory map doesn't expose.
+    pub fn cartridge(&self) -> &dyn Cartridge {
+        self.cartridge.as_ref()
+    }
+
+    pub fn cartridge_mut(&mut self) -> &mut dyn Cartridge {
+        self.cartridge.as_mut()
+    }
+
+    /// Returns the state of the cartridge (registers, RAM and clocks), for save states.
+    pub fn save_cartridge_state(&mut self) -> Vec<u8> {
+        let mut state = StateWriter::new();
+        self.cartridge.save_state(&mut state);
+        state.into_bytes()
+    }
+
+    /// Restores a cartridge state returned by `save_cartridge_state`, for the same cartridge.
+    pub fn load_cartridge_state(&mut self, data: &[u8]) -> Result<(), StateError> {
+        let mut state = StateReader::new(data);
+        self.cartridge.load_state(&mut state)?;
+        state.finish()
+    }
+
+    /// Returns the contents of the battery-backed cartridge RAM, for frontends to save,
+    /// or None if the cartridge has no battery.
+    pub fn battery_ram(&self) -> Option<&[u8]> {
+        self.cartridge_type.battery.then(|| self.cartridge.ram())
+    }
+
+    /// Restores the battery-backed cartridge RAM, as previously returned by `battery_ram`.
+    pub fn load_battery_ram(&mut self, data: &[u8]) {
+        self.cartridge.load_ram(data);
+        self.save_dirty = false;
+    }
+
+    /// Returns the contents of a `.sav` file for the cartridge, or None if it has no battery: the RAM,
+    /// followed by the clock footer used by other emulators. MBC3 clocks get the standard 48-byte RTC
+    /// footer (see `rtc::RTC_FOOTER_SIZE`). HuC3 clocks don't fit its MBC3 registers, and get SameBoy's
+    /// 17-byte HuC3 footer instead, which other emulators don't all read. TAMA5 clocks get a 15-byte
+    /// footer of their own: a 64-bit UNIX timestamp and the date and time counters.
+    /// The save is no longer dirty afterwards.
+    pub fn export_save(&mut self) -> Option<Vec<u8>> {
+        if !self.cartridge_type.battery {
+            return None;
+        }
+        self.save_dirty = false;
+        Some(self.save_data())
+    }
+
+    /// Restores the cartridge from a `.sav` file. A missing or unknown clock footer leaves the clock untouched.
+    pub fn load_save(&mut self, data: &[u8]) {
+        let ram_size = self.cartridge.ram().len().min(data.len());
+        let (ram, footer) = data.split_at(ram_size);
+        self.cartridge.load_ram(ram);
+        if !footer.is_empty() {
+            self.cartridge.load_save_footer(footer);
+        }
+        self.save_dirty = false;
+    }
+
+    /// Loads a `.sav` file, like `load_save`.
+    pub fn load_save_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        self.load_save(&fs::read(path)?);
+        Ok(())
+    }
+
+    /// Writes a `.sav` file, as returned by `export_save`. Nothing is written if the cartridge has no battery.
+    pub fn write_save_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        match self.export_save() {
+            Some(save) => fs::write(path, save),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns true when battery-backed memory was written since the save was last exported or loaded,
+    /// so that frontends know when to write the `.sav` file.
+    pub fn save_dirty(&self) -> bool {
+        self.save_dirty
+    }
+
+    /// Sets the callback writing the save, called with the contents of the `.sav` file when the game
+    /// disables cartridge RAM after writing to it, which is when games are done saving.
+    pub fn set_autosave_callback(&mut self, callback: SaveCallback) {
+        self.autosave = Some(callback);
+    }
+
+    /// Returns the real-time clock of the cartridge, to read, set or advance it, if it has one.
+    pub fn rtc(&mut self) -> Option<&mut Rtc> {
+        self.cartridge.rtc()
+    }
+
+    /// Sets the callback driving force feedback, called whenever the cartridge rumble motor
+    /// is turned on or off. Never called for cartridges without a motor.
+    pub fn set_rumble_callback(&mut self, callback: RumbleCallback) {
+        self.cartridge.set_rumble_callback(callback);
+    }
+
+    /// Feeds the cartridge accelerometer, with the acceleration along each axis in g:
+    /// x is positive when the console is tilted to the right, and y when it is tilted towards the player.
+    /// Does nothing if the cartridge has no accelerometer.
+    pub fn set_tilt(&mut self, x: f32, y: f32) {
+        self.cartridge.set_tilt(x, y);
+    }
+
+    /// Replaces the image seen by the cartridge camera, which is black until an image is supplied.
+    /// Does nothing if the cartridge has no camera.
+    pub fn set_camera_image(&mut self, image: CameraImage) {
+        self.cartridge.set_camera_image(image);
+    }
+
+    /// Replaces the time source of the cartridge clock (the host clock by default).
+    /// Does nothing if the cartridge has no clock.
+    pub fn set_time_source(&mut self, source: Box<dyn TimeSource>) {
+        self.cartridge.set_time_source(source);
+    }
+
+    /// Sets the callback playing the tones of the cartridge speaker (HuC3).
+    /// Never called for cartridges without a speaker.
+    pub fn set_tone_callback(&mut self, callback: ToneCallback) {
+        self.cartridge.set_tone_callback(callback);
+    }
+
+    /// Connects the other end of the cartridge infrared port (HuC1 and HuC3).
+    /// Does nothing if the cartridge has no infrared port.
+    pub fn set_infrared_link(&mut self, link: Box<dyn InfraredLink>) {
+        self.cartridge.set_infrared_link(link);
+    }
+
     pub fn get_serial_output(&self) -> String {
         String::from_utf8_lossy(&self.serial_output).to_string()
     }
-}
 
-impl MemoryBus for Mmu {
-    fn read_byte(&self, addr: u16) -> u8 {
+    /// Presses or releases a joypad button, requesting a joypad interrupt when an input line goes low.
+    pub fn set_button(&mut self, button: Button, pressed: bool) {
+        if self.joypad.set_pressed(button, pressed) {
+            self.interrupts.request(Interrupt::Joypad);
+        }
+    }
+
+    /// Returns true while the LCD is blanked because the CPU is in STOP mode.
+    pub fn lcd_blanked(&self) -> bool {
+        self.stopped
+    }
+
+    /// Returns true if the CPU runs in CGB double speed mode.
+    pub fn double_speed(&self) -> bool {
+        self.key1 & 0x80 != 0
+    }
+
+    /// The PPU, owning video memory and the LCD registers, and the rendered frame.
+    pub fn ppu(&self) -> &Ppu {
+        &self.ppu
+    }
+
+    // T-cycles (or dots) per M-cycle
+    fn dots_per_cycle(&self) -> u32 {
+        if self.double_speed() { 2 } else { 4 }
+    }
+
+    // OAM can't be accessed by the CPU while a DMA transfer writes to it, or the PPU reads it
+    fn oam_blocked(&self) -> bool {
+        self.oam_dma.is_some() || self.ppu.oam_blocked()
+    }
+
+    // 0xFEA0 - 0xFEFF: reads 0x00 on DMG, the high nibble of the address twice on recent CGBs, and RAM
+    // on earlier ones (with FEC0 - FEFF mirroring FEF0 - FEFF on revision D, and A3 - A4 ignored before)
+    fn unusable_index(&self, addr: u16) -> Option<usize> {
+        match self.model {
+            Model::CgbC => Some((addr & !0x18) as usize - 0xFEA0),
+            Model::CgbD if addr >= 0xFEC0 => Some((addr | 0xF0) as usize - 0xFEA0),
+            Model::CgbD => Some(addr as usize - 0xFEA0),
+            _ => None,
+        }
+    }
+
+    fn read_unusable(&self, addr: u16) -> u8 {
+        match self.model {
+            Model::Dmg if self.oam_blocked() => 0xFF,
+            Model::Dmg => 0x00,
+            Model::CgbE | Model::Agb => (addr as u8 & 0xF0) | (addr as u8 >> 4),
+            _ => self.unusable_index(addr).map_or(0xFF, |i| self.unusable[i]),
+        }
+    }
+
+    fn read_io(&self, addr: u16) -> u8 {
+        let unused_bits = match addr {
+            // Bit 1 selects the CGB high speed clock
+            0xFF02 if self.cgb => 0x7C,
+            _ => IO_UNUSED_BITS[addr as usize - 0xFF00],
+        };
         match addr {
-            0x0000..=0x3FFF => {
-                // ROM Bank 0
-                self.rom.get(addr as usize).copied().unwrap_or(0xFF)
+            0xFF00 => self.joypad.read_byte(),
+            0xFF01 => self.sb,
+            0xFF02 => self.sc | unused_bits,
+            0xFF04..=0xFF07 => self.timer.read_byte(addr),
+            0xFF0F => self.interrupts.read_if(),
+            0xFF44 if self.gameboy_doctor => DOCTOR_LY,
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.ppu.read_byte(addr),
+            0xFF4D if self.cgb => self.key1 | 0x7E,
+            _ => self.io[addr as usize - 0xFF00] | unused_bits,
+        }
+    }
+
+    fn write_io(&mut self, addr: u16, val: u8) {
+        match addr {
+            0xFF00 => {
+                // Selecting a group of held buttons pulls input lines low
+                let line_low = self.joypad.write_byte(val);
+                if line_low {
+                    self.interrupts.request(Interrupt::Joypad);
+                }
             },
-            0x4000..=0x7FFF => {
-                // Switchable ROM Bank
-                let banked_addr = (self.rom_bank * 0x4000) + ((addr as usize) - 0x4000);
-                self.rom.get(banked_addr).copied().unwrap_or(0xFF)
+            0xFF01 => self.sb = val,
+            0xFF02 => {
+                self.sc = val;
+                if val & 0x80 != 0 {
+                    // Start serial transfer (for simplicity, we just output the byte)
+                    self.serial_output.push(self.sb);
+                    self.sc &= 0x7F; // Clear the start bit
+                }
             },
-            0x8000..=0xBFFF => 0xFF, // VRAM and external RAM (not implemented yet)
+            0xFF04..=0xFF07 => self.timer.write_byte(addr, val),
+            0xFF0F => self.interrupts.write_if(val),
+            // Powering the APU off clears the sound registers, which ignore writes until it is powered on
+            NR52 => {
+                if val & 0x80 == 0 {
+                    self.io[0x10..=0x25].fill(0);
+                }
+                self.io[0x26] = val & 0x80;
+            },
+            _ if SOUND_REGISTERS.contains(&addr) && self.io[0x26] & 0x80 == 0 => {},
+            0xFF40..=0xFF45 | 0xFF47..=0xFF4B => self.write_ppu(addr, val),
+            0xFF46 => {
+                self.io[0x46] = val;
+                self.oam_dma = Some(0);
+            },
+            0xFF4D if self.cgb => self.key1 = (self.key1 & 0x80) | (val & 0x01),
+            _ => self.io[addr as usize - 0xFF00] = val,
+        }
+    }
+
+    // Copies the next bytes of the OAM DMA transfer. The source is read from the external bus,
+    // where 0xE000 - 0xFFFF maps to working RAM
+    fn step_oam_dma(&mut self, num_cycles: u8) {
+        for _ in 0..num_cycles {
+            let Some(i) = self.oam_dma else { return };
+            let src = (self.io[0x46] as u16) << 8 | i as u16;
+            let val = match src {
+                0xE000..=0xFFFF => self.wram[(src & 0x1FFF) as usize],
+                _ => self.peek_byte(src),
+            };
+            self.ppu.write_oam_dma(i, val);
+            self.oam_dma = (i + 1 < OAM_DMA_LENGTH).then_some(i + 1);
+        }
+    }
+
+    fn write_ppu(&mut self, addr: u16, val: u8) {
+        if self.ppu.write_byte(addr, val) {
+            self.interrupts.request(Interrupt::LcdStat);
+        }
+    }
+
+    fn save_data(&mut self) -> Vec<u8> {
+        let mut save = self.cartridge.ram().to_vec();
+        self.cartridge.write_save_footer(&mut save);
+        save
+    }
+
+    // Tracks writes to the MBC registers that change battery-backed memory (like MBC6 flash), or disable RAM,
+    // following the enable rule of the mapper
+    fn cartridge_written(&mut self, ram_was_enabled: bool) {
+        if !self.cartridge_type.battery {
+            return;
+        }
+        self.save_dirty |= self.cartridge.take_save_written();
+        let ram_disabled = ram_was_enabled && !self.cartridge.ram_enabled();
+        if ram_disabled && self.save_dirty && self.autosave.is_some() {
+            let save = self.save_data();
+            self.save_dirty = false;
+            if let Some(autosave) = &mut self.autosave {
+                autosave(&save);
+            }
+        }
+    }
+}
+
+impl MemoryBus for Mmu {
+    fn read_byte(&mut self, addr: u16) -> u8 {
+        // The machine advances by one M-cycle, at the end of which the CPU samples the bus
+        self.tick(1);
+        self.peek_byte(addr)
+    }
+
+    fn write_byte(&mut self, addr: u16, val: u8) {
+        self.tick(1);
+        self.poke_byte(addr, val);
+    }
+
+    fn peek_byte(&self, addr: u16) -> u8 {
+        match addr {
+            0x0000..=0x7FFF => self.cartridge.read_rom(addr), // Cartridge ROM
+            0x8000..=0x9FFF => self.ppu.read_byte(addr), // VRAM
+            0xA000..=0xBFFF => self.cartridge.read_ram(addr), // External RAM
             0xC000..=0xDFFF => {
                 // Working RAM
                 self.wram[(addr - 0xC000) as usize]
@@ -77,47 +572,31 @@ impl MemoryBus for Mmu {
                 // Echo RAM (mirror of C000-DDFF)
                 self.wram[(addr - 0xE000) as usize]
             },
-            0xFE00..=0xFE9F => 0xFF, // OAM (not implemented yet)
-            0xFEA0..=0xFEFF => 0xFF, // Unusable memory
-            0xFF00..=0xFF7F => {
-                // I/O Registers
-                match addr {
-                    0xFF01 => self.sb,
-                    0xFF02 => self.sc,
-                    0xFF0F => self.if_reg,
-                    0xFF44 => 0x90, // LY register (just return a dummy value for Gameboy Doctor tests)
-                    _ => 0xFF, // Other I/O registers not implemented yet
-                }
-            }
+            0xFE00..=0xFE9F if self.oam_blocked() => 0xFF,
+            0xFE00..=0xFE9F => self.ppu.read_byte(addr), // OAM
+            0xFEA0..=0xFEFF => self.read_unusable(addr), // Unusable memory
+            0xFF00..=0xFF7F => self.read_io(addr), // I/O Registers
             0xFF80..=0xFFFE => {
                 // High RAM
                 self.hram[(addr - 0xFF80) as usize]
             },
-            0xFFFF => self.ie_reg,
+            0xFFFF => self.interrupts.read_ie(),
         }
     }
 
-    fn read_word(&self, addr: u16) -> u16 {
-        let low = self.read_byte(addr) as u16;
-        let high = self.read_byte(addr + 1) as u16;
-        (high << 8) | low
-    }
-
-    fn write_byte(&mut self, addr: u16, val: u8) {
+    fn poke_byte(&mut self, addr: u16, val: u8) {
         match addr {
-            0x0000..=0x1FFF => {
-                // MBC1 RAM Enable (not implemented)
+            0x0000..=0x7FFF => {
+                // MBC registers
+                let ram_enabled = self.cartridge.ram_enabled();
+                self.cartridge.write_rom(addr, val);
+                self.cartridge_written(ram_enabled);
             },
-            0x2000..=0x3FFF => {
-                // MBC1 ROM Bank Number
-                let bank = (val & 0x1F) as usize;
-                self.rom_bank = if bank == 0 { 1 } else { bank };
-            },
-            0x4000..=0x7FFF => {
-                // RAM Bank Number / Upper Bits of ROM Bank Number (not implemented)
-            },
-            0x8000..=0xBFFF => {
-                // VRAM and external RAM (not implemented yet)
+            0x8000..=0x9FFF => self.write_ppu(addr, val), // VRAM
+            0xA000..=0xBFFF => {
+                // External RAM, which ignores writes while disabled
+                self.save_dirty |= self.cartridge_type.battery && self.cartridge.ram_enabled();
+                self.cartridge.write_ram(addr, val);
             },
             0xC000..=0xDFFF => {
                 // Working RAM
@@ -127,41 +606,84 @@ impl MemoryBus for Mmu {
                 // Echo RAM (mirror of C000-DDFF)
                 self.wram[(addr - 0xE000) as usize] = val;
             },
-            0xFE00..=0xFEFF => {
-                // OAM (not implemented yet) + unsusable memory
-            },
-            0xFF00..=0xFF7F => {
-                // I/O Registers
-                match addr {
-                    0xFF01 => self.sb = val,
-                    0xFF02 => {
-                        self.sc = val;
-                        if val & 0x80 != 0 {
-                            // Start serial transfer (for simplicity, we just output the byte)
-                            self.serial_output.push(self.sb);
-                            self.sc &= 0x7F; // Clear the start bit
-                        }
-                    },
-                    0xFF0F => self.if_reg = val,
-                    _ => {}, // Other I/O registers not implemented yet
+            0xFE00..=0xFE9F if self.oam_blocked() => {},
+            0xFE00..=0xFE9F => self.write_ppu(addr, val), // OAM
+            0xFEA0..=0xFEFF => {
+                // Unusable memory
+                if let Some(i) = self.unusable_index(addr) {
+                    self.unusable[i] = val;
                 }
             },
+            0xFF00..=0xFF7F => self.write_io(addr, val), // I/O Registers
             0xFF80..=0xFFFE => {
                 // High RAM
                 self.hram[(addr - 0xFF80) as usize] = val;
             },
-            0xFFFF => self.ie_reg = val,
+            0xFFFF => self.interrupts.write_ie(val),
         }
     }
 
-    fn write_word(&mut self, addr: u16, val: u16) {
-        let low = (val & 0xFF) as u8;
-        let high = (val >> 8) as u8;
-        self.write_byte(addr, low);
-        self.write_byte(addr + 1, high);
+    fn tick(&mut self, num_cycles: u8) {
+        self.step_oam_dma(num_cycles);
+        if self.timer.tick(num_cycles) {
+            self.interrupts.request(Interrupt::Timer);
+        }
+
+        // The PPU and cartridge clocks (and the camera, which takes time to capture pictures) don't follow
+        // the CPU speed
+        let t_cycles = self.dots_per_cycle() * num_cycles as u32;
+        let requested = self.ppu.tick(t_cycles);
+        for interrupt in [Interrupt::VBlank, Interrupt::LcdStat] {
+            if requested & interrupt.mask() != 0 {
+                self.interrupts.request(interrupt);
+            }
+        }
+        self.cartridge.tick(t_cycles);
     }
 
-    fn tick(&mut self, num_cycles: u8) {
-        // TODO: update timers, etc.
+    fn interrupts(&self) -> &InterruptController {
+        &self.interrupts
+    }
+
+    fn interrupts_mut(&mut self) -> &mut InterruptController {
+        &mut self.interrupts
+    }
+
+    fn next_event(&self) -> Option<u32> {
+        // PPU events only matter when they can wake the CPU up
+        let lcd_interrupts = Interrupt::VBlank.mask() | Interrupt::LcdStat.mask();
+        let ppu = match self.interrupts.read_ie() & lcd_interrupts {
+            0 => None,
+            _ => self.ppu.next_event().map(|dots| dots.div_ceil(self.dots_per_cycle())),
+        };
+        [self.timer.next_event(), ppu].into_iter().flatten().min()
+    }
+
+    fn stop(&mut self) -> bool {
+        self.timer.reset_div();
+
+        if self.cgb && self.key1 & 0x01 != 0 {
+            // Armed speed switch: toggle the current speed and clear the prepare bit
+            self.key1 = (self.key1 ^ 0x80) & 0x80;
+            return true;
+        }
+
+        self.stopped = true;
+        false
+    }
+
+    fn resume(&mut self) {
+        self.stopped = false;
+    }
+
+    fn joypad_line_low(&self) -> bool {
+        self.joypad.line_low()
+    }
+
+    fn rom_bank(&self, addr: u16) -> Option<u16> {
+        match addr {
+            0x0000..=0x7FFF => Some(self.cartridge.rom_bank(addr)),
+            _ => None,
+        }
     }
 }
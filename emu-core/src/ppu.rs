@@ -0,0 +1,310 @@
+use crate::interrupts::Interrupt;
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+// Line timing, in dots (T-cycles at normal speed)
+const DOTS_PER_LINE: u32 = 456;
+const LINES_PER_FRAME: u8 = 154;
+const OAM_SCAN_DOTS: u32 = 80;
+const DRAWING_DOTS: u32 = 172; // without the pixels discarded for the fine scroll
+
+// LCDC bits
+const LCD_ENABLE: u8 = 0x80;
+const BG_TILE_DATA: u8 = 0x10; // 0x8000 with unsigned tile numbers, or 0x9000 with signed ones
+const BG_TILE_MAP: u8 = 0x08; // 0x9800 or 0x9C00
+const BG_ENABLE: u8 = 0x01;
+
+// STAT interrupt sources
+const STAT_HBLANK: u8 = 0x08;
+const STAT_VBLANK: u8 = 0x10;
+const STAT_OAM_SCAN: u8 = 0x20;
+const STAT_LYC: u8 = 0x40;
+
+/// PPU modes, as reported in the low bits of STAT
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    HBlank = 0,
+    VBlank = 1,
+    OamScan = 2,
+    Drawing = 3,
+}
+
+/// Pixel processing unit (see: https://gbdev.io/pandocs/Rendering.html).
+/// Owns the video memory and the LCD registers, and renders the background into a framebuffer of
+/// shades from 0 (white) to 3 (black), one line at a time.
+pub struct Ppu {
+    vram: [u8; 0x2000], // Video RAM (8KB: 0x8000 - 0x9FFF)
+    oam: [u8; 0xA0],    // Object attribute memory (160B: 0xFE00 - 0xFE9F)
+
+    lcdc: u8, // 0xFF40 - LCD control
+    stat: u8, // 0xFF41 - LCD status (only the interrupt sources, bits 3-6, are stored)
+    scy: u8,  // 0xFF42 - Background viewport Y
+    scx: u8,  // 0xFF43 - Background viewport X
+    ly: u8,   // 0xFF44 - Current line
+    lyc: u8,  // 0xFF45 - Line compare
+    bgp: u8,  // 0xFF47 - Background palette
+    obp0: u8, // 0xFF48 - Object palette 0
+    obp1: u8, // 0xFF49 - Object palette 1
+    wy: u8,   // 0xFF4A - Window Y
+    wx: u8,   // 0xFF4B - Window X + 7
+
+    mode: Mode,
+    dot: u32,         // position in the current line
+    drawing_end: u32, // dot at which the current line's mode 3 ends, depending on SCX
+    // The STAT interrupt is requested on rising edges of the OR of its enabled sources
+    stat_line: bool,
+
+    framebuffer: [u8; SCREEN_WIDTH * SCREEN_HEIGHT],
+    frames: u64,
+}
+
+impl Ppu {
+    /// Creates the PPU in the state left by the DMG boot ROM: LCD on, at the start of a frame.
+    pub fn new() -> Self {
+        Self {
+            vram: [0; 0x2000],
+            oam: [0; 0xA0],
+            lcdc: 0x91,
+            stat: 0,
+            scy: 0,
+            scx: 0,
+            ly: 0,
+            lyc: 0,
+            bgp: 0xFC,
+            obp0: 0,
+            obp1: 0,
+            wy: 0,
+            wx: 0,
+            mode: Mode::OamScan,
+            dot: 0,
+            drawing_end: OAM_SCAN_DOTS + DRAWING_DOTS,
+            stat_line: false,
+            framebuffer: [0; SCREEN_WIDTH * SCREEN_HEIGHT],
+            frames: 0,
+        }
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Returns the last rendered frame, as `SCREEN_WIDTH * SCREEN_HEIGHT` shades from 0 (white) to 3 (black).
+    pub fn framebuffer(&self) -> &[u8] {
+        &self.framebuffer
+    }
+
+    /// Returns the number of frames completed (VBlank periods entered) since power on.
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    /// Returns true if the CPU can't access VRAM, while the PPU reads it to draw a line.
+    pub fn vram_blocked(&self) -> bool {
+        self.mode == Mode::Drawing
+    }
+
+    /// Returns true if the CPU can't access OAM, while the PPU scans it and draws a line.
+    pub fn oam_blocked(&self) -> bool {
+        matches!(self.mode, Mode::OamScan | Mode::Drawing)
+    }
+
+    /// Reads VRAM, OAM or an LCD register (except DMA, at 0xFF46).
+    /// Video memory in use by the PPU reads as 0xFF.
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            0x8000..=0x9FFF if self.vram_blocked() => 0xFF,
+            0x8000..=0x9FFF => self.vram[(addr - 0x8000) as usize],
+            0xFE00..=0xFE9F if self.oam_blocked() => 0xFF,
+            0xFE00..=0xFE9F => self.oam[(addr - 0xFE00) as usize],
+            0xFF40 => self.lcdc,
+            0xFF41 => 0x80 | self.stat | (self.coincidence() as u8) << 2 | self.mode as u8,
+            0xFF42 => self.scy,
+            0xFF43 => self.scx,
+            0xFF44 => self.ly,
+            0xFF45 => self.lyc,
+            0xFF47 => self.bgp,
+            0xFF48 => self.obp0,
+            0xFF49 => self.obp1,
+            0xFF4A => self.wy,
+            0xFF4B => self.wx,
+            _ => 0xFF,
+        }
+    }
+
+    /// Writes VRAM, OAM or an LCD register (except DMA, at 0xFF46).
+    /// Writes to video memory in use by the PPU are ignored.
+    /// Returns true if a STAT interrupt was requested, by enabling a source or changing LYC.
+    pub fn write_byte(&mut self, addr: u16, val: u8) -> bool {
+        match addr {
+            0x8000..=0x9FFF if self.vram_blocked() => {},
+            0x8000..=0x9FFF => self.vram[(addr - 0x8000) as usize] = val,
+            0xFE00..=0xFE9F if self.oam_blocked() => {},
+            0xFE00..=0xFE9F => self.oam[(addr - 0xFE00) as usize] = val,
+            0xFF40 => return self.write_lcdc(val),
+            // Mode and coincidence flags are read-only
+            0xFF41 => self.stat = val & 0x78,
+            0xFF42 => self.scy = val,
+            0xFF43 => self.scx = val,
+            0xFF44 => {}, // LY is read-only
+            0xFF45 => self.lyc = val,
+            0xFF47 => self.bgp = val,
+            0xFF48 => self.obp0 = val,
+            0xFF49 => self.obp1 = val,
+            0xFF4A => self.wy = val,
+            0xFF4B => self.wx = val,
+            _ => {},
+        }
+        self.update_stat_line()
+    }
+
+    /// Writes a byte of OAM on behalf of a DMA transfer, which has priority over the PPU.
+    pub fn write_oam_dma(&mut self, index: u8, val: u8) {
+        self.oam[index as usize] = val;
+    }
+
+    /// Advances the PPU by `dots` dots (T-cycles at normal speed).
+    /// Returns the interrupts requested in the meantime, as a mask of IF bits.
+    pub fn tick(&mut self, dots: u32) -> u8 {
+        if self.lcdc & LCD_ENABLE == 0 {
+            return 0;
+        }
+
+        let mut requested = 0;
+        let mut dots = dots;
+        while dots > 0 {
+            let step = dots.min(self.mode_end() - self.dot);
+            self.dot += step;
+            dots -= step;
+            if self.dot == self.mode_end() {
+                requested |= self.next_mode();
+            }
+        }
+        requested
+    }
+
+    /// Returns the number of dots until the next mode change, which may request an interrupt,
+    /// or None while the LCD is off.
+    pub fn next_event(&self) -> Option<u32> {
+        (self.lcdc & LCD_ENABLE != 0).then(|| self.mode_end() - self.dot)
+    }
+
+    fn coincidence(&self) -> bool {
+        self.ly == self.lyc
+    }
+
+    // Dot at which the current mode ends
+    fn mode_end(&self) -> u32 {
+        match self.mode {
+            Mode::OamScan => OAM_SCAN_DOTS,
+            Mode::Drawing => self.drawing_end,
+            Mode::HBlank | Mode::VBlank => DOTS_PER_LINE,
+        }
+    }
+
+    // Moves on to the next mode (and line, at the end of HBlank and VBlank lines)
+    fn next_mode(&mut self) -> u8 {
+        let mut requested = 0;
+        match self.mode {
+            Mode::OamScan => {
+                // The fine scroll is applied by discarding pixels at the start of the line
+                self.mode = Mode::Drawing;
+                self.drawing_end = OAM_SCAN_DOTS + DRAWING_DOTS + (self.scx & 7) as u32;
+            },
+            Mode::Drawing => {
+                self.render_line();
+                self.mode = Mode::HBlank;
+            },
+            Mode::HBlank | Mode::VBlank => {
+                self.dot = 0;
+                self.ly = (self.ly + 1) % LINES_PER_FRAME;
+                self.mode = match self.ly as usize {
+                    SCREEN_HEIGHT => {
+                        self.frames += 1;
+                        requested |= Interrupt::VBlank.mask();
+                        Mode::VBlank
+                    },
+                    0..SCREEN_HEIGHT => Mode::OamScan,
+                    _ => Mode::VBlank,
+                };
+            },
+        }
+        if self.update_stat_line() {
+            requested |= Interrupt::LcdStat.mask();
+        }
+        requested
+    }
+
+    // Turning the LCD off resets LY and blanks the screen, and turning it on restarts the first line
+    fn write_lcdc(&mut self, val: u8) -> bool {
+        let was_enabled = self.lcdc & LCD_ENABLE != 0;
+        self.lcdc = val;
+        match (was_enabled, val & LCD_ENABLE != 0) {
+            (true, false) => {
+                self.ly = 0;
+                self.dot = 0;
+                self.mode = Mode::HBlank;
+                self.framebuffer.fill(0);
+            },
+            (false, true) => {
+                self.ly = 0;
+                self.dot = 0;
+                self.mode = Mode::OamScan;
+            },
+            _ => {},
+        }
+        self.update_stat_line()
+    }
+
+    // Updates the STAT interrupt line, returning true on a rising edge
+    fn update_stat_line(&mut self) -> bool {
+        let line = self.lcdc & LCD_ENABLE != 0
+            && (self.stat & STAT_LYC != 0 && self.coincidence()
+                || match self.mode {
+                    Mode::HBlank => self.stat & STAT_HBLANK != 0,
+                    Mode::VBlank => self.stat & STAT_VBLANK != 0,
+                    Mode::OamScan => self.stat & STAT_OAM_SCAN != 0,
+                    Mode::Drawing => false,
+                });
+        let rising = line && !self.stat_line;
+        self.stat_line = line;
+        rising
+    }
+
+    // Draws the background of the current line, as seen through the SCX/SCY viewport
+    fn render_line(&mut self) {
+        let start = self.ly as usize * SCREEN_WIDTH;
+        if self.lcdc & BG_ENABLE == 0 {
+            self.framebuffer[start..start + SCREEN_WIDTH].fill(0);
+            return;
+        }
+
+        let map = if self.lcdc & BG_TILE_MAP != 0 { 0x1C00 } else { 0x1800 };
+        let y = self.ly.wrapping_add(self.scy);
+        for x in 0..SCREEN_WIDTH {
+            let bg_x = (x as u8).wrapping_add(self.scx);
+            let tile = self.vram[map + (y as usize / 8) * 32 + bg_x as usize / 8];
+            let color = self.tile_pixel(tile, bg_x % 8, y % 8);
+            self.framebuffer[start + x] = (self.bgp >> (color * 2)) & 0x03;
+        }
+    }
+
+    // Returns the color number (0 - 3) of a background tile pixel
+    fn tile_pixel(&self, tile: u8, x: u8, y: u8) -> u8 {
+        let base = if self.lcdc & BG_TILE_DATA != 0 {
+            tile as usize * 16
+        } else {
+            (0x1000 + tile as i8 as i32 * 16) as usize
+        };
+        // Each row is 2 bytes: the low bits of its 8 pixels, then the high bits
+        let row = base + y as usize * 2;
+        let bit = 7 - x;
+        (self.vram[row] >> bit) & 0x01 | ((self.vram[row + 1] >> bit) & 0x01) << 1
+    }
+}
+
+impl Default for Ppu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,76 @@
+pub mod patch;
+
+pub use patch::apply_patch;
+
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path::Path;
+
+use flate2::read::GzDecoder;
+use zip::ZipArchive;
+
+use crate::error::RomError;
+
+// Archives are recognized from their contents rather than their extension
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+
+// Extensions of the files looked for in zip archives
+const ROM_EXTENSIONS: [&str; 2] = ["gb", "gbc"];
+
+// Largest ROM accepted from archives and patches (the largest Game Boy ROMs are 8 MiB)
+const MAX_ROM_SIZE: usize = 8 * 1024 * 1024;
+
+/// Reads a ROM, to be given to `Mmu::new`: a .gb or .gbc file, either as is or compressed in
+/// a .zip or .gz archive.
+pub fn load(path: impl AsRef<Path>) -> Result<Vec<u8>, RomError> {
+    decompress(read(path)?)
+}
+
+/// Reads a ROM like `load`, then applies IPS, UPS or BPS patches to it, in order.
+pub fn load_patched(path: impl AsRef<Path>, patches: &[impl AsRef<Path>]) -> Result<Vec<u8>, RomError> {
+    let mut rom = load(path)?;
+    for patch in patches {
+        rom = apply_patch(&rom, &read(patch)?)?;
+    }
+    Ok(rom)
+}
+
+/// Extracts a ROM from the contents of a .zip or .gz archive. Anything else is returned as is.
+/// In zip archives, the first .gb or .gbc file is taken. Decompression stops at `RomError::TooLarge`
+/// past the size of the largest Game Boy ROMs.
+pub fn decompress(data: Vec<u8>) -> Result<Vec<u8>, RomError> {
+    let archive_error = |err: &dyn std::error::Error| RomError::Archive(err.to_string());
+    if data.starts_with(ZIP_MAGIC) {
+        let mut archive = ZipArchive::new(Cursor::new(data)).map_err(|err| archive_error(&err))?;
+        for i in 0..archive.len() {
+            let file = archive.by_index(i).map_err(|err| archive_error(&err))?;
+            let is_rom = Path::new(file.name())
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ROM_EXTENSIONS.iter().any(|rom_ext| ext.eq_ignore_ascii_case(rom_ext)));
+            if file.is_file() && is_rom {
+                return read_limited(file);
+            }
+        }
+        Err(RomError::NoRomInArchive)
+    } else if data.starts_with(GZIP_MAGIC) {
+        read_limited(GzDecoder::new(data.as_slice()))
+    } else {
+        Ok(data)
+    }
+}
+
+// Decompresses a ROM, reading one byte past the limit to tell if it is exceeded
+fn read_limited(reader: impl Read) -> Result<Vec<u8>, RomError> {
+    let mut rom = Vec::new();
+    reader.take(MAX_ROM_SIZE as u64 + 1).read_to_end(&mut rom).map_err(|err| RomError::Archive(err.to_string()))?;
+    if rom.len() > MAX_ROM_SIZE {
+        return Err(RomError::TooLarge);
+    }
+    Ok(rom)
+}
+
+fn read(path: impl AsRef<Path>) -> Result<Vec<u8>, RomError> {
+    fs::read(path).map_err(|err| RomError::Io(err.to_string()))
+}
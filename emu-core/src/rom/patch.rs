@@ -0,0 +1,247 @@
+use flate2::Crc;
+
+use crate::error::PatchError;
+use crate::rom::MAX_ROM_SIZE;
+
+const IPS_MAGIC: &[u8] = b"PATCH";
+const IPS_EOF: &[u8] = b"EOF";
+const UPS_MAGIC: &[u8] = b"UPS1";
+const BPS_MAGIC: &[u8] = b"BPS1";
+
+// UPS and BPS patches end with the CRC32 of the source ROM, of the target ROM and of the patch itself
+const FOOTER_SIZE: usize = 12;
+
+/// Applies a patch to a ROM, and returns the patched ROM. The format (IPS, UPS or BPS) is recognized
+/// from the patch header. UPS and BPS patches are checked against the CRC32 of the ROMs they were made from
+/// and for, so they are only applied to the right ROM.
+pub fn apply_patch(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if let Some(records) = patch.strip_prefix(IPS_MAGIC) {
+        apply_ips(rom, records)
+    } else if patch.starts_with(UPS_MAGIC) {
+        apply_ups(rom, patch)
+    } else if patch.starts_with(BPS_MAGIC) {
+        apply_bps(rom, patch)
+    } else {
+        Err(PatchError::UnknownFormat)
+    }
+}
+
+/// IPS: records of a 24-bit offset and 16-bit size (big-endian), followed by the data, or by a 16-bit
+/// count and a byte to repeat when the size is 0. The "EOF" marker can be followed by the size to truncate
+/// the ROM to. There are no checksums.
+fn apply_ips(rom: &[u8], records: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let mut target = rom.to_vec();
+    let mut patch = Reader::new(records);
+    loop {
+        let offset = patch.bytes(3)?;
+        if offset == IPS_EOF {
+            break;
+        }
+        let offset = u32::from_be_bytes([0, offset[0], offset[1], offset[2]]) as usize;
+        let size = patch.u16_be()? as usize;
+        let data = if size == 0 {
+            let count = patch.u16_be()? as usize;
+            vec![patch.u8()?; count]
+        } else {
+            patch.bytes(size)?.to_vec()
+        };
+        if target.len() < offset + data.len() {
+            target.resize(offset + data.len(), 0);
+        }
+        target[offset..offset + data.len()].copy_from_slice(&data);
+    }
+    if let Ok(size) = patch.bytes(3) {
+        target.truncate(u32::from_be_bytes([0, size[0], size[1], size[2]]) as usize);
+    }
+    Ok(target)
+}
+
+/// UPS: the sizes of the source and target ROMs, then hunks of a relative offset followed by bytes to XOR
+/// with the ROM, up to a 0 byte.
+fn apply_ups(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (body, footer) = split_footer(rom, patch)?;
+    let mut patch = Reader::new(&body[UPS_MAGIC.len()..]);
+    let source_size = patch.varint()?;
+    let target_size = check_target_size(patch.varint()?)?;
+    check_source_size(rom, source_size)?;
+
+    let mut target = rom.to_vec();
+    target.resize(target_size, 0);
+    let mut pos = 0usize;
+    while !patch.is_empty() {
+        pos = pos.checked_add(patch.varint()?).ok_or(PatchError::OutOfBounds(pos))?;
+        loop {
+            let xor = patch.u8()?;
+            if xor != 0 {
+                *target.get_mut(pos).ok_or(PatchError::OutOfBounds(pos))? ^= xor;
+            }
+            pos = pos.checked_add(1).ok_or(PatchError::OutOfBounds(pos))?;
+            if xor == 0 {
+                break;
+            }
+        }
+    }
+    check_target(&target, target_size, footer)?;
+    Ok(target)
+}
+
+/// BPS: the sizes of the source and target ROMs and metadata, then actions building the target ROM
+/// from the source ROM, the patch, or the target ROM built so far.
+fn apply_bps(rom: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (body, footer) = split_footer(rom, patch)?;
+    let mut patch = Reader::new(&body[BPS_MAGIC.len()..]);
+    let source_size = patch.varint()?;
+    let target_size = check_target_size(patch.varint()?)?;
+    let metadata_size = patch.varint()?;
+    patch.bytes(metadata_size)?;
+    check_source_size(rom, source_size)?;
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset = 0usize;
+    let mut target_offset = 0usize;
+    while !patch.is_empty() {
+        let action = patch.varint()?;
+        let len = (action >> 2) + 1;
+        // Every action appends its bytes to the target ROM, which can't grow past its announced size
+        let end = target.len().checked_add(len).filter(|&end| end <= target_size);
+        let end = end.ok_or(PatchError::OutOfBounds(target.len()))?;
+        match action & 0x03 {
+            // Source read: the bytes at the same position in the source ROM
+            0 => {
+                let pos = target.len();
+                let data = rom.get(pos..end).ok_or(PatchError::OutOfBounds(pos))?;
+                target.extend_from_slice(data);
+            },
+            // Target read: bytes from the patch
+            1 => target.extend_from_slice(patch.bytes(len)?),
+            // Source copy: bytes from anywhere in the source ROM
+            2 => {
+                source_offset = relative_offset(source_offset, patch.varint()?)?;
+                let copy_end = source_offset.checked_add(len).ok_or(PatchError::OutOfBounds(source_offset))?;
+                let data = rom.get(source_offset..copy_end).ok_or(PatchError::OutOfBounds(source_offset))?;
+                target.extend_from_slice(data);
+                source_offset = copy_end;
+            },
+            // Target copy: bytes from the target ROM, which the copy can overlap to repeat a pattern
+            _ => {
+                target_offset = relative_offset(target_offset, patch.varint()?)?;
+                if target_offset >= target.len() {
+                    return Err(PatchError::OutOfBounds(target_offset));
+                }
+                for _ in 0..len {
+                    target.push(target[target_offset]);
+                    target_offset += 1;
+                }
+            },
+        }
+    }
+    check_target(&target, target_size, footer)?;
+    Ok(target)
+}
+
+// Checks the CRC32 of the patch and of the source ROM, found in the footer of UPS and BPS patches,
+// and returns the rest of the patch and the footer
+fn split_footer<'a>(rom: &[u8], patch: &'a [u8]) -> Result<(&'a [u8], [u32; 3]), PatchError> {
+    if patch.len() < 4 + FOOTER_SIZE {
+        return Err(PatchError::Truncated);
+    }
+    let (body, crcs) = patch.split_at(patch.len() - FOOTER_SIZE);
+    let footer: [u32; 3] = std::array::from_fn(|i| u32::from_le_bytes(crcs[i * 4..i * 4 + 4].try_into().unwrap()));
+
+    let computed = crc32(&patch[..patch.len() - 4]);
+    if computed != footer[2] {
+        return Err(PatchError::PatchChecksum { expected: footer[2], computed });
+    }
+    let computed = crc32(rom);
+    if computed != footer[0] {
+        return Err(PatchError::SourceChecksum { expected: footer[0], computed });
+    }
+    Ok((body, footer))
+}
+
+fn check_source_size(rom: &[u8], expected: usize) -> Result<(), PatchError> {
+    if rom.len() != expected {
+        return Err(PatchError::SourceSize { expected, actual: rom.len() });
+    }
+    Ok(())
+}
+
+// Sizes given by UPS and BPS patches are checked before allocating the patched ROM
+fn check_target_size(size: usize) -> Result<usize, PatchError> {
+    if size > MAX_ROM_SIZE {
+        return Err(PatchError::TargetTooLarge(size));
+    }
+    Ok(size)
+}
+
+fn check_target(target: &[u8], expected: usize, footer: [u32; 3]) -> Result<(), PatchError> {
+    if target.len() != expected {
+        return Err(PatchError::TargetSize { expected, actual: target.len() });
+    }
+    let computed = crc32(target);
+    if computed != footer[1] {
+        return Err(PatchError::TargetChecksum { expected: footer[1], computed });
+    }
+    Ok(())
+}
+
+// BPS copy offsets are relative to the end of the previous copy: bit 0 is the sign, the other bits the distance
+fn relative_offset(offset: usize, delta: usize) -> Result<usize, PatchError> {
+    let distance = delta >> 1;
+    let moved = if delta & 1 != 0 { offset.checked_sub(distance) } else { offset.checked_add(distance) };
+    moved.ok_or(PatchError::OutOfBounds(offset))
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+// Reads the fields of a patch
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], PatchError> {
+        let (bytes, rest) = self.data.split_at_checked(len).ok_or(PatchError::Truncated)?;
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, PatchError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16_be(&mut self) -> Result<u16, PatchError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    // Variable-length number of UPS and BPS: 7 bits per byte, least significant first, the last byte having
+    // bit 7 set. Each continuation adds one to the next group, so that every number has a single encoding.
+    fn varint(&mut self) -> Result<usize, PatchError> {
+        let mut val = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.u8()?;
+            // Numbers too large to be a size or offset can't be in range
+            let overflow = PatchError::OutOfBounds(val);
+            val = (byte as usize & 0x7F).checked_mul(shift).and_then(|group| val.checked_add(group)).ok_or(overflow)?;
+            if byte & 0x80 != 0 {
+                return Ok(val);
+            }
+            shift = shift.checked_mul(0x80).ok_or(overflow)?;
+            val = val.checked_add(shift).ok_or(overflow)?;
+        }
+    }
+}
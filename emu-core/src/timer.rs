@@ -0,0 +1,130 @@
+/// DIV/TIMA/TMA/TAC timer (see: https://gbdev.io/pandocs/Timer_and_Divider_Registers.html)
+pub struct Timer {
+    // 16-bit system counter, incremented every T-cycle. DIV is its upper byte.
+    counter: u16,
+    tima: u8, // 0xFF05 - Timer counter
+    tma: u8,  // 0xFF06 - Timer modulo
+    tac: u8,  // 0xFF07 - Timer control
+
+    // TIMA overflowed during the last M-cycle: it reads 0 until it is reloaded from TMA
+    overflow_pending: bool,
+}
+
+impl Timer {
+    pub fn new() -> Self {
+        Self {
+            counter: 0,
+            tima: 0,
+            tma: 0,
+            tac: 0,
+            overflow_pending: false,
+        }
+    }
+
+    pub fn read_byte(&self, addr: u16) -> u8 {
+        match addr {
+            0xFF04 => (self.counter >> 8) as u8,
+            0xFF05 => self.tima,
+            0xFF06 => self.tma,
+            0xFF07 => self.tac | 0xF8, // upper bits are unused and read as 1
+            _ => 0xFF,
+        }
+    }
+
+    pub fn write_byte(&mut self, addr: u16, val: u8) {
+        match addr {
+            0xFF04 => self.reset_div(),
+            0xFF05 => {
+                // Writing TIMA during the reload delay cancels the reload
+                self.tima = val;
+                self.overflow_pending = false;
+            },
+            0xFF06 => self.tma = val,
+            0xFF07 => {
+                // Changing TAC can produce a falling edge on the timer input
+                let before = self.timer_input();
+                self.tac = val & 0x07;
+                if before && !self.timer_input() {
+                    self.increment_tima();
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// Resets the system counter (DIV). This may produce a falling edge on the timer input.
+    pub fn reset_div(&mut self) {
+        let before = self.timer_input();
+        self.counter = 0;
+        if before {
+            self.increment_tima();
+        }
+    }
+
+    /// Advances the timer by `num_cycles` M-cycles.
+    /// Returns true if a timer interrupt was requested in the meantime.
+    pub fn tick(&mut self, num_cycles: u8) -> bool {
+        let mut interrupt = false;
+        for _ in 0..num_cycles {
+            if self.overflow_pending {
+                // TIMA is reloaded one M-cycle after overflowing, and the interrupt is requested
+                self.overflow_pending = false;
+                self.tima = self.tma;
+                interrupt = true;
+            }
+
+            let before = self.timer_input();
+            self.counter = self.counter.wrapping_add(4);
+            if before && !self.timer_input() {
+                self.increment_tima();
+            }
+        }
+        interrupt
+    }
+
+    /// Returns the number of M-cycles until the timer requests its next interrupt, if it will.
+    pub fn next_event(&self) -> Option<u32> {
+        if self.overflow_pending {
+            return Some(1);
+        }
+        if self.tac & 0x04 == 0 {
+            return None;
+        }
+
+        // TIMA increments on each falling edge of the selected counter bit
+        let period = 2 << self.selected_bit(); // in T-cycles
+        let until_edge = period - (self.counter as u32 & (period - 1));
+        let until_overflow = until_edge + (0xFF - self.tima as u32) * period;
+
+        // +1 M-cycle for the reload delay
+        Some(until_overflow.div_ceil(4) + 1)
+    }
+
+    fn selected_bit(&self) -> u32 {
+        match self.tac & 0x03 {
+            0b00 => 9, // 4096 Hz
+            0b01 => 3, // 262144 Hz
+            0b10 => 5, // 65536 Hz
+            _ => 7,    // 16384 Hz
+        }
+    }
+
+    // The timer input is the selected system counter bit, ANDed with the enable bit
+    fn timer_input(&self) -> bool {
+        self.tac & 0x04 != 0 && (self.counter >> self.selected_bit()) & 1 != 0
+    }
+
+    fn increment_tima(&mut self) {
+        let (tima, overflow) = self.tima.overflowing_add(1);
+        self.tima = tima;
+        if overflow {
+            self.overflow_pending = true;
+        }
+    }
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
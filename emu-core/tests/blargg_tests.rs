@@ -1,18 +1,50 @@
 use std::fs;
 use std::io::Write;
 
-#[test]
-fn blarggs_cpu_instr() {
-    let rom = fs::read("tests/data/blarggs/cpu_instrs/cpu_instrs.gb").expect("Failed to read ROM");
-    let mut mmu = emu_core::memory::Mmu::new(rom);
-    let mut cpu = emu_core::cpu::cpu::Cpu::new(mmu);
+use emu_core::cpu::cpu::Cpu;
+use emu_core::memory::Mmu;
+
+// M-cycles per second of emulated time
+const M_CYCLES_PER_SECOND: u64 = 1_048_576;
 
-    for _ in 0..100_000_000 {
-        cpu.tick();
+// The whole cpu_instrs suite takes about a minute on hardware
+const TIMEOUT: u64 = 120 * M_CYCLES_PER_SECOND;
+
+/// Returns true once the test ROM has reported its result on the serial port.
+fn finished(cpu: &Cpu<Mmu>) -> bool {
+    let output = cpu.mmu.get_serial_output();
+    output.contains("Passed") || output.contains("Failed")
+}
+
+/// Runs a test ROM until it reports its result on the serial port, and checks that it passed.
+fn run_until_passed(rom_path: &str, expected: &str) {
+    let rom = fs::read(rom_path).expect("Failed to read ROM");
+    let mmu = Mmu::new(rom);
+    let mut cpu = Cpu::new(mmu);
+
+    // Checking the serial output is slow, so only do it every emulated frame or so
+    while cpu.m_cycles() < TIMEOUT && !finished(&cpu) {
+        cpu.run_for_cycles(M_CYCLES_PER_SECOND / 64);
     }
 
     let output = cpu.mmu.get_serial_output();
     println!("Serial output:\n{}", output);
+    assert!(output.contains(expected), "after {} M-cycles", cpu.m_cycles());
+}
+
+#[test]
+fn blarggs_cpu_instr() {
+    run_until_passed("tests/data/blarggs/cpu_instrs/cpu_instrs.gb", "Passed all tests");
+}
+
+#[test]
+fn blarggs_mem_timing() {
+    run_until_passed("tests/data/blarggs/mem_timing/mem_timing.gb", "Passed");
+}
+
+#[test]
+fn blarggs_mem_timing_2() {
+    run_until_passed("tests/data/blarggs/mem_timing-2/mem_timing.gb", "Passed");
 }
 
 #[test]
@@ -22,18 +54,20 @@ fn blarggs_cpu_instrs_2() {
 
 fn run_with_doctor_log(rom_path: &str, log_path: &str) {
     let rom = fs::read(rom_path).expect("Failed to read ROM");
-    let mut mmu = emu_core::memory::Mmu::new(rom);
-    let mut cpu = emu_core::cpu::cpu::Cpu::boot_rom_initialized(mmu);
+    let mut mmu = Mmu::new(rom);
+    mmu.set_gameboy_doctor(true);
+    let mut cpu = Cpu::boot_rom_initialized(mmu);
 
     // open the log file to write to it
     let mut log_file = fs::File::create(log_path).expect("Failed to create log file");
 
-    for _ in 0..100_000_000 {
-        cpu.tick();
+    while cpu.m_cycles() < TIMEOUT && !finished(&cpu) {
+        cpu.step();
         // Write CPU state to log file in Gameboy Doctor format
         let state = cpu.doctor_log_state();
         writeln!(log_file, "{}", state).expect("Failed to write to log file");
     }
 
     let output = cpu.mmu.get_serial_output();
-}
\ No newline at end of file
+    println!("Serial output:\n{}", output);
+}
@@ -0,0 +1,213 @@
+mod common;
+
+use emu_core::cartridge::camera::{CameraImage, HEIGHT, WIDTH};
+use emu_core::cartridge::Mapper;
+use emu_core::error::{ImageError, StateError};
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::cartridge_rom;
+
+const POCKET_CAMERA: u8 = 0xFC;
+
+// Longest possible capture, in M-cycles
+const MAX_CAPTURE: u32 = 32446 + 512 + 16 * 0xFFFF;
+
+fn camera_cartridge(image: CameraImage) -> Mmu {
+    let mut mmu = Mmu::new(cartridge_rom(POCKET_CAMERA, 0x05, 0x04));
+    mmu.set_camera_image(image);
+    mmu.write_byte(0x0000, 0x0A);
+    mmu
+}
+
+fn uniform(value: u8) -> CameraImage {
+    CameraImage::from_gray(WIDTH, HEIGHT, vec![value; WIDTH * HEIGHT]).unwrap()
+}
+
+/// Sets the sensor registers: exposure, edge mode (A001) and ratio (A004), and the same
+/// thresholds for every pixel of the dithering matrix.
+fn configure(mmu: &mut Mmu, exposure: u16, edge_mode: u8, edge_ratio: u8, thresholds: [u8; 3]) {
+    mmu.write_byte(0x4000, 0x10);
+    mmu.write_byte(0xA001, edge_mode);
+    mmu.write_byte(0xA002, (exposure >> 8) as u8);
+    mmu.write_byte(0xA003, exposure as u8);
+    mmu.write_byte(0xA004, edge_ratio);
+    for i in 0..16 {
+        for (j, &threshold) in thresholds.iter().enumerate() {
+            mmu.write_byte(0xA006 + i * 3 + j as u16, threshold);
+        }
+    }
+}
+
+/// Starts a capture and waits for it to be over, returning how many M-cycles it took.
+fn capture(mmu: &mut Mmu) -> u32 {
+    mmu.write_byte(0x4000, 0x10);
+    mmu.write_byte(0xA000, 0x01);
+    let mut cycles = 0;
+    while mmu.peek_byte(0xA000) & 0x01 != 0 {
+        assert!(cycles < MAX_CAPTURE, "capture never ended");
+        mmu.tick(4);
+        cycles += 4;
+    }
+    mmu.write_byte(0x4000, 0x00);
+    cycles
+}
+
+/// Decodes the shade (0 white to 3 black) of a pixel of the picture stored in RAM bank 0.
+fn shade(mmu: &Mmu, x: usize, y: usize) -> u8 {
+    let row = 0xA100 + ((y / 8 * 16 + x / 8) * 16 + (y % 8) * 2) as u16;
+    let bit = 7 - (x % 8);
+    (mmu.peek_byte(row) >> bit & 1) | (mmu.peek_byte(row + 1) >> bit & 1) << 1
+}
+
+#[test]
+fn ram_has_16_banks() {
+    let mut mmu = camera_cartridge(uniform(0));
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::PocketCamera);
+    for bank in 0..16 {
+        mmu.write_byte(0x4000, bank);
+        mmu.write_byte(0xA000, bank + 0x40);
+    }
+    for bank in 0..16 {
+        mmu.write_byte(0x4000, bank);
+        assert_eq!(mmu.read_byte(0xA000), bank + 0x40);
+    }
+
+    // Reads don't need RAM to be enabled, writes do
+    mmu.write_byte(0x0000, 0x00);
+    mmu.write_byte(0xA000, 0x00);
+    assert_eq!(mmu.read_byte(0xA000), 0x4F);
+}
+
+#[test]
+fn states_with_invalid_banks_are_rejected() {
+    let mut mmu = camera_cartridge(uniform(0));
+    mmu.write_byte(0x4000, 0x10);
+    let mut state = mmu.save_cartridge_state();
+    assert_eq!(mmu.load_cartridge_state(&state), Ok(()));
+
+    // The RAM bank follows the RAM, the RAM enable and the ROM bank
+    let ram_bank = 4 + mmu.cartridge().ram().len() + 2;
+    assert_eq!(state[ram_bank], 0x10);
+    state[ram_bank] = 0x11;
+    assert_eq!(mmu.load_cartridge_state(&state), Err(StateError::InvalidValue(0x11)));
+}
+
+#[test]
+fn registers_are_write_only() {
+    let mut mmu = camera_cartridge(uniform(0));
+    configure(&mut mmu, 0x1234, 0x00, 0x00, [1, 2, 3]);
+    assert_eq!(mmu.read_byte(0xA002), 0x00);
+    assert_eq!(mmu.read_byte(0xA000), 0x00);
+}
+
+#[test]
+fn capture_takes_time_with_exposure() {
+    let mut mmu = camera_cartridge(uniform(0x80));
+    configure(&mut mmu, 0x0100, 0x80, 0x00, [0x40, 0x80, 0xC0]);
+    let short = capture(&mut mmu);
+    configure(&mut mmu, 0x0200, 0x80, 0x00, [0x40, 0x80, 0xC0]);
+    let long = capture(&mut mmu);
+    // The N bit is set, so the capture takes 32446 M-cycles plus the exposure (polled every 4 M-cycles)
+    let expected = 32446 + 16 * 0x0100;
+    assert!((expected..expected + 4).contains(&short), "{}", short);
+    assert_eq!(long - short, 16 * 0x0100);
+}
+
+#[test]
+fn exposure_and_dithering_thresholds() {
+    let mut mmu = camera_cartridge(uniform(0x80));
+    // Neutral exposure: 0x80 is between the second and third thresholds
+    configure(&mut mmu, 0x0800, 0x00, 0x00, [0x40, 0x80, 0xC0]);
+    capture(&mut mmu);
+    assert_eq!(shade(&mmu, 0, 0), 1);
+    assert_eq!(shade(&mmu, WIDTH - 1, HEIGHT - 1), 1);
+
+    // Half the exposure darkens the picture
+    configure(&mut mmu, 0x0400, 0x00, 0x00, [0x40, 0x80, 0xC0]);
+    capture(&mut mmu);
+    assert_eq!(shade(&mmu, 10, 20), 2);
+
+    // Inverted output
+    configure(&mut mmu, 0x0400, 0x00, 0x08, [0x40, 0x80, 0xC0]);
+    capture(&mut mmu);
+    assert_eq!(shade(&mmu, 10, 20), 1);
+}
+
+#[test]
+fn dithering_matrix_is_tiled() {
+    let mut mmu = camera_cartridge(uniform(0x80));
+    configure(&mut mmu, 0x0800, 0x00, 0x00, [0x40, 0x80, 0xC0]);
+    // Only the top left pixel of each 4x4 block has its thresholds raised
+    mmu.write_byte(0x4000, 0x10);
+    for (i, threshold) in [0xD0, 0xE0, 0xF0].into_iter().enumerate() {
+        mmu.write_byte(0xA006 + i as u16, threshold);
+    }
+    capture(&mut mmu);
+    for (x, y) in [(0, 0), (4, 0), (0, 4), (124, 108)] {
+        assert_eq!(shade(&mmu, x, y), 3, "({}, {})", x, y);
+    }
+    for (x, y) in [(1, 0), (0, 1), (5, 5), (127, 111)] {
+        assert_eq!(shade(&mmu, x, y), 1, "({}, {})", x, y);
+    }
+}
+
+#[test]
+fn edge_enhancement() {
+    // A bright vertical line on a gray background
+    let mut pixels = vec![0x80; WIDTH * HEIGHT];
+    for y in 0..HEIGHT {
+        pixels[y * WIDTH + 64] = 0xA0;
+    }
+    let image = CameraImage::from_gray(WIDTH, HEIGHT, pixels).unwrap();
+    let mut mmu = camera_cartridge(image);
+
+    // Without enhancement, the line is not bright enough to be white
+    configure(&mut mmu, 0x0800, 0x00, 0x20, [0x40, 0x80, 0xC0]);
+    capture(&mut mmu);
+    assert_eq!(shade(&mmu, 64, 50), 1);
+
+    // Horizontal enhancement makes it stand out, and darkens its sides
+    configure(&mut mmu, 0x0800, 0x20, 0x20, [0x40, 0x80, 0xC0]);
+    capture(&mut mmu);
+    assert_eq!(shade(&mmu, 64, 50), 0);
+    assert_eq!(shade(&mmu, 63, 50), 2);
+    assert_eq!(shade(&mmu, 60, 50), 1);
+
+    // Vertical enhancement doesn't see a vertical line
+    configure(&mut mmu, 0x0800, 0x40, 0x20, [0x40, 0x80, 0xC0]);
+    capture(&mut mmu);
+    assert_eq!(shade(&mmu, 64, 50), 1);
+}
+
+#[test]
+fn png_images_are_stretched_and_converted_to_gray() {
+    // 2x1 image: a red pixel and a white one
+    let mut png = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut png, 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header().unwrap();
+        writer.write_image_data(&[0xFF, 0x00, 0x00, 0xFF, 0xFF, 0xFF]).unwrap();
+    }
+    let image = CameraImage::from_png(&png).unwrap();
+    assert_eq!((image.width(), image.height()), (2, 1));
+
+    let mut mmu = camera_cartridge(image);
+    // Red is about 30% bright
+    configure(&mut mmu, 0x0800, 0x00, 0x00, [0x40, 0x80, 0xFF]);
+    capture(&mut mmu);
+    assert_eq!(shade(&mmu, 0, 0), 2);
+    assert_eq!(shade(&mmu, WIDTH / 2 - 1, HEIGHT - 1), 2);
+    assert_eq!(shade(&mmu, WIDTH / 2, 0), 0);
+}
+
+#[test]
+fn invalid_images() {
+    assert_eq!(
+        CameraImage::from_gray(4, 4, vec![0; 15]),
+        Err(ImageError::SizeMismatch { width: 4, height: 4, len: 15 })
+    );
+    assert!(matches!(CameraImage::from_png(b"not a png"), Err(ImageError::Png(_))));
+    assert!(matches!(CameraImage::load_png("tests/data/missing.png"), Err(ImageError::Io(_))));
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use emu_core::cartridge::{detect_type, CartridgeType, CgbSupport, Header, Licensee, Mapper};
+use emu_core::error::HeaderError;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{cartridge_rom, fix_header_checksum};
+
+#[test]
+fn parses_header_fields() {
+    let mut rom = cartridge_rom(0x1B, 0x02, 0x03);
+    rom[0x0134..0x0143].copy_from_slice(b"POKEMON_\0\0\0\0\0\0\0");
+    rom[0x0143] = 0x80;
+    rom[0x0146] = 0x03;
+    rom[0x014A] = 0x01;
+    rom[0x014B] = 0x01;
+    rom[0x014C] = 0x02;
+    fix_header_checksum(&mut rom);
+
+    let header = Header::parse(&rom).unwrap();
+    assert_eq!(header.title, "POKEMON_");
+    assert_eq!(header.manufacturer_code, None);
+    assert_eq!(header.cgb, CgbSupport::Enhanced);
+    assert_eq!(header.licensee, Licensee::Old(0x01));
+    assert!(!header.sgb, "SGB functions need the new licensee code");
+    assert!(!header.japanese);
+    assert_eq!(header.version, 2);
+    assert_eq!(header.rom_banks(), Some(8));
+    assert_eq!(header.ram_bytes(), Some(0x8000));
+    assert_eq!(
+        header.kind(),
+        Some(CartridgeType { mapper: Mapper::Mbc5, ram: true, battery: true, timer: false, rumble: false, sensor: false })
+    );
+    assert!(header.logo_valid());
+    assert!(header.header_checksum_valid());
+    assert!(!header.global_checksum_valid());
+    assert_eq!(header.validate(), Ok(()));
+}
+
+#[test]
+fn parses_new_licensee_and_manufacturer_code() {
+    let mut rom = cartridge_rom(0x00, 0x00, 0x00);
+    rom[0x0134..0x0144].copy_from_slice(b"ZELDA\0\0\0\0\0\0AZ7E\xC0");
+    rom[0x0144..0x0146].copy_from_slice(b"01");
+    rom[0x0146] = 0x03;
+    rom[0x014B] = 0x33;
+
+    let header = Header::parse(&rom).unwrap();
+    assert_eq!(header.title, "ZELDA");
+    assert_eq!(header.manufacturer_code.as_deref(), Some("AZ7E"));
+    assert_eq!(header.cgb, CgbSupport::Only);
+    assert_eq!(header.licensee, Licensee::New(*b"01"));
+    assert!(header.sgb);
+}
+
+#[test]
+fn global_checksum_covers_the_whole_rom() {
+    let mut rom = cartridge_rom(0x01, 0x01, 0x00);
+    let sum = rom.iter().fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16));
+    rom[0x014E..0x0150].copy_from_slice(&sum.to_be_bytes());
+    assert!(Header::parse(&rom).unwrap().global_checksum_valid());
+}
+
+#[test]
+fn reports_invalid_headers() {
+    assert_eq!(Header::parse(&[0; 0x14F]), Err(HeaderError::Truncated(0x14F)));
+
+    let validate = |patch: &dyn Fn(&mut Vec<u8>)| {
+        let mut rom = cartridge_rom(0x01, 0x01, 0x00);
+        patch(&mut rom);
+        Header::parse(&rom).unwrap().validate()
+    };
+    assert_eq!(validate(&|rom| rom[0x0110] ^= 1), Err(HeaderError::BadLogo));
+    assert_eq!(
+        validate(&|rom| rom[0x0134] = b'X'),
+        Err(HeaderError::HeaderChecksum { expected: 0xA5, computed: 0xA1 })
+    );
+    assert_eq!(
+        validate(&|rom| { rom[0x0147] = 0x04; fix_header_checksum(rom) }),
+        Err(HeaderError::UnknownCartridgeType(0x04))
+    );
+    assert_eq!(
+        validate(&|rom| { rom[0x0148] = 0x09; fix_header_checksum(rom) }),
+        Err(HeaderError::UnknownRomSize(0x09))
+    );
+    assert_eq!(
+        validate(&|rom| { rom[0x0149] = 0x06; fix_header_checksum(rom) }),
+        Err(HeaderError::UnknownRamSize(0x06))
+    );
+    assert_eq!(
+        validate(&|rom| rom.truncate(0x6000)),
+        Err(HeaderError::RomSizeMismatch { header: 0x10000, actual: 0x6000 })
+    );
+    assert_eq!(
+        validate(&|rom| { rom[0x0149] = 0x02; fix_header_checksum(rom) }),
+        Err(HeaderError::RamSizeMismatch { cartridge_type: 0x01, ram_size: 0x02 })
+    );
+}
+
+#[test]
+fn detects_mapper_from_header() {
+    assert_eq!(detect_type(&cartridge_rom(0x00, 0x00, 0x00)).mapper, Mapper::RomOnly);
+    assert_eq!(detect_type(&cartridge_rom(0x13, 0x03, 0x03)).mapper, Mapper::Mbc3);
+    assert_eq!(detect_type(&cartridge_rom(0xFF, 0x03, 0x03)).mapper, Mapper::HuC1);
+}
+
+#[test]
+fn guesses_mapper_of_homebrew_roms() {
+    // ROM-only type, but too large for it
+    let kind = detect_type(&cartridge_rom(0x00, 0x03, 0x00));
+    assert_eq!(kind.mapper, Mapper::Mbc1);
+    assert!(kind.ram && kind.battery);
+
+    // MBC1 type, but too large for it
+    assert_eq!(detect_type(&cartridge_rom(0x03, 0x07, 0x03)).mapper, Mapper::Mbc5);
+
+    // Unknown type
+    assert_eq!(detect_type(&cartridge_rom(0x42, 0x00, 0x00)).mapper, Mapper::RomOnly);
+    assert_eq!(detect_type(&cartridge_rom(0x42, 0x02, 0x00)).mapper, Mapper::Mbc1);
+
+    // No header at all
+    assert_eq!(detect_type(&[0x00, 0x18, 0xFD]).mapper, Mapper::RomOnly);
+}
+
+#[test]
+fn rom_only_cartridges_ignore_bank_switches() {
+    let mut mmu = Mmu::new(cartridge_rom(0x00, 0x00, 0x00));
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::RomOnly);
+    mmu.write_byte(0x2000, 0x00);
+    assert_eq!(mmu.peek_byte(0x4000), 1);
+}
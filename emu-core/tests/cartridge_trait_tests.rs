@@ -0,0 +1,165 @@
+mod common;
+
+use std::time::Duration;
+
+use emu_core::cartridge::rtc::CycleClock;
+use emu_core::cartridge::state::{StateReader, StateWriter};
+use emu_core::cartridge::{Cartridge, CartridgeType, Mapper};
+use emu_core::error::StateError;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::cartridge_rom;
+
+const MBC1_RAM_BATTERY: u8 = 0x03;
+const MBC3_TIMER_RAM_BATTERY: u8 = 0x10;
+
+/// Flash cart with 32KB banks, selected by writing 0x7FFF, and 8KB of RAM programmed through the ROM area.
+struct FlashCart {
+    rom: Vec<u8>,
+    ram: Vec<u8>,
+    bank: u8,
+    written: bool, // RAM was written since `take_save_written`
+}
+
+impl Cartridge for FlashCart {
+    fn read_rom(&self, addr: u16) -> u8 {
+        self.rom[(self.bank as usize * 0x8000 + addr as usize) % self.rom.len()]
+    }
+
+    fn write_rom(&mut self, addr: u16, val: u8) {
+        match addr {
+            0x7FFF => self.bank = val,
+            _ => {
+                self.ram[addr as usize & 0x1FFF] = val;
+                self.written = true;
+            },
+        }
+    }
+
+    fn read_ram(&self, addr: u16) -> u8 {
+        self.ram[addr as usize & 0x1FFF]
+    }
+
+    fn write_ram(&mut self, _addr: u16, _val: u8) {}
+
+    fn rom_bank(&self, addr: u16) -> u16 {
+        self.bank as u16 * 2 + (addr >> 14)
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn load_ram(&mut self, data: &[u8]) {
+        self.ram.copy_from_slice(data);
+    }
+
+    fn take_save_written(&mut self) -> bool {
+        std::mem::take(&mut self.written)
+    }
+
+    fn save_state(&mut self, state: &mut StateWriter) {
+        state.write_bytes(&self.ram);
+        state.write_u8(self.bank);
+    }
+
+    fn load_state(&mut self, state: &mut StateReader) -> Result<(), StateError> {
+        state.read_bytes(&mut self.ram)?;
+        self.bank = state.read_u8()?;
+        Ok(())
+    }
+}
+
+fn flash_cart() -> Mmu {
+    let mut rom = vec![0; 0x10000];
+    rom[0x0143] = 0x80;
+    rom[0x8000] = 0x42;
+    let cartridge = FlashCart { rom, ram: vec![0; 0x2000], bank: 0, written: false };
+    let kind = CartridgeType::from_code(MBC1_RAM_BATTERY).unwrap();
+    Mmu::with_cartridge(Box::new(cartridge), kind)
+}
+
+#[test]
+fn custom_cartridges_are_mapped() {
+    let mut mmu = flash_cart();
+    mmu.write_byte(0x7FFF, 0x01);
+    assert_eq!(mmu.peek_byte(0x0000), 0x42);
+    assert_eq!(mmu.cartridge().rom_bank(0x4000), 3);
+
+    mmu.write_byte(0x1234, 0x99);
+    assert_eq!(mmu.peek_byte(0xB234), 0x99);
+    assert_eq!(mmu.battery_ram().unwrap()[0x1234], 0x99);
+}
+
+#[test]
+fn custom_cartridges_mark_the_save_dirty() {
+    let mut mmu = flash_cart();
+    mmu.write_byte(0x7FFF, 0x01);
+    assert!(!mmu.save_dirty());
+    mmu.write_byte(0x1234, 0x99);
+    assert!(mmu.save_dirty());
+    assert_eq!(mmu.export_save().unwrap()[0x1234], 0x99);
+    assert!(!mmu.save_dirty());
+}
+
+#[test]
+fn custom_cartridges_are_saved_in_states() {
+    let mut mmu = flash_cart();
+    mmu.write_byte(0x7FFF, 0x01);
+    mmu.write_byte(0x0010, 0x55);
+    let state = mmu.save_cartridge_state();
+
+    let mut other = flash_cart();
+    other.load_cartridge_state(&state).unwrap();
+    assert_eq!(other.peek_byte(0x0000), 0x42);
+    assert_eq!(other.peek_byte(0xA010), 0x55);
+}
+
+#[test]
+fn mbc_registers_and_ram_are_restored() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x04, 0x03));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0x2000, 0x05);
+    mmu.write_byte(0xA000, 0x12);
+    let state = mmu.save_cartridge_state();
+
+    mmu.write_byte(0x2000, 0x01);
+    mmu.write_byte(0xA000, 0x34);
+    mmu.write_byte(0x0000, 0x00);
+    mmu.load_cartridge_state(&state).unwrap();
+    assert_eq!(mmu.cartridge().rom_bank(0x4000), 5);
+    assert_eq!(mmu.peek_byte(0xA000), 0x12);
+}
+
+#[test]
+fn clocks_resume_from_the_saved_time() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC3_TIMER_RAM_BATTERY, 0x01, 0x03));
+    mmu.set_time_source(Box::new(CycleClock::new()));
+    mmu.rtc().unwrap().advance(Duration::from_secs(90));
+    let state = mmu.save_cartridge_state();
+
+    // Time spent after saving (or before loading) doesn't count
+    mmu.rtc().unwrap().advance(Duration::from_secs(3600));
+    mmu.load_cartridge_state(&state).unwrap();
+    let time = mmu.rtc().unwrap().time();
+    assert_eq!((time.hours, time.minutes, time.seconds), (0, 1, 30));
+}
+
+#[test]
+fn rejects_states_of_other_cartridges() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x04, 0x03));
+    let state = mmu.save_cartridge_state();
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::Mbc1);
+
+    // 8KB of RAM instead of 32KB
+    let mut other = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x04, 0x02));
+    assert_eq!(
+        other.load_cartridge_state(&state),
+        Err(StateError::SizeMismatch { expected: 0x2000, actual: 0x8000 })
+    );
+    assert_eq!(mmu.load_cartridge_state(&state[..10]), Err(StateError::Truncated));
+
+    let mut longer = state.clone();
+    longer.push(0);
+    assert_eq!(mmu.load_cartridge_state(&longer), Err(StateError::TrailingData(1)));
+}
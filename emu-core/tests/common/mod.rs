@@ -1,4 +1,10 @@
-use std::{cell::RefCell, collections::HashMap};
+// Shared by several test crates, which each use only part of it
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use emu_core::cartridge::header::NINTENDO_LOGO;
+use emu_core::error::EmuError;
+use emu_core::interrupts::InterruptController;
 use emu_core::memory::MemoryBus;
 use serde::{Serialize, Deserialize};
 
@@ -19,7 +25,9 @@ pub enum MemoryCycle {
 
 pub struct MockMemory {
     data: HashMap<u16, u8>, // for unit tests, only stores relevant (addr, value)
-    cycles: RefCell<Vec<MemoryCycle>>, // for integration tests, stores all memory accesses
+    cycles: Vec<MemoryCycle>, // for integration tests, stores all memory accesses
+    error: Option<EmuError>, // first access to an address the test did not set up
+    interrupts: InterruptController, // not mapped in memory, tests drive it directly
 }
 
 impl MockMemory {
@@ -32,11 +40,11 @@ impl MockMemory {
     }
 
     pub fn get_cycles(&self) -> Vec<MemoryCycle> {
-         self.cycles.borrow().clone()
+         self.cycles.clone()
     }
 
-    pub fn clear_cycles(&self) {
-        self.cycles.borrow_mut().clear();
+    pub fn clear_cycles(&mut self) {
+        self.cycles.clear();
     }
 }
 
@@ -44,51 +52,95 @@ impl Default for MockMemory {
     fn default() -> Self {
         Self {
             data: HashMap::new(),
-            cycles: RefCell::new(Vec::new()),
+            cycles: Vec::new(),
+            error: None,
+            interrupts: InterruptController::new(),
         }
     }
 }
 
 impl MemoryBus for MockMemory {
-    fn read_byte(&self, addr: u16) -> u8 {
-        let val = *self.data.get(&addr).expect(&format!("Address 0x{:04X} not found in MockMemory", addr));
-        self.cycles.borrow_mut().push(MemoryCycle::BusActivity(addr, val, MemoryCycleType::Read));
+    fn read_byte(&mut self, addr: u16) -> u8 {
+        // Unmapped addresses read as open bus, and the error is reported by the next CPU step
+        let val = match self.data.get(&addr) {
+            Some(val) => *val,
+            None => {
+                self.error.get_or_insert(EmuError::UnmappedAddress(addr));
+                0xFF
+            },
+        };
+        self.cycles.push(MemoryCycle::BusActivity(addr, val, MemoryCycleType::Read));
         val
     }
 
-    fn read_word(&self, addr: u16) -> u16 {
-        let first = *self.data.get(&addr).expect(&format!("Address 0x{:04X} not found in MockMemory", addr));
-        let second = *self.data.get(&(addr+1)).expect(&format!("Address 0x{:04X} not found in MockMemory", addr+1));
+    fn write_byte(&mut self, addr: u16, val: u8) {
+        self.cycles.push(MemoryCycle::BusActivity(addr, val, MemoryCycleType::Write));
+        self.data.insert(addr, val);
+    }
 
-        self.cycles.borrow_mut().push(MemoryCycle::BusActivity(addr, first, MemoryCycleType::Read));
-        self.cycles.borrow_mut().push(MemoryCycle::BusActivity(addr+1, second, MemoryCycleType::Read));
+    fn tick(&mut self, num_cycles: u8) {
+        // Record a null cycle for each M-cycle without memory access
+        for _ in 0..num_cycles {
+            self.cycles.push(MemoryCycle::Null);
+        }
+    }
 
-        (first as u16) << 8 | second as u16
+    fn peek_byte(&self, addr: u16) -> u8 {
+        self.data.get(&addr).copied().unwrap_or(0xFF)
     }
 
-    fn write_byte(&mut self, addr: u16, val: u8) {
-        self.cycles.borrow_mut().push(MemoryCycle::BusActivity(addr, val, MemoryCycleType::Write));
+    fn poke_byte(&mut self, addr: u16, val: u8) {
         self.data.insert(addr, val);
     }
 
-    fn write_word(&mut self, addr: u16, val: u16) {
-        let high = (val >> 8) as u8;
-        let low = (val & 0xFF) as u8;
+    fn take_error(&mut self) -> Option<EmuError> {
+        self.error.take()
+    }
 
-        self.cycles.borrow_mut().push(MemoryCycle::BusActivity(addr, high, MemoryCycleType::Write));
-        self.cycles.borrow_mut().push(MemoryCycle::BusActivity(addr+1, low, MemoryCycleType::Write));
+    fn interrupts(&self) -> &InterruptController {
+        &self.interrupts
+    }
 
-        self.data.insert(addr, high);
-        self.data.insert(addr+1, low);
+    fn interrupts_mut(&mut self) -> &mut InterruptController {
+        &mut self.interrupts
     }
+}
 
-    fn tick(&mut self, num_cycles: u8) {
-        // TODO: Clean this up if it's not needed
-        // memory cicles are already recorded in read/write methods and tick_internal
+/// Builds a 32KB ROM-only cartridge image with `program` placed at the entry point (0x0100),
+/// and `handler` placed at every interrupt vector (0x0040 - 0x0060).
+pub fn rom_with_program(program: &[u8], handler: &[u8]) -> Vec<u8> {
+    let mut rom = vec![0; 0x8000];
+    for vector in [0x40, 0x48, 0x50, 0x58, 0x60] {
+        rom[vector..vector + handler.len()].copy_from_slice(handler);
     }
+    rom[0x100..0x100 + program.len()].copy_from_slice(program);
+    rom
+}
 
-    fn tick_internal(&mut self) {
-        // Record a null cycle for internal CPU operations (no memory access)
-        self.cycles.borrow_mut().push(MemoryCycle::Null);
+/// Builds a cartridge image with a valid header for the given type and size codes.
+/// The first two bytes of every ROM bank hold its number (little-endian), so that tests can tell banks apart.
+pub fn cartridge_rom(cartridge_type: u8, rom_size: u8, ram_size: u8) -> Vec<u8> {
+    let banks = 2usize << rom_size;
+    let mut rom = vec![0; banks * 0x4000];
+    for bank in 0..banks {
+        rom[bank * 0x4000] = bank as u8;
+        rom[bank * 0x4000 + 1] = (bank >> 8) as u8;
     }
+    rom[0x0104..0x0134].copy_from_slice(&NINTENDO_LOGO);
+    rom[0x0134..0x0138].copy_from_slice(b"TEST");
+    rom[0x0147] = cartridge_type;
+    rom[0x0148] = rom_size;
+    rom[0x0149] = ram_size;
+    fix_header_checksum(&mut rom);
+    rom
+}
+
+/// Returns the number of the ROM bank mapped at an address (see `cartridge_rom`).
+pub fn bank_at(mmu: &impl MemoryBus, addr: u16) -> u16 {
+    u16::from_le_bytes([mmu.peek_byte(addr), mmu.peek_byte(addr + 1)])
+}
+
+/// Recomputes the header checksum (0x014D) after the header was modified.
+pub fn fix_header_checksum(rom: &mut [u8]) {
+    rom[0x014D] = rom[0x0134..=0x014C].iter().fold(0u8, |sum, &byte| sum.wrapping_sub(byte).wrapping_sub(1));
 }
@@ -53,7 +53,7 @@ impl CpuState {
     }
 
     /// Create a Cpu from a CpuState
-    fn into_cpu(&self, mmu: MockMemory) -> Cpu<MockMemory> {
+    fn to_cpu(&self, mmu: MockMemory) -> Cpu<MockMemory> {
         let mut cpu = Cpu::new(mmu);
         cpu.reg.a = self.a;
         cpu.reg.b = self.b;
@@ -68,14 +68,11 @@ impl CpuState {
 
         // Write RAM values
         for (addr, val) in &self.ram {
-            cpu.mmu.write_byte(*addr, *val);
+            cpu.mmu.poke_byte(*addr, *val);
         }
 
         // Loading the prefetched opcode
-        cpu.prefetched = cpu.mmu.read_byte(cpu.reg.pc-1);
-
-        // Clear any cycles recorded during setup
-        cpu.mmu.clear_cycles();
+        cpu.prefetched = cpu.mmu.peek_byte(cpu.reg.pc-1);
 
         cpu
     }
@@ -257,10 +254,12 @@ opcode_tests! {
 fn run_single_test(test: &CpuTest) {
     // Initialize MockMemory and CPU
     let mmu = MockMemory::default();
-    let mut cpu = test.initial.into_cpu(mmu);
+    let mut cpu = test.initial.to_cpu(mmu);
 
-    // Run a single CPU tick
-    cpu.tick();
+    // Run a single CPU step
+    if let Err(err) = cpu.try_step() {
+        panic!("{} in test '{}'", err, test.name);
+    }
 
     // Compare final CPU state
     let final_state = CpuState::from_cpu(&cpu);
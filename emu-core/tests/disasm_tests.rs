@@ -0,0 +1,96 @@
+mod common;
+
+use emu_core::cpu::opcodes::{Condition, Mnemonic, Reg16, Reg8};
+use emu_core::disasm::{decode, Arg};
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{rom_with_program, MockMemory};
+
+fn disasm(bytes: &[u8]) -> String {
+    let mut mem = MockMemory::default();
+    for (i, &byte) in bytes.iter().enumerate() {
+        mem.poke_byte(0x0100 + i as u16, byte);
+    }
+    let instr = decode(&mem, 0x0100);
+    assert_eq!(instr.bytes(), bytes, "length of {}", instr);
+    instr.to_string()
+}
+
+#[test]
+fn formats_in_rgbds_syntax() {
+    let cases: &[(&[u8], &str)] = &[
+        (&[0x00], "nop"),
+        (&[0x2A], "ld a, [hl+]"),
+        (&[0x32], "ld [hl-], a"),
+        (&[0x0A], "ld a, [bc]"),
+        (&[0x20, 0x4E], "jr nz, $0150"),
+        (&[0x18, 0xFE], "jr $0100"),
+        (&[0x01, 0x34, 0x12], "ld bc, $1234"),
+        (&[0x3E, 0x0F], "ld a, $0F"),
+        (&[0x08, 0x00, 0xC0], "ld [$C000], sp"),
+        (&[0xEA, 0x00, 0xC0], "ld [$C000], a"),
+        (&[0xE0, 0x44], "ldh [$FF44], a"),
+        (&[0xF2], "ldh a, [c]"),
+        (&[0xF8, 0xFE], "ld hl, sp-2"),
+        (&[0xF8, 0x05], "ld hl, sp+5"),
+        (&[0xE8, 0xFE], "add sp, -2"),
+        (&[0x09], "add hl, bc"),
+        (&[0x80], "add a, b"),
+        (&[0xCE, 0x01], "adc a, $01"),
+        (&[0x90], "sub b"),
+        (&[0xAF], "xor a"),
+        (&[0xFE, 0x90], "cp $90"),
+        (&[0xE9], "jp hl"),
+        (&[0xC2, 0x50, 0x01], "jp nz, $0150"),
+        (&[0xCD, 0x00, 0x40], "call $4000"),
+        (&[0xD8], "ret c"),
+        (&[0xFF], "rst $38"),
+        (&[0xF5], "push af"),
+        (&[0x10, 0x00], "stop"),
+        (&[0xCB, 0x7C], "bit 7, h"),
+        (&[0xCB, 0x86], "res 0, [hl]"),
+        (&[0xCB, 0x37], "swap a"),
+        (&[0xD3], "db $D3"),
+    ];
+    for (bytes, expected) in cases {
+        assert_eq!(disasm(bytes), *expected, "{:02X?}", bytes);
+    }
+}
+
+#[test]
+fn decodes_structured_operands() {
+    let mut mem = MockMemory::default();
+    for (i, byte) in [0x20, 0xFC, 0xCB, 0x5E, 0xFA, 0x34, 0x12].into_iter().enumerate() {
+        mem.poke_byte(0x0200 + i as u16, byte);
+    }
+
+    let jr = decode(&mem, 0x0200);
+    assert_eq!(jr.mnemonic, Mnemonic::Jr);
+    assert_eq!(jr.length, 2);
+    assert_eq!(jr.operands, [Some(Arg::Cond(Condition::NZ)), Some(Arg::Target(0x01FE))]);
+
+    let bit = decode(&mem, jr.next_addr());
+    assert_eq!(bit.mnemonic, Mnemonic::Bit);
+    assert_eq!(bit.length, 2);
+    assert_eq!(bit.operands, [Some(Arg::Bit(3)), Some(Arg::Indirect(Reg16::HL))]);
+
+    let ld = decode(&mem, bit.next_addr());
+    assert_eq!(ld.addr, 0x0204);
+    assert_eq!(ld.length, 3);
+    assert_eq!(ld.operands, [Some(Arg::Reg8(Reg8::A)), Some(Arg::Addr16(0x1234))]);
+    assert_eq!(ld.bank, None);
+}
+
+#[test]
+fn alternate_format_prefixes_the_location() {
+    let mut rom = rom_with_program(&[0xC3, 0x50, 0x01], &[]);
+    rom.resize(0x10000, 0);
+    rom[0x0147] = 0x01; // MBC1
+    rom[0x8000] = 0xC9; // start of bank 2
+    let mut mmu = Mmu::new(rom);
+    mmu.write_byte(0x2000, 0x02);
+
+    assert_eq!(format!("{:#}", decode(&mmu, 0x0100)), "00:0100 jp $0150");
+    assert_eq!(format!("{:#}", decode(&mmu, 0x4000)), "02:4000 ret");
+    assert_eq!(format!("{:#}", decode(&mmu, 0xC000)), "C000 nop");
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use emu_core::cpu::cpu::Cpu;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::rom_with_program;
+
+const INC_B: u8 = 0x04;
+const HALT: u8 = 0x76;
+const EI: u8 = 0xFB;
+const NOP: u8 = 0x00;
+
+/// Creates a CPU running `program` from 0x0100, with the VBlank interrupt enabled.
+/// The first tick executes the NOP that `Cpu::new` starts with and fetches the first opcode.
+fn setup(program: &[u8]) -> Cpu<Mmu> {
+    // Interrupt handlers increment D so that dispatches can be observed
+    let mut cpu = Cpu::new(Mmu::new(rom_with_program(program, &[0x14])));
+    cpu.mmu.poke_byte(0xFFFF, 0x01);
+    cpu.tick();
+    cpu
+}
+
+/// Returns the address on top of the stack.
+fn return_addr(cpu: &Cpu<Mmu>) -> u16 {
+    let low = cpu.mmu.peek_byte(cpu.reg.sp) as u16;
+    let high = cpu.mmu.peek_byte(cpu.reg.sp.wrapping_add(1)) as u16;
+    (high << 8) | low
+}
+
+#[test]
+fn halt_waits_for_interrupt_and_dispatches_with_ime() {
+    let mut cpu = setup(&[EI, NOP, HALT, INC_B]);
+    cpu.tick(); // EI
+    cpu.tick(); // NOP (IME is now set)
+    cpu.tick(); // HALT
+
+    for _ in 0..10 {
+        cpu.tick();
+    }
+    assert_eq!(cpu.reg.b, 0, "CPU should stay halted while no interrupt is pending");
+
+    cpu.mmu.poke_byte(0xFF0F, 0x01);
+    cpu.tick(); // dispatch
+    assert_eq!(cpu.reg.pc, 0x0041, "PC should point after the handler's first opcode");
+    cpu.tick(); // INC D
+
+    assert_eq!(cpu.reg.d, 1, "VBlank handler should have run");
+    assert_eq!(cpu.reg.b, 0, "instruction after HALT should not run before the handler");
+    assert_eq!(cpu.mmu.peek_byte(0xFF0F) & 0x01, 0, "IF bit should be acknowledged");
+    // The return address is the instruction following HALT
+    assert_eq!(return_addr(&cpu), 0x0103);
+}
+
+#[test]
+fn halt_wakes_without_dispatch_when_ime_is_cleared() {
+    let mut cpu = setup(&[HALT, INC_B, INC_B]);
+    cpu.tick(); // HALT
+    cpu.tick();
+    assert_eq!(cpu.reg.b, 0);
+
+    cpu.mmu.poke_byte(0xFF0F, 0x01);
+    cpu.tick();
+
+    assert_eq!(cpu.reg.b, 1, "execution should resume after HALT");
+    assert_eq!(cpu.reg.d, 0, "no interrupt should be dispatched");
+    assert_eq!(cpu.mmu.peek_byte(0xFF0F) & 0x01, 0x01, "IF bit should stay set");
+}
+
+#[test]
+fn halt_bug_reads_next_byte_twice() {
+    let mut cpu = setup(&[HALT, INC_B, NOP]);
+    cpu.mmu.poke_byte(0xFF0F, 0x01);
+
+    cpu.tick(); // HALT, does not halt and fetches INC B without incrementing PC
+    assert_eq!(cpu.prefetched, INC_B);
+    assert!(cpu.doctor_log_state().contains("PC:0101 PCMEM:04,04,00"));
+
+    cpu.tick(); // INC B
+    assert!(cpu.doctor_log_state().contains("PC:0101 PCMEM:04,00"));
+    cpu.tick(); // INC B, again
+
+    assert_eq!(cpu.reg.b, 2);
+    assert_eq!(cpu.reg.d, 0);
+    assert_eq!(cpu.reg.pc, 0x0103);
+}
+
+#[test]
+fn halt_bug_after_ei_returns_to_halt() {
+    let mut cpu = setup(&[EI, HALT, INC_B]);
+    cpu.mmu.poke_byte(0xFF0F, 0x01);
+
+    cpu.tick(); // EI
+    cpu.tick(); // HALT with the bug
+    cpu.tick(); // dispatch
+    cpu.tick(); // INC D
+
+    assert_eq!(cpu.reg.d, 1);
+    // The handler returns to HALT itself, which will execute again
+    assert_eq!(return_addr(&cpu), 0x0101);
+}
+
+#[test]
+fn peripherals_keep_running_while_halted() {
+    // LD A,0x04; LDH (IE),A; LD A,0xFE; LDH (TIMA),A; LD A,0x05; LDH (TAC),A; HALT; INC B
+    let mut cpu = setup(&[0x3E, 0x04, 0xE0, 0xFF, 0x3E, 0xFE, 0xE0, 0x05, 0x3E, 0x05, 0xE0, 0x07, HALT, INC_B]);
+    for _ in 0..7 {
+        cpu.tick();
+    }
+    assert_eq!(cpu.reg.b, 0, "CPU should be halted");
+
+    // A single tick skips straight to the timer overflow
+    cpu.tick();
+    assert_eq!(cpu.mmu.peek_byte(0xFF0F) & 0x04, 0x04, "timer interrupt should be requested");
+    assert_eq!(cpu.mmu.peek_byte(0xFF05), 0x00, "TIMA should be reloaded from TMA");
+
+    cpu.tick();
+    assert_eq!(cpu.reg.b, 1, "timer interrupt should wake the CPU up");
+}
@@ -0,0 +1,184 @@
+mod common;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use emu_core::cartridge::rtc::CycleClock;
+use emu_core::cartridge::Mapper;
+use emu_core::infrared::InfraredLink;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{bank_at, cartridge_rom};
+
+const HUC3: u8 = 0xFE;
+const HUC1: u8 = 0xFF;
+
+// M-cycles per minute at normal speed
+const M_CYCLES_PER_MINUTE: u32 = 60 * 1_048_576;
+
+/// Other end of an infrared link, shared with the test.
+#[derive(Clone, Default)]
+struct Remote {
+    led: Rc<Cell<bool>>,
+    light: Rc<Cell<bool>>,
+}
+
+impl InfraredLink for Remote {
+    fn set_led(&mut self, on: bool) {
+        self.led.set(on);
+    }
+
+    fn receiving(&self) -> bool {
+        self.light.get()
+    }
+}
+
+/// Creates a HuC3 cartridge with a clock driven by emulated time.
+fn huc3_cartridge() -> Mmu {
+    let mut mmu = Mmu::new(cartridge_rom(HUC3, 0x06, 0x03));
+    mmu.set_time_source(Box::new(CycleClock::new()));
+    mmu
+}
+
+/// Runs a HuC3 command, the way games do, and returns the response.
+fn huc3_command(mmu: &mut Mmu, command: u8) -> u8 {
+    mmu.write_byte(0x0000, 0x0B);
+    mmu.write_byte(0xA000, command);
+    mmu.write_byte(0x0000, 0x0D);
+    mmu.write_byte(0xA000, 0xFE);
+    mmu.write_byte(0x0000, 0x0C);
+    let response = mmu.read_byte(0xA000) & 0x0F;
+    mmu.write_byte(0x0000, 0x00);
+    response
+}
+
+fn huc3_set_address(mmu: &mut Mmu, addr: u8) {
+    huc3_command(mmu, 0x40 | (addr & 0x0F));
+    huc3_command(mmu, 0x50 | addr >> 4);
+}
+
+/// Sets the clock, in minutes of the day and days.
+fn huc3_set_time(mmu: &mut Mmu, minutes: u16, days: u16) {
+    huc3_set_address(mmu, 0x00);
+    for val in [minutes, days] {
+        for i in 0..3 {
+            huc3_command(mmu, 0x30 | (val >> (4 * i)) as u8 & 0x0F);
+        }
+    }
+    huc3_command(mmu, 0x61);
+}
+
+fn huc3_time(mmu: &mut Mmu) -> (u16, u16) {
+    huc3_command(mmu, 0x60);
+    huc3_set_address(mmu, 0x00);
+    let mut read = || (0..3).fold(0, |val, i| val | (huc3_command(mmu, 0x10) as u16) << (4 * i));
+    let minutes = read();
+    (minutes, read())
+}
+
+#[test]
+fn huc1_banking() {
+    let mut mmu = Mmu::new(cartridge_rom(HUC1, 0x05, 0x03));
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::HuC1);
+    mmu.write_byte(0x2000, 0x25);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x25);
+    assert_eq!(bank_at(&mmu, 0x0000), 0);
+
+    // RAM needs no enabling
+    mmu.write_byte(0x4000, 0x02);
+    mmu.write_byte(0xA000, 0x42);
+    mmu.write_byte(0x4000, 0x00);
+    assert_eq!(mmu.read_byte(0xA000), 0x00);
+    mmu.write_byte(0x4000, 0x02);
+    assert_eq!(mmu.read_byte(0xA000), 0x42);
+    assert_eq!(mmu.battery_ram().unwrap()[2 * 0x2000], 0x42);
+}
+
+#[test]
+fn huc1_infrared_port() {
+    let mut mmu = Mmu::new(cartridge_rom(HUC1, 0x05, 0x03));
+    let remote = Remote::default();
+    mmu.set_infrared_link(Box::new(remote.clone()));
+
+    mmu.write_byte(0x0000, 0x0E);
+    assert_eq!(mmu.read_byte(0xA000), 0xC0);
+    remote.light.set(true);
+    assert_eq!(mmu.read_byte(0xA000), 0xC1);
+
+    mmu.write_byte(0xA000, 0x01);
+    assert!(remote.led.get());
+    mmu.write_byte(0xA000, 0x00);
+    assert!(!remote.led.get());
+
+    // Back to RAM: writes don't reach the LED anymore
+    mmu.write_byte(0x0000, 0x00);
+    mmu.write_byte(0xA000, 0x01);
+    assert!(!remote.led.get());
+    assert_eq!(mmu.read_byte(0xA000), 0x01);
+}
+
+#[test]
+fn huc3_ram_modes() {
+    let mut mmu = huc3_cartridge();
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::HuC3);
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0x4000, 0x01);
+    mmu.write_byte(0xA123, 0x42);
+    assert_eq!(mmu.read_byte(0xA123), 0x42);
+
+    // Mode 0 maps RAM read-only
+    mmu.write_byte(0x0000, 0x00);
+    mmu.write_byte(0xA123, 0x24);
+    assert_eq!(mmu.read_byte(0xA123), 0x42);
+}
+
+#[test]
+fn huc3_memory_commands() {
+    let mut mmu = huc3_cartridge();
+    assert_eq!(huc3_command(&mut mmu, 0x62), 0x1);
+
+    huc3_set_address(&mut mmu, 0x40);
+    huc3_command(&mut mmu, 0x35);
+    huc3_command(&mut mmu, 0x3A);
+    huc3_set_address(&mut mmu, 0x40);
+    assert_eq!(huc3_command(&mut mmu, 0x10), 0x5);
+    assert_eq!(huc3_command(&mut mmu, 0x10), 0xA);
+}
+
+#[test]
+fn huc3_clock_counts_minutes_and_days() {
+    let mut mmu = huc3_cartridge();
+    huc3_set_time(&mut mmu, 23 * 60 + 59, 0x123);
+    assert_eq!(huc3_time(&mut mmu), (23 * 60 + 59, 0x123));
+
+    for _ in 0..M_CYCLES_PER_MINUTE / 128 {
+        mmu.tick(128);
+    }
+    assert_eq!(huc3_time(&mut mmu), (0, 0x124));
+}
+
+#[test]
+fn huc3_tone_generator() {
+    let mut mmu = huc3_cartridge();
+    let tones = Rc::new(RefCell::new(Vec::new()));
+    let played = tones.clone();
+    mmu.set_tone_callback(Box::new(move |tone| played.borrow_mut().push(tone)));
+
+    huc3_set_address(&mut mmu, 0x26);
+    huc3_command(&mut mmu, 0x33);
+    huc3_command(&mut mmu, 0x6E);
+    assert_eq!(*tones.borrow(), [0x3]);
+}
+
+#[test]
+fn huc3_infrared_port() {
+    let mut mmu = huc3_cartridge();
+    let remote = Remote::default();
+    mmu.set_infrared_link(Box::new(remote.clone()));
+    remote.light.set(true);
+
+    mmu.write_byte(0x0000, 0x0E);
+    assert_eq!(mmu.read_byte(0xA000), 0xC1);
+    mmu.write_byte(0xA000, 0x01);
+    assert!(remote.led.get());
+}
@@ -0,0 +1,161 @@
+mod common;
+
+use emu_core::cpu::cpu::Cpu;
+use emu_core::interrupts::Interrupt;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{rom_with_program, MemoryCycle, MemoryCycleType, MockMemory};
+
+const INC_B: u8 = 0x04;
+const EI: u8 = 0xFB;
+const DI: u8 = 0xF3;
+const RETI: u8 = 0xD9;
+const NOP: u8 = 0x00;
+
+/// Creates a CPU running `program` from 0x0100, with interrupt handlers made of `handler`.
+/// The first tick executes the NOP that `Cpu::new` starts with and fetches the first opcode.
+fn setup(program: &[u8], handler: &[u8]) -> Cpu<Mmu> {
+    let mut cpu = Cpu::new(Mmu::new(rom_with_program(program, handler)));
+    cpu.tick();
+    cpu
+}
+
+#[test]
+fn dispatch_takes_five_m_cycles() {
+    let mut mmu = MockMemory::default();
+    for (addr, opcode) in [(0xC000, EI), (0xC001, NOP), (0xC002, NOP), (0x0040, INC_B)] {
+        mmu.poke_byte(addr, opcode);
+    }
+    let mut cpu = Cpu::new(mmu);
+    cpu.reg.pc = 0xC001;
+    cpu.reg.sp = 0xD000;
+    cpu.prefetched = EI;
+    cpu.tick(); // EI
+    cpu.tick(); // NOP (IME is now set)
+
+    cpu.mmu.interrupts_mut().write_ie(0x01);
+    cpu.mmu.interrupts_mut().request(Interrupt::VBlank);
+    cpu.mmu.clear_cycles();
+    cpu.try_step().unwrap();
+
+    use MemoryCycleType::*;
+    assert_eq!(cpu.mmu.get_cycles(), vec![
+        MemoryCycle::Null,
+        MemoryCycle::Null,
+        MemoryCycle::BusActivity(0xCFFF, 0xC0, Write),
+        MemoryCycle::BusActivity(0xCFFE, 0x02, Write),
+        MemoryCycle::BusActivity(0x0040, INC_B, Read),
+    ]);
+    assert_eq!(cpu.mmu.interrupts().read_if(), 0xE0, "IF bit should be acknowledged");
+    assert_eq!(cpu.reg.b, 0, "the handler should not run during the dispatch step");
+
+    cpu.tick();
+    assert_eq!(cpu.reg.b, 1);
+}
+
+#[test]
+fn ie_overwritten_by_push_cancels_dispatch() {
+    // LD SP,0x0000; EI; NOP
+    let mut cpu = setup(&[0x31, 0x00, 0x00, EI, NOP, NOP], &[INC_B]);
+    cpu.mmu.poke_byte(0xFFFF, 0x04); // Timer
+    for _ in 0..3 {
+        cpu.tick();
+    }
+    cpu.mmu.poke_byte(0xFF0F, 0x04);
+    cpu.tick();
+
+    // The high byte of PC (0x01) was pushed to IE, disabling the timer interrupt
+    assert_eq!(cpu.mmu.peek_byte(0xFFFF), 0x01);
+    assert_eq!(cpu.reg.pc, 0x0001, "dispatch should be cancelled and jump to 0x0000");
+    assert_eq!(cpu.mmu.peek_byte(0xFF0F) & 0x04, 0x04, "IF should be left untouched");
+    assert_eq!(cpu.mmu.peek_byte(0xFFFE), 0x05, "low byte of PC should still be pushed");
+}
+
+#[test]
+fn ie_overwritten_by_push_can_redirect_dispatch() {
+    // LD SP,0x0000; EI; NOP
+    let mut cpu = setup(&[0x31, 0x00, 0x00, EI, NOP, NOP], &[INC_B]);
+    cpu.mmu.poke_byte(0xFFFF, 0x04); // Timer
+    for _ in 0..3 {
+        cpu.tick();
+    }
+    // VBlank is requested but not enabled, until the push enables it instead of the timer
+    cpu.mmu.poke_byte(0xFF0F, 0x05);
+    cpu.tick();
+
+    assert_eq!(cpu.reg.pc, 0x0041, "VBlank should be dispatched instead");
+    assert_eq!(cpu.mmu.peek_byte(0xFF0F) & 0x05, 0x04, "only the VBlank request should be acknowledged");
+}
+
+#[test]
+fn ei_takes_effect_after_next_instruction() {
+    let mut cpu = setup(&[EI, INC_B, INC_B], &[RETI]);
+    cpu.mmu.poke_byte(0xFFFF, 0x01);
+    cpu.mmu.poke_byte(0xFF0F, 0x01);
+
+    cpu.tick(); // EI
+    cpu.tick(); // INC B
+    assert_eq!(cpu.reg.b, 1, "the instruction following EI should run before any dispatch");
+    cpu.tick(); // dispatch
+    assert_eq!(cpu.reg.pc, 0x0041);
+    assert_eq!(cpu.reg.b, 1);
+}
+
+#[test]
+fn di_takes_effect_immediately() {
+    let mut cpu = setup(&[EI, NOP, DI, INC_B, INC_B], &[RETI]);
+    cpu.mmu.poke_byte(0xFFFF, 0x01);
+
+    cpu.tick(); // EI
+    cpu.tick(); // NOP (IME is now set)
+    cpu.tick(); // DI
+    cpu.mmu.poke_byte(0xFF0F, 0x01);
+    cpu.tick(); // INC B
+
+    assert_eq!(cpu.reg.b, 1);
+    assert_eq!(cpu.reg.pc, 0x0105, "no interrupt should be dispatched after DI");
+}
+
+#[test]
+fn di_right_after_ei_keeps_interrupts_disabled() {
+    let mut cpu = setup(&[EI, DI, INC_B, INC_B], &[RETI]);
+    cpu.mmu.poke_byte(0xFFFF, 0x01);
+    cpu.mmu.poke_byte(0xFF0F, 0x01);
+
+    for _ in 0..3 {
+        cpu.tick();
+    }
+    assert_eq!(cpu.reg.b, 1);
+    assert_eq!(cpu.reg.pc, 0x0104, "no interrupt should be dispatched");
+}
+
+#[test]
+fn reti_enables_interrupts_immediately() {
+    let mut cpu = setup(&[EI, NOP, INC_B], &[RETI]);
+    cpu.mmu.poke_byte(0xFFFF, 0x01);
+    cpu.mmu.poke_byte(0xFF0F, 0x01);
+
+    cpu.tick(); // EI
+    cpu.tick(); // NOP
+    cpu.tick(); // dispatch
+    cpu.mmu.poke_byte(0xFF0F, 0x01); // requested again while in the handler
+    cpu.tick(); // RETI
+    assert_eq!(cpu.reg.pc, 0x0103);
+
+    cpu.tick(); // dispatched again, before INC B
+    assert_eq!(cpu.reg.pc, 0x0041);
+    assert_eq!(cpu.reg.b, 0);
+}
+
+#[test]
+fn highest_priority_interrupt_is_dispatched_first() {
+    let mut cpu = setup(&[EI, NOP, NOP], &[RETI]);
+    cpu.mmu.poke_byte(0xFFFF, 0x1F);
+    cpu.mmu.poke_byte(0xFF0F, 0x14); // Timer and Joypad
+
+    cpu.tick(); // EI
+    cpu.tick(); // NOP
+    cpu.tick(); // dispatch
+    assert_eq!(cpu.reg.pc, 0x0051);
+    assert_eq!(cpu.mmu.peek_byte(0xFF0F), 0xF0);
+}
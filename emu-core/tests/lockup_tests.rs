@@ -0,0 +1,46 @@
+mod common;
+
+use emu_core::cpu::cpu::{Cpu, CpuState, LockReason};
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::rom_with_program;
+
+const ILLEGAL_OPCODES: [u8; 11] = [0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD];
+
+#[test]
+fn illegal_opcodes_lock_the_cpu() {
+    for opcode in ILLEGAL_OPCODES {
+        // NOP; <illegal>; INC B
+        let mut cpu = Cpu::new(Mmu::new(rom_with_program(&[0x00, opcode, 0x04], &[])));
+        for _ in 0..10 {
+            cpu.tick();
+        }
+
+        let reason = LockReason::IllegalOpcode { pc: 0x0101, opcode };
+        assert_eq!(cpu.state(), CpuState::Locked(reason));
+        assert_eq!(cpu.lock_reason(), Some(reason));
+        assert_eq!(cpu.reg.b, 0, "nothing runs after 0x{:02X}", opcode);
+    }
+}
+
+#[test]
+fn locked_cpu_ignores_interrupts_but_clocks_the_bus() {
+    // EI; NOP; 0xDD
+    let mut cpu = Cpu::new(Mmu::new(rom_with_program(&[0xFB, 0x00, 0xDD], &[0x04])));
+    for _ in 0..4 {
+        cpu.tick();
+    }
+    assert!(cpu.lock_reason().is_some());
+
+    cpu.mmu.poke_byte(0xFFFF, 0x1F);
+    cpu.mmu.poke_byte(0xFF0F, 0x1F);
+    let sp = cpu.reg.sp;
+    let div = cpu.mmu.peek_byte(0xFF04);
+    for _ in 0..1000 {
+        cpu.tick();
+    }
+
+    assert_eq!(cpu.reg.sp, sp, "no interrupt should be dispatched");
+    assert_eq!(cpu.reg.b, 0);
+    assert_ne!(cpu.mmu.peek_byte(0xFF04), div, "the divider should keep counting");
+}
@@ -0,0 +1,156 @@
+mod common;
+
+use emu_core::cartridge::header::NINTENDO_LOGO;
+use emu_core::cartridge::Mapper;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{bank_at, cartridge_rom};
+
+const MBC1_RAM_BATTERY: u8 = 0x03;
+
+#[test]
+fn bank1_selects_the_bank_at_0x4000() {
+    // 2MB ROM
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x06, 0x00));
+    assert_eq!(bank_at(&mmu, 0x4000), 1);
+
+    for (val, bank) in [(0x02, 2), (0x1F, 0x1F), (0x00, 1), (0xE5, 0x05), (0x20, 1), (0x3F, 0x1F)] {
+        mmu.write_byte(0x2000, val);
+        assert_eq!(bank_at(&mmu, 0x4000), bank, "BANK1 = 0x{:02X}", val);
+    }
+    mmu.write_byte(0x3FFF, 0x03);
+    assert_eq!(bank_at(&mmu, 0x4000), 3);
+    assert_eq!(bank_at(&mmu, 0x0000), 0);
+}
+
+#[test]
+fn bank2_selects_upper_rom_bits_and_skips_banks_0x20_0x40_0x60() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x06, 0x00));
+    for bank2 in 0..4 {
+        mmu.write_byte(0x4000, bank2 | 0xFC);
+        mmu.write_byte(0x2000, 0x00);
+        assert_eq!(bank_at(&mmu, 0x4000), (bank2 as u16) << 5 | 1);
+        mmu.write_byte(0x2000, 0x07);
+        assert_eq!(bank_at(&mmu, 0x4000), (bank2 as u16) << 5 | 7);
+        assert_eq!(bank_at(&mmu, 0x0000), 0, "mode 0 keeps bank 0 at 0x0000");
+    }
+}
+
+#[test]
+fn mode1_maps_bank2_at_0x0000() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x06, 0x00));
+    mmu.write_byte(0x4000, 0x02);
+    mmu.write_byte(0x6000, 0x01);
+    assert_eq!(bank_at(&mmu, 0x0000), 0x40);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x41);
+    assert_eq!(mmu.rom_bank(0x0000), Some(0x40));
+
+    mmu.write_byte(0x7FFF, 0xFE);
+    assert_eq!(bank_at(&mmu, 0x0000), 0x00);
+}
+
+#[test]
+fn rom_banks_wrap_around_small_roms() {
+    // 256KB ROM: BANK2 and the upper bit of BANK1 are not connected
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x03, 0x00));
+    mmu.write_byte(0x2000, 0x12);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x02);
+    mmu.write_byte(0x2000, 0x10);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x00, "bank 0 is reachable through the zero check");
+
+    mmu.write_byte(0x4000, 0x03);
+    mmu.write_byte(0x6000, 0x01);
+    assert_eq!(bank_at(&mmu, 0x0000), 0x00);
+    assert_eq!(mmu.rom_bank(0x4000), Some(0x00));
+}
+
+#[test]
+fn ram_is_enabled_by_0x0a_in_the_low_nibble() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x01, 0x02));
+    mmu.write_byte(0xA000, 0x42);
+    assert_eq!(mmu.read_byte(0xA000), 0xFF, "RAM is disabled at power-on");
+
+    for (val, enabled) in [(0x0A, true), (0x00, false), (0xFA, true), (0x0B, false), (0x1A, true)] {
+        mmu.write_byte(0x1FFF, val);
+        mmu.write_byte(0xA000, 0x42);
+        assert_eq!(mmu.read_byte(0xA000) == 0x42, enabled, "RAMG = 0x{:02X}", val);
+        mmu.write_byte(0x0000, 0x0A);
+        mmu.write_byte(0xA000, 0x00);
+    }
+}
+
+#[test]
+fn ram_banks_are_switched_in_mode1_only() {
+    // 32KB RAM
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x01, 0x03));
+    mmu.write_byte(0x0000, 0x0A);
+    for bank in 0..4 {
+        mmu.write_byte(0x4000, bank);
+        mmu.write_byte(0xA123, 0x10 + bank);
+    }
+    assert_eq!(mmu.read_byte(0xA123), 0x13, "mode 0 always maps bank 0");
+
+    mmu.write_byte(0x6000, 0x01);
+    for bank in 0..4 {
+        mmu.write_byte(0x4000, bank);
+        mmu.write_byte(0xB123, 0x20 + bank);
+    }
+    for bank in 0..4 {
+        mmu.write_byte(0x4000, bank);
+        assert_eq!(mmu.read_byte(0xB123), 0x20 + bank);
+        assert_eq!(mmu.read_byte(0xA123), if bank == 0 { 0x13 } else { 0x00 });
+    }
+}
+
+#[test]
+fn small_ram_is_mirrored() {
+    // 8KB RAM: BANK2 is not connected to RAM
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x01, 0x02));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0x6000, 0x01);
+    mmu.write_byte(0xA000, 0x42);
+    mmu.write_byte(0x4000, 0x03);
+    assert_eq!(mmu.read_byte(0xA000), 0x42);
+
+    // 2KB RAM
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x01, 0x01));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0xA000, 0x42);
+    assert_eq!(mmu.read_byte(0xA800), 0x42);
+    assert_eq!(mmu.read_byte(0xB800), 0x42);
+}
+
+#[test]
+fn cartridges_without_ram_read_open_bus() {
+    let mut mmu = Mmu::new(cartridge_rom(0x01, 0x01, 0x00));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0xA000, 0x42);
+    assert_eq!(mmu.read_byte(0xA000), 0xFF);
+}
+
+#[test]
+fn multicarts_are_detected_and_wired_differently() {
+    // 1MB ROM with a game header every 256KB
+    let mut rom = cartridge_rom(0x01, 0x05, 0x00);
+    for game in 1..4 {
+        let header = game * 0x40000 + 0x0104;
+        rom[header..header + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+    }
+    let mut mmu = Mmu::new(rom.clone());
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::Mbc1Multicart);
+
+    mmu.write_byte(0x4000, 0x01);
+    mmu.write_byte(0x2000, 0x03);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x13);
+    mmu.write_byte(0x2000, 0x13);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x13, "the upper bit of BANK1 is not connected");
+    mmu.write_byte(0x2000, 0x10);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x10);
+    mmu.write_byte(0x6000, 0x01);
+    mmu.write_byte(0x4000, 0x03);
+    assert_eq!(bank_at(&mmu, 0x0000), 0x30);
+
+    // A single header is a regular 1MB cartridge
+    rom[0x40104] = 0;
+    assert_eq!(Mmu::new(rom).cartridge_type().mapper, Mapper::Mbc1);
+}
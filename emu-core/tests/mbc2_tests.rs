@@ -0,0 +1,81 @@
+mod common;
+
+use emu_core::cartridge::Mapper;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{bank_at, cartridge_rom};
+
+const MBC2_BATTERY: u8 = 0x06;
+
+#[test]
+fn address_bit_8_selects_the_register() {
+    // 256KB ROM
+    let mut mmu = Mmu::new(cartridge_rom(MBC2_BATTERY, 0x03, 0x00));
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::Mbc2);
+    assert_eq!(bank_at(&mmu, 0x4000), 1);
+
+    for (addr, val, bank) in [(0x2100, 0x05, 5), (0x0100, 0x0F, 15), (0x3FFF, 0x00, 1), (0x21FF, 0xF3, 3)] {
+        mmu.write_byte(addr, val);
+        assert_eq!(bank_at(&mmu, 0x4000), bank, "0x{:02X} written at 0x{:04X}", val, addr);
+    }
+
+    // With bit 8 clear, writes go to RAMG
+    mmu.write_byte(0x2000, 0x0A);
+    assert_eq!(bank_at(&mmu, 0x4000), 3);
+    mmu.write_byte(0xA000, 0x05);
+    assert_eq!(mmu.read_byte(0xA000), 0xF5);
+
+    // No registers in 0x4000 - 0x7FFF
+    mmu.write_byte(0x4100, 0x02);
+    mmu.write_byte(0x6000, 0x00);
+    assert_eq!(bank_at(&mmu, 0x4000), 3);
+    assert_eq!(bank_at(&mmu, 0x0000), 0);
+}
+
+#[test]
+fn rom_banks_wrap_around_small_roms() {
+    // 128KB ROM
+    let mut mmu = Mmu::new(cartridge_rom(MBC2_BATTERY, 0x02, 0x00));
+    mmu.write_byte(0x2100, 0x09);
+    assert_eq!(bank_at(&mmu, 0x4000), 1);
+    assert_eq!(mmu.rom_bank(0x4000), Some(1));
+}
+
+#[test]
+fn ram_is_512_half_bytes_mirrored() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC2_BATTERY, 0x01, 0x00));
+    mmu.write_byte(0xA000, 0x01);
+    assert_eq!(mmu.read_byte(0xA000), 0xFF, "RAM is disabled at power-on");
+
+    mmu.write_byte(0x0000, 0x0A);
+    for addr in 0xA000..0xA200 {
+        mmu.write_byte(addr, addr as u8);
+    }
+    for addr in 0xA000..=0xBFFF_u16 {
+        assert_eq!(mmu.read_byte(addr), 0xF0 | (addr as u8 & 0x0F), "0x{:04X}", addr);
+    }
+
+    mmu.write_byte(0xBFFF, 0xA7);
+    assert_eq!(mmu.read_byte(0xA1FF), 0xF7);
+
+    mmu.write_byte(0x00FF, 0x00);
+    assert_eq!(mmu.read_byte(0xA000), 0xFF);
+}
+
+#[test]
+fn battery_ram_can_be_saved_and_restored() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC2_BATTERY, 0x01, 0x00));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0xA010, 0x3C);
+    let save = mmu.battery_ram().unwrap().to_vec();
+    assert_eq!(save.len(), 512);
+    assert_eq!(save[0x10], 0x0C);
+
+    let mut mmu = Mmu::new(cartridge_rom(MBC2_BATTERY, 0x01, 0x00));
+    mmu.load_battery_ram(&save);
+    mmu.write_byte(0x0000, 0x0A);
+    assert_eq!(mmu.read_byte(0xA010), 0xFC);
+
+    // Without a battery, there is nothing to save
+    assert_eq!(Mmu::new(cartridge_rom(0x05, 0x01, 0x00)).battery_ram(), None);
+}
@@ -0,0 +1,200 @@
+mod common;
+
+use std::time::Duration;
+
+use emu_core::cartridge::rtc::{CycleClock, RtcTime};
+use emu_core::cartridge::Mapper;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{bank_at, cartridge_rom};
+
+const MBC3_TIMER_RAM_BATTERY: u8 = 0x10;
+const MBC3_RAM_BATTERY: u8 = 0x13;
+
+// M-cycles per second at normal speed
+const M_CYCLES_PER_SECOND: u32 = 1_048_576;
+
+/// Creates an MBC3 cartridge with a clock driven by emulated time, and enables RAM and the clock.
+fn rtc_cartridge() -> Mmu {
+    let mut mmu = Mmu::new(cartridge_rom(MBC3_TIMER_RAM_BATTERY, 0x01, 0x03));
+    mmu.set_time_source(Box::new(CycleClock::new()));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu
+}
+
+fn run_for_seconds(mmu: &mut Mmu, seconds: u32) {
+    for _ in 0..seconds * M_CYCLES_PER_SECOND / 128 {
+        mmu.tick(128);
+    }
+}
+
+fn latch(mmu: &mut Mmu) {
+    mmu.write_byte(0x6000, 0x00);
+    mmu.write_byte(0x6000, 0x01);
+}
+
+fn read_rtc(mmu: &mut Mmu, reg: u8) -> u8 {
+    mmu.write_byte(0x4000, reg);
+    mmu.read_byte(0xA000)
+}
+
+fn write_rtc(mmu: &mut Mmu, reg: u8, val: u8) {
+    mmu.write_byte(0x4000, reg);
+    mmu.write_byte(0xA000, val);
+}
+
+#[test]
+fn rom_bank_has_7_bits() {
+    // 2MB ROM
+    let mut mmu = Mmu::new(cartridge_rom(MBC3_RAM_BATTERY, 0x06, 0x03));
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::Mbc3);
+    for (val, bank) in [(0x00, 1), (0x20, 0x20), (0x7F, 0x7F), (0x80, 1), (0xC5, 0x45)] {
+        mmu.write_byte(0x2000, val);
+        assert_eq!(bank_at(&mmu, 0x4000), bank, "0x{:02X}", val);
+    }
+    assert_eq!(bank_at(&mmu, 0x0000), 0);
+}
+
+#[test]
+fn ram_banks() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC3_RAM_BATTERY, 0x01, 0x03));
+    mmu.write_byte(0x0000, 0x0A);
+    for bank in 0..4 {
+        mmu.write_byte(0x4000, bank);
+        mmu.write_byte(0xA000, 0x10 + bank);
+    }
+    for bank in 0..4 {
+        mmu.write_byte(0x4000, bank);
+        assert_eq!(mmu.read_byte(0xA000), 0x10 + bank);
+    }
+
+    // No clock on this cartridge
+    mmu.write_byte(0x4000, 0x08);
+    mmu.write_byte(0xA000, 0x05);
+    assert_eq!(mmu.read_byte(0xA000), 0xFF);
+
+    mmu.write_byte(0x0000, 0x00);
+    mmu.write_byte(0x4000, 0x01);
+    assert_eq!(mmu.read_byte(0xA000), 0xFF);
+}
+
+#[test]
+fn mbc30_is_detected_from_its_sizes() {
+    // 4MB ROM
+    let mut mmu = Mmu::new(cartridge_rom(MBC3_RAM_BATTERY, 0x07, 0x03));
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::Mbc30);
+    mmu.write_byte(0x2000, 0xC5);
+    assert_eq!(bank_at(&mmu, 0x4000), 0xC5);
+
+    // 64KB RAM
+    let mut mmu = Mmu::new(cartridge_rom(MBC3_RAM_BATTERY, 0x01, 0x05));
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::Mbc30);
+    mmu.write_byte(0x0000, 0x0A);
+    for bank in 0..8 {
+        mmu.write_byte(0x4000, bank);
+        mmu.write_byte(0xA000, 0x10 + bank);
+    }
+    for bank in 0..8 {
+        mmu.write_byte(0x4000, bank);
+        assert_eq!(mmu.read_byte(0xA000), 0x10 + bank);
+    }
+}
+
+#[test]
+fn clock_counts_emulated_time() {
+    let mut mmu = rtc_cartridge();
+    run_for_seconds(&mut mmu, 3);
+    latch(&mut mmu);
+    assert_eq!(read_rtc(&mut mmu, 0x08), 3);
+
+    // The latched registers don't move until the next latch
+    run_for_seconds(&mut mmu, 1);
+    assert_eq!(read_rtc(&mut mmu, 0x08), 3);
+    mmu.write_byte(0x6000, 0x01);
+    assert_eq!(read_rtc(&mut mmu, 0x08), 3, "latching needs 0x00 first");
+    latch(&mut mmu);
+    assert_eq!(read_rtc(&mut mmu, 0x08), 4);
+}
+
+#[test]
+fn clock_registers_carry_into_each_other() {
+    let mut mmu = rtc_cartridge();
+    write_rtc(&mut mmu, 0x08, 59);
+    write_rtc(&mut mmu, 0x09, 59);
+    write_rtc(&mut mmu, 0x0A, 23);
+    write_rtc(&mut mmu, 0x0B, 0xFF);
+    write_rtc(&mut mmu, 0x0C, 0x00);
+    run_for_seconds(&mut mmu, 1);
+    latch(&mut mmu);
+    let regs: Vec<u8> = (0x08..=0x0C).map(|reg| read_rtc(&mut mmu, reg)).collect();
+    assert_eq!(regs, [0, 0, 0, 0x00, 0x01]);
+
+    // Day counter overflow
+    write_rtc(&mut mmu, 0x08, 59);
+    write_rtc(&mut mmu, 0x09, 59);
+    write_rtc(&mut mmu, 0x0A, 23);
+    write_rtc(&mut mmu, 0x0B, 0xFF);
+    write_rtc(&mut mmu, 0x0C, 0x01);
+    run_for_seconds(&mut mmu, 1);
+    latch(&mut mmu);
+    assert_eq!(read_rtc(&mut mmu, 0x0B), 0x00);
+    assert_eq!(read_rtc(&mut mmu, 0x0C), 0x80, "day carry set, and day bit 8 cleared");
+}
+
+#[test]
+fn out_of_range_values_wrap_without_carry() {
+    let mut mmu = rtc_cartridge();
+    write_rtc(&mut mmu, 0x08, 0xFE);
+    write_rtc(&mut mmu, 0x09, 0x05);
+    run_for_seconds(&mut mmu, 2);
+    latch(&mut mmu);
+    assert_eq!(read_rtc(&mut mmu, 0x08), 0x00, "0x3E -> 0x3F -> 0x00");
+    assert_eq!(read_rtc(&mut mmu, 0x09), 0x05);
+}
+
+#[test]
+fn halt_stops_the_clock() {
+    let mut mmu = rtc_cartridge();
+    write_rtc(&mut mmu, 0x0C, 0x40);
+    run_for_seconds(&mut mmu, 2);
+    latch(&mut mmu);
+    assert_eq!(read_rtc(&mut mmu, 0x08), 0);
+    assert_eq!(read_rtc(&mut mmu, 0x0C), 0x40);
+
+    write_rtc(&mut mmu, 0x0C, 0x00);
+    run_for_seconds(&mut mmu, 2);
+    latch(&mut mmu);
+    assert_eq!(read_rtc(&mut mmu, 0x08), 2);
+}
+
+#[test]
+fn writing_seconds_restarts_the_second() {
+    let mut mmu = rtc_cartridge();
+    for _ in 0..M_CYCLES_PER_SECOND / 2 / 128 {
+        mmu.tick(128);
+    }
+    write_rtc(&mut mmu, 0x08, 10);
+    for _ in 0..M_CYCLES_PER_SECOND * 3 / 4 / 128 {
+        mmu.tick(128);
+    }
+    latch(&mut mmu);
+    assert_eq!(read_rtc(&mut mmu, 0x08), 10);
+}
+
+#[test]
+fn clock_can_be_set_and_advanced_by_the_host() {
+    let mut mmu = rtc_cartridge();
+    let rtc = mmu.rtc().unwrap();
+    rtc.set_time(RtcTime { days: 300, hours: 12, ..RtcTime::default() });
+    rtc.advance(Duration::from_secs(212 * 24 * 3600 + 61));
+    assert_eq!(
+        rtc.time(),
+        RtcTime { seconds: 1, minutes: 1, hours: 12, days: 0, halted: false, day_carry: true }
+    );
+
+    latch(&mut mmu);
+    assert_eq!(read_rtc(&mut mmu, 0x09), 1);
+    assert!(mmu.rtc().unwrap().latched().day_carry);
+
+    assert!(Mmu::new(cartridge_rom(MBC3_RAM_BATTERY, 0x01, 0x03)).rtc().is_none());
+}
@@ -0,0 +1,80 @@
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use emu_core::cartridge::Mapper;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{bank_at, cartridge_rom};
+
+const MBC5_RAM_BATTERY: u8 = 0x1B;
+const MBC5_RUMBLE_RAM_BATTERY: u8 = 0x1E;
+
+#[test]
+fn rom_bank_has_9_bits_and_bank_0_is_selectable() {
+    // 8MB ROM
+    let mut mmu = Mmu::new(cartridge_rom(MBC5_RAM_BATTERY, 0x08, 0x04));
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::Mbc5);
+    assert_eq!(bank_at(&mmu, 0x4000), 1);
+
+    mmu.write_byte(0x2000, 0x00);
+    assert_eq!(bank_at(&mmu, 0x4000), 0);
+    mmu.write_byte(0x2FFF, 0xA5);
+    assert_eq!(bank_at(&mmu, 0x4000), 0xA5);
+    mmu.write_byte(0x3000, 0xFF);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x1A5);
+    mmu.write_byte(0x2000, 0x20);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x120);
+    mmu.write_byte(0x3FFF, 0x00);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x20);
+    assert_eq!(bank_at(&mmu, 0x0000), 0);
+
+    // No banking mode register
+    mmu.write_byte(0x6000, 0x01);
+    assert_eq!(bank_at(&mmu, 0x0000), 0);
+}
+
+#[test]
+fn ram_has_16_banks_and_needs_exactly_0x0a() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC5_RAM_BATTERY, 0x01, 0x04));
+    mmu.write_byte(0x0000, 0x1A);
+    mmu.write_byte(0xA000, 0x42);
+    assert_eq!(mmu.read_byte(0xA000), 0xFF);
+
+    mmu.write_byte(0x0000, 0x0A);
+    for bank in 0..16 {
+        mmu.write_byte(0x4000, bank);
+        mmu.write_byte(0xBFFF, 0x10 + bank);
+    }
+    for bank in 0..16 {
+        mmu.write_byte(0x4000, bank);
+        assert_eq!(mmu.read_byte(0xBFFF), 0x10 + bank);
+    }
+}
+
+#[test]
+fn rumble_motor_is_reported_to_the_host() {
+    let states = Rc::new(RefCell::new(Vec::new()));
+    let mut mmu = Mmu::new(cartridge_rom(MBC5_RUMBLE_RAM_BATTERY, 0x01, 0x03));
+    let recorder = states.clone();
+    mmu.set_rumble_callback(Box::new(move |on| recorder.borrow_mut().push(on)));
+
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0x4000, 0x01);
+    mmu.write_byte(0xA000, 0x11);
+    mmu.write_byte(0x4000, 0x09); // motor on, bank 1
+    assert_eq!(mmu.read_byte(0xA000), 0x11, "bit 3 is not a RAM bank bit");
+    mmu.write_byte(0x4000, 0x0B);
+    mmu.write_byte(0x4000, 0x02);
+    assert_eq!(*states.borrow(), [true, false]);
+
+    // Without a motor, bit 3 selects RAM banks
+    let mut mmu = Mmu::new(cartridge_rom(MBC5_RAM_BATTERY, 0x01, 0x04));
+    mmu.set_rumble_callback(Box::new(|_| panic!("no motor")));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0x4000, 0x09);
+    mmu.write_byte(0xA000, 0x99);
+    mmu.write_byte(0x4000, 0x01);
+    assert_eq!(mmu.read_byte(0xA000), 0x00);
+}
@@ -0,0 +1,141 @@
+mod common;
+
+use emu_core::cartridge::Mapper;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::cartridge_rom;
+
+const MBC6: u8 = 0x20;
+const RAM_SIZE: usize = 0x8000;
+
+/// Creates a 1MB MBC6 cartridge with 32KB of RAM. Every 8KB bank starts with its number.
+fn mbc6_cartridge() -> Mmu {
+    let mut rom = cartridge_rom(MBC6, 0x05, 0x03);
+    for bank in 1..rom.len() / 0x2000 {
+        rom[bank * 0x2000] = bank as u8;
+    }
+    Mmu::new(rom)
+}
+
+/// Maps a flash bank in the window at 0x4000 - 0x5FFF.
+fn map_flash(mmu: &mut Mmu, bank: u8) {
+    mmu.write_byte(0x2000, bank);
+    mmu.write_byte(0x2800, 0x08);
+}
+
+/// Writes the unlock sequence preceding flash commands, to flash addresses 0x5555 and 0x2AAA.
+fn flash_unlock(mmu: &mut Mmu) {
+    map_flash(mmu, 2);
+    mmu.write_byte(0x5555, 0xAA);
+    map_flash(mmu, 1);
+    mmu.write_byte(0x4AAA, 0x55);
+}
+
+fn flash_command(mmu: &mut Mmu, command: u8) {
+    flash_unlock(mmu);
+    map_flash(mmu, 2);
+    mmu.write_byte(0x5555, command);
+}
+
+fn enable_flash_writes(mmu: &mut Mmu) {
+    mmu.write_byte(0x1000, 0x01);
+    mmu.write_byte(0x0C00, 0x01);
+}
+
+#[test]
+fn rom_windows_are_independent() {
+    let mut mmu = mbc6_cartridge();
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::Mbc6);
+    mmu.write_byte(0x2000, 0x21);
+    mmu.write_byte(0x3000, 0x42);
+    assert_eq!(mmu.read_byte(0x4000), 0x21);
+    assert_eq!(mmu.read_byte(0x6000), 0x42);
+    assert_eq!(mmu.read_byte(0x2000), 0x01);
+}
+
+#[test]
+fn ram_windows_are_independent() {
+    let mut mmu = mbc6_cartridge();
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0x0400, 0x03);
+    mmu.write_byte(0x0800, 0x05);
+    mmu.write_byte(0xA000, 0x33);
+    mmu.write_byte(0xB000, 0x55);
+    let save = mmu.battery_ram().unwrap();
+    assert_eq!(save[3 * 0x1000], 0x33);
+    assert_eq!(save[5 * 0x1000], 0x55);
+
+    mmu.write_byte(0x0000, 0x00);
+    assert_eq!(mmu.read_byte(0xA000), 0xFF);
+}
+
+#[test]
+fn flash_is_erased_and_readable_through_both_windows() {
+    let mut mmu = mbc6_cartridge();
+    map_flash(&mut mmu, 0x10);
+    mmu.write_byte(0x3000, 0x10);
+    mmu.write_byte(0x3800, 0x08);
+    assert_eq!(mmu.read_byte(0x4000), 0xFF);
+    assert_eq!(mmu.read_byte(0x6000), 0xFF);
+}
+
+#[test]
+fn flash_id_mode() {
+    let mut mmu = mbc6_cartridge();
+    enable_flash_writes(&mut mmu);
+    flash_command(&mut mmu, 0x90);
+    assert_eq!(mmu.read_byte(0x4000), 0xC2);
+    assert_eq!(mmu.read_byte(0x4001), 0x81);
+    mmu.write_byte(0x4000, 0xF0);
+    assert_eq!(mmu.read_byte(0x4000), 0xFF);
+}
+
+#[test]
+fn flash_program_and_erase() {
+    let mut mmu = mbc6_cartridge();
+
+    // Ignored while flash writes are disabled
+    flash_command(&mut mmu, 0xA0);
+    map_flash(&mut mmu, 0x09);
+    mmu.write_byte(0x4123, 0x42);
+    assert_eq!(mmu.read_byte(0x4123), 0xFF);
+    assert!(!mmu.save_dirty());
+
+    enable_flash_writes(&mut mmu);
+    flash_command(&mut mmu, 0xA0);
+    map_flash(&mut mmu, 0x09);
+    assert!(!mmu.save_dirty());
+    mmu.write_byte(0x4123, 0x42);
+    assert_eq!(mmu.read_byte(0x4123), 0x42);
+    assert!(mmu.save_dirty());
+    assert_eq!(mmu.battery_ram().unwrap()[RAM_SIZE + 9 * 0x2000 + 0x123], 0x42);
+
+    // Programming only clears bits
+    flash_command(&mut mmu, 0xA0);
+    map_flash(&mut mmu, 0x09);
+    mmu.write_byte(0x4123, 0x81);
+    assert_eq!(mmu.read_byte(0x4123), 0x00);
+
+    // Sector erase (64KB: banks 8 - 15)
+    flash_command(&mut mmu, 0x80);
+    flash_unlock(&mut mmu);
+    map_flash(&mut mmu, 0x08);
+    mmu.write_byte(0x4000, 0x30);
+    map_flash(&mut mmu, 0x09);
+    assert_eq!(mmu.read_byte(0x4123), 0xFF);
+}
+
+#[test]
+fn ram_and_flash_are_saved() {
+    let mut mmu = mbc6_cartridge();
+    let mut save = vec![0; RAM_SIZE + 0x100000];
+    save[0x10] = 0x12;
+    save[RAM_SIZE + 0x2000 * 3] = 0x34;
+    mmu.load_battery_ram(&save);
+
+    mmu.write_byte(0x0000, 0x0A);
+    assert_eq!(mmu.read_byte(0xA010), 0x12);
+    map_flash(&mut mmu, 3);
+    assert_eq!(mmu.read_byte(0x4000), 0x34);
+    assert_eq!(mmu.battery_ram().unwrap(), save.as_slice());
+}
@@ -0,0 +1,191 @@
+mod common;
+
+use emu_core::cartridge::Mapper;
+use emu_core::error::StateError;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::cartridge_rom;
+
+const MBC7: u8 = 0x22;
+
+// EEPROM register lines
+const CS: u8 = 0x80;
+const CLK: u8 = 0x40;
+const DI: u8 = 0x02;
+const DO: u8 = 0x01;
+
+fn mbc7_cartridge() -> Mmu {
+    let mut mmu = Mmu::new(cartridge_rom(MBC7, 0x06, 0x00));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0x4000, 0x40);
+    mmu
+}
+
+fn read_accel(mmu: &mut Mmu) -> (u16, u16) {
+    let x = u16::from_le_bytes([mmu.read_byte(0xA020), mmu.read_byte(0xA030)]);
+    let y = u16::from_le_bytes([mmu.read_byte(0xA040), mmu.read_byte(0xA050)]);
+    (x, y)
+}
+
+/// Clocks bits into the EEPROM (MSB first), and returns the bits it output on DO.
+fn shift_bits(mmu: &mut Mmu, bits: u32, count: u32) -> u32 {
+    let mut out = 0;
+    for i in (0..count).rev() {
+        let di = if bits >> i & 1 != 0 { DI } else { 0 };
+        mmu.write_byte(0xA080, CS | di);
+        mmu.write_byte(0xA080, CS | CLK | di);
+        out = (out << 1) | (mmu.read_byte(0xA080) & DO) as u32;
+    }
+    out
+}
+
+/// Sends a command: a start bit, a 2-bit opcode and an 8-bit address, followed by `data` if any.
+/// Returns the 16 bits output after the command if it reads.
+fn eeprom_command(mmu: &mut Mmu, opcode: u32, addr: u32, data: Option<u16>) -> u16 {
+    mmu.write_byte(0xA080, 0x00);
+    mmu.write_byte(0xA080, CS);
+    shift_bits(mmu, 0b1 << 10 | opcode << 8 | addr, 11);
+    let out = match data {
+        Some(word) => shift_bits(mmu, word as u32, 16),
+        None if opcode == 0b10 => shift_bits(mmu, 0, 16),
+        None => 0,
+    };
+    mmu.write_byte(0xA080, 0x00);
+    out as u16
+}
+
+const READ: u32 = 0b10;
+const WRITE: u32 = 0b01;
+const ERASE: u32 = 0b11;
+const EXTENDED: u32 = 0b00;
+const EWEN: u32 = 0b11 << 6;
+const EWDS: u32 = 0b00;
+const ERAL: u32 = 0b10 << 6;
+const WRAL: u32 = 0b01 << 6;
+
+#[test]
+fn registers_need_both_enables() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC7, 0x06, 0x00));
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::Mbc7);
+    assert_eq!(mmu.read_byte(0xA060), 0xFF);
+    mmu.write_byte(0x0000, 0x0A);
+    assert_eq!(mmu.read_byte(0xA060), 0xFF);
+    mmu.write_byte(0x4000, 0x40);
+    assert_eq!(mmu.read_byte(0xA060), 0x00);
+    assert_eq!(mmu.read_byte(0xA070), 0xFF);
+    assert_eq!(mmu.read_byte(0xB060), 0xFF, "registers are only mapped at 0xA000 - 0xAFFF");
+}
+
+#[test]
+fn rom_bank_can_be_0() {
+    let mut mmu = mbc7_cartridge();
+    mmu.write_byte(0x2000, 0x00);
+    assert_eq!(mmu.peek_byte(0x4000), 0);
+    mmu.write_byte(0x2000, 0x45);
+    assert_eq!(mmu.peek_byte(0x4000), 0x45);
+}
+
+#[test]
+fn accelerometer_is_latched_after_being_erased() {
+    let mut mmu = mbc7_cartridge();
+    mmu.set_tilt(1.0, -0.5);
+    assert_eq!(read_accel(&mut mmu), (0x8000, 0x8000));
+
+    mmu.write_byte(0xA010, 0xAA);
+    assert_eq!(read_accel(&mut mmu), (0x8000, 0x8000), "latching needs an erase first");
+
+    mmu.write_byte(0xA000, 0x55);
+    mmu.write_byte(0xA010, 0xAA);
+    assert_eq!(read_accel(&mut mmu), (0x81D0 + 0x70, 0x81D0 - 0x38));
+
+    mmu.set_tilt(0.0, 0.0);
+    mmu.write_byte(0xA010, 0xAA);
+    assert_eq!(read_accel(&mut mmu), (0x81D0 + 0x70, 0x81D0 - 0x38));
+    mmu.write_byte(0xA000, 0x55);
+    assert_eq!(read_accel(&mut mmu), (0x8000, 0x8000));
+    mmu.write_byte(0xA010, 0xAA);
+    assert_eq!(read_accel(&mut mmu), (0x81D0, 0x81D0));
+}
+
+#[test]
+fn eeprom_writes_need_ewen() {
+    let mut mmu = mbc7_cartridge();
+    assert_eq!(eeprom_command(&mut mmu, READ, 0x05, None), 0xFFFF);
+
+    eeprom_command(&mut mmu, WRITE, 0x05, Some(0x1234));
+    assert_eq!(eeprom_command(&mut mmu, READ, 0x05, None), 0xFFFF);
+
+    eeprom_command(&mut mmu, EXTENDED, EWEN, None);
+    eeprom_command(&mut mmu, WRITE, 0x05, Some(0x1234));
+    eeprom_command(&mut mmu, WRITE, 0x7F, Some(0xBEEF));
+    assert_eq!(eeprom_command(&mut mmu, READ, 0x05, None), 0x1234);
+    assert_eq!(eeprom_command(&mut mmu, READ, 0xFF, None), 0xBEEF, "the upper address bit is ignored");
+
+    eeprom_command(&mut mmu, ERASE, 0x05, None);
+    assert_eq!(eeprom_command(&mut mmu, READ, 0x05, None), 0xFFFF);
+
+    eeprom_command(&mut mmu, EXTENDED, EWDS, None);
+    eeprom_command(&mut mmu, WRITE, 0x05, Some(0x5678));
+    assert_eq!(eeprom_command(&mut mmu, READ, 0x05, None), 0xFFFF);
+}
+
+#[test]
+fn eeprom_write_and_erase_all() {
+    let mut mmu = mbc7_cartridge();
+    eeprom_command(&mut mmu, EXTENDED, EWEN, None);
+    eeprom_command(&mut mmu, EXTENDED, WRAL, Some(0xA55A));
+    for addr in [0x00, 0x40, 0x7F] {
+        assert_eq!(eeprom_command(&mut mmu, READ, addr, None), 0xA55A);
+    }
+    eeprom_command(&mut mmu, EXTENDED, ERAL, None);
+    assert_eq!(eeprom_command(&mut mmu, READ, 0x40, None), 0xFFFF);
+}
+
+#[test]
+fn eeprom_ignores_leading_zeros_and_reports_ready() {
+    let mut mmu = mbc7_cartridge();
+    mmu.write_byte(0xA080, CS);
+    shift_bits(&mut mmu, 0, 5);
+    shift_bits(&mut mmu, 0b1_00_11 << 6, 11); // EWEN
+    mmu.write_byte(0xA080, 0x00);
+    assert_eq!(mmu.read_byte(0xA080) & DO, DO);
+
+    eeprom_command(&mut mmu, WRITE, 0x10, Some(0x0042));
+    assert_eq!(eeprom_command(&mut mmu, READ, 0x10, None), 0x0042);
+}
+
+#[test]
+fn eeprom_is_saved_like_battery_ram() {
+    let mut mmu = mbc7_cartridge();
+    eeprom_command(&mut mmu, EXTENDED, EWEN, None);
+    eeprom_command(&mut mmu, WRITE, 0x01, Some(0x1234));
+    let save = mmu.battery_ram().unwrap().to_vec();
+    assert_eq!(save.len(), 256);
+    assert_eq!(save[2..4], [0x34, 0x12]);
+
+    let mut mmu = mbc7_cartridge();
+    mmu.load_battery_ram(&save);
+    assert_eq!(eeprom_command(&mut mmu, READ, 0x01, None), 0x1234);
+}
+
+#[test]
+fn states_with_invalid_eeprom_commands_are_rejected() {
+    let mut mmu = mbc7_cartridge();
+    // A WRITE command to address 0x7F, waiting for its data
+    mmu.write_byte(0xA080, CS);
+    shift_bits(&mut mmu, 0b1 << 10 | WRITE << 8 | 0x7F, 11);
+    let mut state = mmu.save_cartridge_state();
+    assert_eq!(mmu.load_cartridge_state(&state), Ok(()));
+
+    // The EEPROM state ends with its tag, shift register, bit count and address
+    let len = state.len();
+    assert_eq!([state[len - 5], state[len - 2], state[len - 1]], [3, 0, 0x7F]);
+    state[len - 1] = 0x80;
+    assert_eq!(mmu.load_cartridge_state(&state), Err(StateError::InvalidValue(0x80)));
+    state[len - 1] = 0x7F;
+    state[len - 2] = 16;
+    assert_eq!(mmu.load_cartridge_state(&state), Err(StateError::InvalidValue(16)));
+    state[len - 2] = 0;
+    state[len - 5] = 4;
+    assert_eq!(mmu.load_cartridge_state(&state), Err(StateError::InvalidValue(4)));
+}
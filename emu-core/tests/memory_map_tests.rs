@@ -0,0 +1,187 @@
+mod common;
+
+use emu_core::memory::{MemoryBus, Mmu, Model};
+
+use crate::common::cartridge_rom;
+
+const ROM_ONLY: u8 = 0x00;
+
+fn dmg() -> Mmu {
+    Mmu::new(cartridge_rom(ROM_ONLY, 0x00, 0x00))
+}
+
+fn cgb() -> Mmu {
+    let mut rom = cartridge_rom(ROM_ONLY, 0x00, 0x00);
+    rom[0x0143] = 0x80;
+    Mmu::new(rom)
+}
+
+// Video memory is only always accessible with the LCD off
+fn lcd_off(mut mmu: Mmu) -> Mmu {
+    mmu.write_byte(0xFF40, 0x00);
+    mmu
+}
+
+#[test]
+fn video_memory_is_mapped() {
+    let mut mmu = lcd_off(dmg());
+    mmu.write_byte(0x8000, 0x12);
+    mmu.write_byte(0x9FFF, 0x34);
+    mmu.write_byte(0xFE00, 0x56);
+    mmu.write_byte(0xFE9F, 0x78);
+    assert_eq!(
+        [mmu.peek_byte(0x8000), mmu.peek_byte(0x9FFF), mmu.peek_byte(0xFE00), mmu.peek_byte(0xFE9F)],
+        [0x12, 0x34, 0x56, 0x78]
+    );
+}
+
+#[test]
+fn unused_register_bits_read_as_set() {
+    let mut mmu = dmg();
+    for (addr, unused) in [(0xFF0F, 0xE0), (0xFF41, 0x80), (0xFF10, 0x80), (0xFF1A, 0x7F), (0xFF26, 0x70)] {
+        mmu.write_byte(addr, 0x00);
+        // NR52 keeps the APU powered for the next registers
+        mmu.write_byte(0xFF26, 0x80);
+        assert_eq!(mmu.peek_byte(addr) & unused, unused, "0x{:04X}", addr);
+    }
+    mmu.write_byte(0xFF0F, 0x01);
+    assert_eq!(mmu.peek_byte(0xFF0F), 0xE1);
+    mmu.write_byte(0xFF02, 0x01);
+    assert_eq!(mmu.peek_byte(0xFF02), 0x7F);
+
+    // SC bit 1 selects the clock speed on CGB
+    let mut mmu = cgb();
+    mmu.write_byte(0xFF02, 0x00);
+    assert_eq!(mmu.peek_byte(0xFF02), 0x7C);
+}
+
+#[test]
+fn write_only_and_unmapped_registers_read_as_ff() {
+    let mut mmu = dmg();
+    for addr in [0xFF03, 0xFF08, 0xFF13, 0xFF15, 0xFF18, 0xFF1D, 0xFF20, 0xFF27, 0xFF4C, 0xFF50, 0xFF7F] {
+        mmu.write_byte(addr, 0x00);
+        assert_eq!(mmu.peek_byte(addr), 0xFF, "0x{:04X}", addr);
+    }
+    // KEY1 is a CGB register
+    assert_eq!(mmu.peek_byte(0xFF4D), 0xFF);
+}
+
+#[test]
+fn registers_read_back() {
+    let mut mmu = dmg();
+    for addr in [0xFF40, 0xFF42, 0xFF43, 0xFF45, 0xFF46, 0xFF47, 0xFF48, 0xFF49, 0xFF4A, 0xFF4B, 0xFF30, 0xFF3F] {
+        mmu.write_byte(addr, 0x5A);
+        assert_eq!(mmu.peek_byte(addr), 0x5A, "0x{:04X}", addr);
+    }
+    // Post-boot values
+    let mmu = dmg();
+    assert_eq!([mmu.peek_byte(0xFF40), mmu.peek_byte(0xFF47), mmu.peek_byte(0xFF26)], [0x91, 0xFC, 0xF0]);
+}
+
+#[test]
+fn stat_mode_bits_are_read_only() {
+    let mut mmu = lcd_off(dmg());
+    mmu.write_byte(0xFF45, 0x01);
+    mmu.write_byte(0xFF41, 0xFF);
+    assert_eq!(mmu.peek_byte(0xFF41), 0xF8);
+}
+
+#[test]
+fn ly_is_read_only_unless_doctor_mode() {
+    let mut mmu = dmg();
+    mmu.write_byte(0xFF44, 0x42);
+    assert_eq!(mmu.peek_byte(0xFF44), 0x00);
+    mmu.set_gameboy_doctor(true);
+    assert_eq!(mmu.peek_byte(0xFF44), 0x90);
+}
+
+#[test]
+fn sound_registers_are_cleared_while_powered_off() {
+    let mut mmu = dmg();
+    mmu.write_byte(0xFF24, 0x77);
+    mmu.write_byte(0xFF26, 0x00);
+    assert_eq!(mmu.peek_byte(0xFF24), 0x00);
+    assert_eq!(mmu.peek_byte(0xFF26), 0x70);
+    mmu.write_byte(0xFF24, 0x55);
+    assert_eq!(mmu.peek_byte(0xFF24), 0x00);
+    // Wave RAM stays accessible
+    mmu.write_byte(0xFF30, 0x12);
+    assert_eq!(mmu.peek_byte(0xFF30), 0x12);
+
+    mmu.write_byte(0xFF26, 0x80);
+    mmu.write_byte(0xFF24, 0x55);
+    assert_eq!(mmu.peek_byte(0xFF24), 0x55);
+}
+
+#[test]
+fn unusable_area_depends_on_the_model() {
+    let mut mmu = lcd_off(dmg());
+    mmu.write_byte(0xFEA0, 0x12);
+    assert_eq!(mmu.peek_byte(0xFEA0), 0x00);
+    assert_eq!(mmu.peek_byte(0xFEFF), 0x00);
+
+    let mut mmu = cgb();
+    assert_eq!(mmu.model(), Model::CgbE);
+    mmu.write_byte(0xFEA0, 0x12);
+    assert_eq!([mmu.peek_byte(0xFEA0), mmu.peek_byte(0xFEB5), mmu.peek_byte(0xFEFF)], [0xAA, 0xBB, 0xFF]);
+
+    // Revision D: RAM, with FEC0 - FEFF mirroring FEF0 - FEFF
+    mmu.set_model(Model::CgbD);
+    mmu.write_byte(0xFEA0, 0x12);
+    mmu.write_byte(0xFEC3, 0x34);
+    assert_eq!([mmu.peek_byte(0xFEA0), mmu.peek_byte(0xFEF3), mmu.peek_byte(0xFED3)], [0x12, 0x34, 0x34]);
+
+    // Revisions 0 - C: A3 and A4 are ignored
+    mmu.set_model(Model::CgbC);
+    mmu.write_byte(0xFEA1, 0x56);
+    assert_eq!(mmu.peek_byte(0xFEB9), 0x56);
+}
+
+#[test]
+fn cgb_mode_needs_a_cgb_model() {
+    let mut mmu = cgb();
+    mmu.write_byte(0xFF4D, 0x01);
+    assert_eq!(mmu.peek_byte(0xFF4D), 0x7F);
+    mmu.set_model(Model::Dmg);
+    assert_eq!(mmu.peek_byte(0xFF4D), 0xFF);
+}
+
+#[test]
+fn roms_without_a_cgb_flag_run_on_dmg() {
+    // The flag at 0x0143 is past the end of the ROM, where reads return 0xFF
+    let mmu = Mmu::new(vec![0; 0x100]);
+    assert_eq!(mmu.peek_byte(0x0143), 0xFF);
+    assert_eq!(mmu.model(), Model::Dmg);
+}
+
+#[test]
+fn oam_dma_copies_160_bytes() {
+    let mut mmu = lcd_off(dmg());
+    for i in 0..0xA0 {
+        mmu.poke_byte(0xC100 + i, i as u8 ^ 0x5A);
+    }
+    mmu.write_byte(0xFF46, 0xC1);
+    assert_eq!(mmu.peek_byte(0xFF46), 0xC1);
+
+    // OAM (and the area after it on DMG) is inaccessible during the transfer
+    mmu.tick(1);
+    assert_eq!(mmu.peek_byte(0xFE00), 0xFF);
+    assert_eq!(mmu.peek_byte(0xFEA0), 0xFF);
+    mmu.write_byte(0xFE00, 0x00);
+    mmu.tick(0x9D);
+    assert_eq!(mmu.peek_byte(0xFE00), 0xFF);
+    mmu.tick(1);
+    for i in 0..0xA0 {
+        assert_eq!(mmu.peek_byte(0xFE00 + i), i as u8 ^ 0x5A);
+    }
+    assert_eq!(mmu.peek_byte(0xFEA0), 0x00);
+}
+
+#[test]
+fn oam_dma_reads_working_ram_above_0xe000() {
+    let mut mmu = lcd_off(dmg());
+    mmu.poke_byte(0xDE05, 0x42);
+    mmu.write_byte(0xFF46, 0xFE);
+    mmu.tick(0xA0);
+    assert_eq!(mmu.peek_byte(0xFE05), 0x42);
+}
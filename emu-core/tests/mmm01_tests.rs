@@ -0,0 +1,85 @@
+mod common;
+
+use emu_core::cartridge::Mapper;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{bank_at, cartridge_rom, fix_header_checksum};
+
+const MBC1: u8 = 0x01;
+const MMM01_RAM_BATTERY: u8 = 0x0D;
+
+/// Creates a 512KB multicart: the header at the start of the ROM is the first game's (MBC1),
+/// while the one of the menu, in the last 32KB, is MMM01 with 32KB of RAM.
+fn multicart() -> Vec<u8> {
+    let mut rom = cartridge_rom(MBC1, 0x04, 0x00);
+    let menu = rom.len() - 0x8000;
+    let mut header = rom[..0x0150].to_vec();
+    header[0x0147] = MMM01_RAM_BATTERY;
+    header[0x0149] = 0x03;
+    fix_header_checksum(&mut header);
+    rom[menu + 0x0100..menu + 0x0150].copy_from_slice(&header[0x0100..]);
+    rom
+}
+
+/// Maps a game starting at a ROM bank, of which the game can change the lower bits (up to
+/// the mask), and locks the mapping like the menu does.
+fn select_game(mmu: &mut Mmu, first_bank: u8, rom_mask: u8) {
+    mmu.write_byte(0x2000, first_bank);
+    mmu.write_byte(0x6000, rom_mask << 2);
+    mmu.write_byte(0x0000, 0x40);
+}
+
+#[test]
+fn detects_the_menu_header() {
+    let mmu = Mmu::new(multicart());
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::Mmm01);
+    assert!(mmu.cartridge_type().battery);
+    assert_eq!(mmu.battery_ram().unwrap().len(), 0x8000);
+}
+
+#[test]
+fn menu_is_mapped_at_power_on() {
+    let mut mmu = Mmu::new(multicart());
+    assert_eq!(bank_at(&mmu, 0x0000), 30);
+    assert_eq!(bank_at(&mmu, 0x4000), 31);
+
+    // Bank writes don't escape the last 32KB until the mapping is locked: only the lowest bit applies
+    for (val, bank) in [(0x08, 30), (0x09, 31), (0x10, 30), (0x20, 31)] {
+        mmu.write_byte(0x2000, val);
+        assert_eq!(bank_at(&mmu, 0x4000), bank, "0x{:02X}", val);
+    }
+    assert_eq!(bank_at(&mmu, 0x0000), 30);
+}
+
+#[test]
+fn locked_game_sees_an_mbc1_restricted_to_its_banks() {
+    let mut mmu = Mmu::new(multicart());
+    // A 4 banks game, from bank 8: bits 2 - 4 of the bank are the menu's
+    select_game(&mut mmu, 0x08, 0b1110);
+    assert_eq!(bank_at(&mmu, 0x0000), 8);
+    assert_eq!(bank_at(&mmu, 0x4000), 8);
+
+    for (val, bank) in [(0x01, 9), (0x03, 11), (0x1F, 11), (0x04, 8)] {
+        mmu.write_byte(0x2000, val);
+        assert_eq!(bank_at(&mmu, 0x4000), bank, "0x{:02X}", val);
+    }
+    assert_eq!(bank_at(&mmu, 0x0000), 8);
+
+    // The menu settings are now read-only
+    mmu.write_byte(0x0000, 0x00);
+    mmu.write_byte(0x6000, 0x00);
+    mmu.write_byte(0x2000, 0x01);
+    assert_eq!(bank_at(&mmu, 0x0000), 8);
+    assert_eq!(bank_at(&mmu, 0x4000), 9);
+}
+
+#[test]
+fn ram_bank_bits_can_be_protected() {
+    let mut mmu = Mmu::new(multicart());
+    // The menu gives RAM bank 2 to the game, and protects both RAM bank bits
+    mmu.write_byte(0x4000, 0x02);
+    mmu.write_byte(0x0000, 0x70 | 0x0A);
+    mmu.write_byte(0x4000, 0x01);
+    mmu.write_byte(0xA000, 0x42);
+    assert_eq!(mmu.battery_ram().unwrap()[2 * 0x2000], 0x42);
+}
@@ -0,0 +1,82 @@
+use std::fs;
+
+use emu_core::cpu::cpu::Cpu;
+use emu_core::memory::Mmu;
+
+// The mooneye test suite ROMs aren't distributed with the repository, so these tests are ignored by default.
+// To run them, build the suite from https://github.com/Gekkio/mooneye-test-suite (`make`, which needs
+// wla-dx), copy the contents of its `build/emulator-only` directory into `tests/data/mooneye`, then run
+// `cargo test --test mooneye_tests -- --ignored`.
+static TESTS_PATH: &str = "tests/data/mooneye";
+
+// M-cycles per second of emulated time
+const M_CYCLES_PER_SECOND: u64 = 1_048_576;
+
+// Mooneye tests report their result in a few seconds at most
+const TIMEOUT: u64 = 20 * M_CYCLES_PER_SECOND;
+
+// LD B,B is used as a breakpoint to signal the end of a test
+const LD_B_B: u8 = 0x40;
+
+/// Runs a mooneye test ROM until it reaches its breakpoint, and checks that it passed:
+/// passing tests load the Fibonacci numbers 3, 5, 8, 13, 21 and 34 into B, C, D, E, H and L.
+fn run_mooneye_test(name: &str) {
+    let rom = fs::read(format!("{}/{}.gb", TESTS_PATH, name)).expect("Failed to read ROM");
+    let mut cpu = Cpu::boot_rom_initialized(Mmu::new(rom));
+
+    while cpu.m_cycles() < TIMEOUT {
+        let info = cpu.step();
+        if info.executed && info.opcode == LD_B_B {
+            let reg = &cpu.reg;
+            let result = [reg.b, reg.c, reg.d, reg.e, reg.h, reg.l];
+            assert_eq!(result, [3, 5, 8, 13, 21, 34], "{} failed", name);
+            return;
+        }
+    }
+    panic!("{} timed out at PC 0x{:04X}", name, cpu.reg.pc);
+}
+
+macro_rules! mooneye_tests {
+    ($($dir:ident / $name:ident),* $(,)?) => {
+        $(
+            paste::paste! {
+                #[test]
+                #[ignore = "needs the mooneye test suite ROMs in tests/data/mooneye"]
+                fn [<$dir _ $name:lower>]() {
+                    run_mooneye_test(concat!(stringify!($dir), "/", stringify!($name)));
+                }
+            }
+        )*
+    };
+}
+
+mooneye_tests! {
+    mbc1/bits_bank1,
+    mbc1/bits_bank2,
+    mbc1/bits_mode,
+    mbc1/bits_ramg,
+    mbc1/multicart_rom_8Mb,
+    mbc1/ram_64kb,
+    mbc1/ram_256kb,
+    mbc1/rom_512kb,
+    mbc1/rom_1Mb,
+    mbc1/rom_2Mb,
+    mbc1/rom_4Mb,
+    mbc1/rom_8Mb,
+    mbc1/rom_16Mb,
+    mbc2/bits_ramg,
+    mbc2/bits_romb,
+    mbc2/bits_unused,
+    mbc2/ram,
+    mbc2/rom_512kb,
+    mbc2/rom_1Mb,
+    mbc2/rom_2Mb,
+    mbc5/rom_512kb,
+    mbc5/rom_1Mb,
+    mbc5/rom_2Mb,
+    mbc5/rom_4Mb,
+    mbc5/rom_8Mb,
+    mbc5/rom_16Mb,
+    mbc5/rom_32Mb,
+    mbc5/rom_64Mb,
+}
@@ -0,0 +1,238 @@
+mod common;
+
+use emu_core::interrupts::Interrupt;
+use emu_core::memory::{MemoryBus, Mmu};
+use emu_core::ppu::{Mode, Ppu, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+use crate::common::cartridge_rom;
+
+const DOTS_PER_LINE: u32 = 456;
+const DOTS_PER_FRAME: u32 = 70224;
+
+const VBLANK: u8 = 0x01;
+const STAT: u8 = 0x02;
+
+// Writes video memory with the LCD off, then turns it on with `lcdc`, at the start of a frame
+fn ppu_with_vram(lcdc: u8, writes: &[(u16, u8)]) -> Ppu {
+    let mut ppu = Ppu::new();
+    ppu.write_byte(0xFF40, 0x00);
+    for &(addr, val) in writes {
+        ppu.write_byte(addr, val);
+    }
+    ppu.write_byte(0xFF40, lcdc);
+    ppu
+}
+
+fn pixel(ppu: &Ppu, x: usize, y: usize) -> u8 {
+    ppu.framebuffer()[y * SCREEN_WIDTH + x]
+}
+
+#[test]
+fn line_goes_through_modes_2_3_and_0() {
+    let mut ppu = Ppu::new();
+    assert_eq!(ppu.mode(), Mode::OamScan);
+    ppu.tick(79);
+    assert_eq!(ppu.mode(), Mode::OamScan);
+    ppu.tick(1);
+    assert_eq!(ppu.mode(), Mode::Drawing);
+    ppu.tick(171);
+    assert_eq!(ppu.mode(), Mode::Drawing);
+    ppu.tick(1);
+    assert_eq!(ppu.mode(), Mode::HBlank);
+    assert_eq!(ppu.read_byte(0xFF41) & 0x03, 0x00);
+
+    ppu.tick(DOTS_PER_LINE - 253);
+    assert_eq!(ppu.read_byte(0xFF44), 0);
+    ppu.tick(1);
+    assert_eq!(ppu.read_byte(0xFF44), 1);
+    assert_eq!(ppu.read_byte(0xFF41) & 0x03, 0x02);
+}
+
+#[test]
+fn fine_scroll_lengthens_mode_3() {
+    let mut ppu = Ppu::new();
+    ppu.write_byte(0xFF43, 0x05);
+    ppu.tick(80 + 172);
+    assert_eq!(ppu.mode(), Mode::Drawing);
+    ppu.tick(5);
+    assert_eq!(ppu.mode(), Mode::HBlank);
+}
+
+#[test]
+fn frame_has_154_lines_with_vblank_from_line_144() {
+    let mut ppu = Ppu::new();
+    assert_eq!(ppu.tick(144 * DOTS_PER_LINE - 1) & VBLANK, 0);
+    assert_eq!(ppu.tick(1) & VBLANK, VBLANK);
+    assert_eq!((ppu.read_byte(0xFF44), ppu.mode()), (144, Mode::VBlank));
+    assert_eq!(ppu.frames(), 1);
+
+    ppu.tick(9 * DOTS_PER_LINE);
+    assert_eq!((ppu.read_byte(0xFF44), ppu.mode()), (153, Mode::VBlank));
+    ppu.tick(DOTS_PER_LINE);
+    assert_eq!((ppu.read_byte(0xFF44), ppu.mode()), (0, Mode::OamScan));
+
+    // One VBlank interrupt per frame
+    assert_eq!(ppu.tick(DOTS_PER_FRAME) & VBLANK, VBLANK);
+    assert_eq!(ppu.frames(), 2);
+    assert_eq!(ppu.read_byte(0xFF44), 0);
+}
+
+#[test]
+fn stat_interrupt_on_ly_coincidence() {
+    let mut ppu = Ppu::new();
+    ppu.write_byte(0xFF45, 0x02);
+    assert!(!ppu.write_byte(0xFF41, 0x40));
+    assert_eq!(ppu.tick(2 * DOTS_PER_LINE - 1) & STAT, 0);
+    assert_eq!(ppu.read_byte(0xFF41) & 0x04, 0x00);
+    assert_eq!(ppu.tick(1) & STAT, STAT);
+    assert_eq!(ppu.read_byte(0xFF41), 0xC6);
+
+    // Setting LYC to the current line raises the line again
+    assert_eq!(ppu.tick(DOTS_PER_LINE) & STAT, 0);
+    assert!(ppu.write_byte(0xFF45, 0x03));
+}
+
+#[test]
+fn stat_interrupt_on_mode_changes() {
+    let mut ppu = Ppu::new();
+    ppu.write_byte(0xFF41, 0x08);
+    assert_eq!(ppu.tick(80 + 172) & STAT, STAT);
+
+    // Enabling a source that is already active requests the interrupt
+    let mut ppu = Ppu::new();
+    assert!(ppu.write_byte(0xFF41, 0x20));
+    ppu.write_byte(0xFF41, 0x10);
+    assert_eq!(ppu.tick(144 * DOTS_PER_LINE) & (VBLANK | STAT), VBLANK | STAT);
+}
+
+#[test]
+fn stat_interrupt_needs_a_rising_edge() {
+    // HBlank is directly followed by the OAM scan of the next line: the STAT line stays high
+    let mut ppu = Ppu::new();
+    ppu.write_byte(0xFF41, 0x28);
+    assert_eq!(ppu.tick(80 + 172) & STAT, STAT);
+    assert_eq!(ppu.tick(DOTS_PER_LINE - 252) & STAT, 0);
+    assert_eq!(ppu.mode(), Mode::OamScan);
+}
+
+#[test]
+fn next_event_is_the_next_mode_change() {
+    let mut ppu = Ppu::new();
+    ppu.tick(10);
+    assert_eq!(ppu.next_event(), Some(70));
+    ppu.tick(70);
+    assert_eq!(ppu.next_event(), Some(172));
+
+    ppu.write_byte(0xFF40, 0x00);
+    assert_eq!(ppu.next_event(), None);
+}
+
+#[test]
+fn lcd_off_resets_ly_and_stops() {
+    let mut ppu = Ppu::new();
+    ppu.tick(10 * DOTS_PER_LINE + 100);
+    ppu.write_byte(0xFF40, 0x11);
+    assert_eq!((ppu.read_byte(0xFF44), ppu.mode()), (0, Mode::HBlank));
+    assert_eq!(ppu.tick(DOTS_PER_FRAME), 0);
+    assert_eq!(ppu.read_byte(0xFF44), 0);
+
+    // Turning it back on restarts the first line
+    ppu.write_byte(0xFF40, 0x91);
+    assert_eq!(ppu.mode(), Mode::OamScan);
+    ppu.tick(DOTS_PER_LINE);
+    assert_eq!(ppu.read_byte(0xFF44), 1);
+}
+
+#[test]
+fn video_memory_is_blocked_while_in_use() {
+    let mut ppu = ppu_with_vram(0x91, &[(0x8000, 0x12), (0xFE00, 0x34)]);
+
+    // Mode 2: OAM is being scanned
+    assert_eq!([ppu.read_byte(0x8000), ppu.read_byte(0xFE00)], [0x12, 0xFF]);
+    ppu.write_byte(0xFE00, 0x56);
+
+    // Mode 3: both are being read
+    ppu.tick(80);
+    assert_eq!([ppu.read_byte(0x8000), ppu.read_byte(0xFE00)], [0xFF, 0xFF]);
+    ppu.write_byte(0x8000, 0x78);
+
+    // Mode 0
+    ppu.tick(172);
+    assert_eq!([ppu.read_byte(0x8000), ppu.read_byte(0xFE00)], [0x12, 0x34]);
+
+    // DMA transfers have priority
+    ppu.tick(DOTS_PER_LINE - 252);
+    ppu.write_oam_dma(0x00, 0x9A);
+    ppu.tick(172);
+    assert_eq!(ppu.read_byte(0xFE00), 0xFF);
+    ppu.tick(80);
+    assert_eq!(ppu.read_byte(0xFE00), 0x9A);
+}
+
+#[test]
+fn renders_background_through_the_viewport() {
+    // Tile 1 is filled with color 1, and placed at the top left of the 0x9800 map
+    let mut writes: Vec<(u16, u8)> =
+        (0..8).flat_map(|row| [(0x8010 + row * 2, 0xFF), (0x8011 + row * 2, 0x00)]).collect();
+    writes.push((0x9800, 0x01));
+    writes.push((0xFF47, 0xE4));
+    let mut ppu = ppu_with_vram(0x91, &writes);
+    ppu.tick(DOTS_PER_FRAME);
+    assert_eq!([pixel(&ppu, 0, 0), pixel(&ppu, 7, 7), pixel(&ppu, 8, 0), pixel(&ppu, 0, 8)], [1, 1, 0, 0]);
+
+    // The viewport wraps around the 256x256 background
+    ppu.write_byte(0xFF42, 0xFE);
+    ppu.write_byte(0xFF43, 0x04);
+    ppu.tick(DOTS_PER_FRAME);
+    assert_eq!([pixel(&ppu, 0, 1), pixel(&ppu, 0, 2), pixel(&ppu, 3, 9), pixel(&ppu, 4, 9)], [0, 1, 1, 0]);
+
+    // The palette maps colors to shades
+    ppu.write_byte(0xFF47, 0x0C);
+    ppu.tick(DOTS_PER_FRAME);
+    assert_eq!([pixel(&ppu, 0, 1), pixel(&ppu, 0, 2)], [0, 3]);
+    assert_eq!(ppu.framebuffer().len(), SCREEN_WIDTH * SCREEN_HEIGHT);
+}
+
+#[test]
+fn renders_signed_tile_numbers_and_second_map() {
+    let mut writes = vec![(0xFF47, 0xE4), (0x9C00, 0x80), (0x9C01, 0x7F)];
+    // Tile 0 is at 0x9000 (color 3), tile 0x80 at 0x8800 (color 2), tile 0x7F at 0x97F0 (color 1)
+    for row in 0..8 {
+        writes.extend([(0x9000 + row * 2, 0xFF), (0x9001 + row * 2, 0xFF)]);
+        writes.extend([(0x8800 + row * 2, 0x00), (0x8801 + row * 2, 0xFF)]);
+        writes.extend([(0x97F0 + row * 2, 0xFF), (0x97F1 + row * 2, 0x00)]);
+    }
+    let mut ppu = ppu_with_vram(0x89, &writes);
+    ppu.tick(DOTS_PER_FRAME);
+    assert_eq!([pixel(&ppu, 0, 0), pixel(&ppu, 8, 0), pixel(&ppu, 16, 0), pixel(&ppu, 0, 8)], [2, 1, 3, 3]);
+
+    // Without the background, the screen is white
+    ppu.write_byte(0xFF40, 0x88);
+    ppu.tick(DOTS_PER_FRAME);
+    assert!(ppu.framebuffer().iter().all(|&shade| shade == 0));
+}
+
+#[test]
+fn clocked_by_the_memory_bus() {
+    let mut mmu = Mmu::new(cartridge_rom(0x00, 0x00, 0x00));
+    assert_eq!([mmu.peek_byte(0xFF40), mmu.peek_byte(0xFF47)], [0x91, 0xFC]);
+
+    // 114 M-cycles per line
+    mmu.tick(114);
+    assert_eq!(mmu.peek_byte(0xFF44), 1);
+    for _ in 0..143 {
+        mmu.tick(114);
+    }
+    assert_eq!(mmu.peek_byte(0xFF44), 144);
+    assert_eq!(mmu.peek_byte(0xFF0F) & Interrupt::VBlank.mask(), Interrupt::VBlank.mask());
+    assert_eq!(mmu.ppu().frames(), 1);
+
+    // STAT interrupts requested by register writes
+    mmu.write_byte(0xFF41, 0x10);
+    assert_eq!(mmu.peek_byte(0xFF0F) & Interrupt::LcdStat.mask(), Interrupt::LcdStat.mask());
+
+    // The next event is only scheduled when LCD interrupts are enabled
+    assert_eq!(mmu.next_event(), None);
+    mmu.write_byte(0xFFFF, Interrupt::VBlank.mask());
+    assert!(mmu.next_event().is_some_and(|cycles| cycles <= 114));
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use emu_core::cpu::cpu::Cpu;
+use emu_core::error::EmuError;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::MockMemory;
+
+// Small xorshift PRNG, so that the generated ROMs are reproducible
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+#[test]
+fn random_roms_never_panic() {
+    let mut rng = XorShift(0x0DDB_A11C_AFE5_EED5);
+    for _ in 0..16 {
+        // Random sizes also cover truncated ROMs
+        let size = (rng.next() % 0x10000) as usize;
+        let rom: Vec<u8> = (0..size).map(|_| rng.next() as u8).collect();
+        let mut cpu = Cpu::boot_rom_initialized(Mmu::new(rom));
+
+        for _ in 0..50_000 {
+            assert!(cpu.try_step().is_ok());
+            cpu.doctor_log_state();
+        }
+    }
+}
+
+#[test]
+fn unmapped_mock_memory_is_reported_as_error() {
+    let mut mmu = MockMemory::default();
+    mmu.write_byte(0xC000, 0x00); // NOP, the next opcode fetch is unmapped
+    let mut cpu = Cpu::new(mmu);
+    cpu.reg.pc = 0xC001;
+
+    assert_eq!(cpu.try_step(), Err(EmuError::UnmappedAddress(0xC001)));
+}
+
+#[test]
+fn step_discards_errors() {
+    let mut mmu = MockMemory::default();
+    mmu.write_byte(0xC000, 0x00); // NOP, the next opcode fetch is unmapped
+    let mut cpu = Cpu::new(mmu);
+    cpu.reg.pc = 0xC001;
+    cpu.step();
+
+    // The error isn't left for the next `try_step` to report
+    assert_eq!(cpu.mmu.take_error(), None);
+}
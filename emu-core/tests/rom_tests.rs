@@ -0,0 +1,272 @@
+mod common;
+
+use std::io::Write;
+use std::path::PathBuf;
+
+use emu_core::cartridge::Mapper;
+use emu_core::error::{PatchError, RomError};
+use emu_core::memory::{MemoryBus, Mmu};
+use emu_core::rom::{self, apply_patch};
+use flate2::write::GzEncoder;
+use flate2::{Compression, Crc};
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+use crate::common::cartridge_rom;
+
+const MBC1: u8 = 0x01;
+
+/// Test file in the temporary directory, removed when dropped.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str, data: &[u8]) -> Self {
+        let path = std::env::temp_dir().join(format!("emu_core_rom_test_{}_{}", std::process::id(), name));
+        std::fs::write(&path, data).unwrap();
+        Self(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+fn varint(out: &mut Vec<u8>, mut val: usize) {
+    loop {
+        let group = (val & 0x7F) as u8;
+        val >>= 7;
+        if val == 0 {
+            out.push(0x80 | group);
+            return;
+        }
+        out.push(group);
+        val -= 1;
+    }
+}
+
+// Appends the CRC32 of the source and target ROMs, then of the patch
+fn add_footer(patch: &mut Vec<u8>, source: &[u8], target: &[u8]) {
+    patch.extend_from_slice(&crc32(source).to_le_bytes());
+    patch.extend_from_slice(&crc32(target).to_le_bytes());
+    let crc = crc32(patch);
+    patch.extend_from_slice(&crc.to_le_bytes());
+}
+
+/// Builds a UPS patch turning the source into the target.
+fn ups_patch(source: &[u8], target: &[u8]) -> Vec<u8> {
+    let mut patch = b"UPS1".to_vec();
+    varint(&mut patch, source.len());
+    varint(&mut patch, target.len());
+    let byte = |rom: &[u8], i: usize| rom.get(i).copied().unwrap_or(0);
+    let mut last = 0;
+    let mut i = 0;
+    while i < target.len() {
+        if byte(source, i) == target[i] {
+            i += 1;
+            continue;
+        }
+        varint(&mut patch, i - last);
+        while i < target.len() && byte(source, i) != target[i] {
+            patch.push(byte(source, i) ^ target[i]);
+            i += 1;
+        }
+        patch.push(0);
+        i += 1;
+        last = i;
+    }
+    add_footer(&mut patch, source, target);
+    patch
+}
+
+fn small_rom() -> Vec<u8> {
+    (0..=255).collect()
+}
+
+fn zip_archive(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    for (name, data) in files {
+        writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+        writer.write_all(data).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn loads_plain_and_compressed_roms() {
+    let rom = cartridge_rom(MBC1, 0x01, 0x00);
+    let plain = TempFile::new("plain.gb", &rom);
+    assert_eq!(rom::load(&plain.0).unwrap(), rom);
+
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(&rom).unwrap();
+    let gz = TempFile::new("game.gb.gz", &gz.finish().unwrap());
+    assert_eq!(rom::load(&gz.0).unwrap(), rom);
+
+    let zip = TempFile::new("game.zip", &zip_archive(&[("readme.txt", b"hello"), ("Game.GBC", &rom)]));
+    let loaded = rom::load(&zip.0).unwrap();
+    assert_eq!(loaded, rom);
+    assert_eq!(Mmu::new(loaded).cartridge_type().mapper, Mapper::Mbc1);
+}
+
+#[test]
+fn reports_loading_errors() {
+    let zip = TempFile::new("empty.zip", &zip_archive(&[("readme.txt", b"hello")]));
+    assert_eq!(rom::load(&zip.0), Err(RomError::NoRomInArchive));
+    assert!(matches!(rom::load(zip.0.with_extension("missing")), Err(RomError::Io(_))));
+    assert!(matches!(rom::decompress(vec![0x1F, 0x8B, 0x00]), Err(RomError::Archive(_))));
+}
+
+#[test]
+fn rejects_archives_larger_than_any_rom() {
+    // 8 MiB is accepted, one more byte isn't
+    let largest = vec![0; 8 * 1024 * 1024];
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(&largest).unwrap();
+    assert_eq!(rom::decompress(gz.finish().unwrap()).map(|rom| rom.len()), Ok(largest.len()));
+
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    gz.write_all(&largest).unwrap();
+    gz.write_all(&[0]).unwrap();
+    assert_eq!(rom::decompress(gz.finish().unwrap()), Err(RomError::TooLarge));
+
+    let zip = zip_archive(&[("game.gb", &[largest.as_slice(), &[0]].concat())]);
+    assert_eq!(rom::decompress(zip), Err(RomError::TooLarge));
+}
+
+#[test]
+fn ips_patches() {
+    let mut patch = b"PATCH".to_vec();
+    // 2 bytes at 0x10
+    patch.extend_from_slice(&[0x00, 0x00, 0x10, 0x00, 0x02, 0xAA, 0xBB]);
+    // 4 times 0x77 at 0x20 (RLE)
+    patch.extend_from_slice(&[0x00, 0x00, 0x20, 0x00, 0x00, 0x00, 0x04, 0x77]);
+    // past the end of the ROM
+    patch.extend_from_slice(&[0x00, 0x01, 0x01, 0x00, 0x01, 0x55]);
+    patch.extend_from_slice(b"EOF");
+
+    let patched = apply_patch(&small_rom(), &patch).unwrap();
+    assert_eq!(&patched[0x0F..0x13], &[0x0F, 0xAA, 0xBB, 0x12]);
+    assert_eq!(&patched[0x20..0x25], &[0x77, 0x77, 0x77, 0x77, 0x24]);
+    assert_eq!(patched.len(), 0x102);
+    assert_eq!(&patched[0x100..], &[0x00, 0x55]);
+
+    // Truncation after the end marker
+    patch.extend_from_slice(&[0x00, 0x00, 0x80]);
+    assert_eq!(apply_patch(&small_rom(), &patch).unwrap().len(), 0x80);
+
+    assert_eq!(apply_patch(&small_rom(), b"PATCH\x00\x00\x10\x00\x04\x01"), Err(PatchError::Truncated));
+    assert_eq!(apply_patch(&small_rom(), b"NOT A PATCH"), Err(PatchError::UnknownFormat));
+}
+
+#[test]
+fn ups_patches() {
+    let source = small_rom();
+    let mut target = source.clone();
+    target[0x05] = 0x00;
+    target[0x80..0x84].copy_from_slice(b"HACK");
+    target.extend_from_slice(&[0x01; 300]);
+    let patch = ups_patch(&source, &target);
+    assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+
+    // Made for another ROM
+    let mut other = source.clone();
+    other[0] = 0xFF;
+    assert_eq!(
+        apply_patch(&other, &patch),
+        Err(PatchError::SourceChecksum { expected: crc32(&source), computed: crc32(&other) })
+    );
+
+    let mut corrupted = patch.clone();
+    corrupted[8] ^= 0x01;
+    assert!(matches!(apply_patch(&source, &corrupted), Err(PatchError::PatchChecksum { .. })));
+}
+
+#[test]
+fn bps_patches() {
+    let source = small_rom();
+    let mut target = source[..0x40].to_vec();
+    target.extend_from_slice(b"ABC");
+    target.extend_from_slice(b"ABCABCA");
+    target.extend_from_slice(&source[0x10..0x20]);
+
+    let mut patch = b"BPS1".to_vec();
+    varint(&mut patch, source.len());
+    varint(&mut patch, target.len());
+    varint(&mut patch, 4);
+    patch.extend_from_slice(b"meta");
+    // Source read of 0x40 bytes
+    varint(&mut patch, (0x40 - 1) << 2);
+    // Target read of "ABC"
+    varint(&mut patch, ((3 - 1) << 2) | 1);
+    patch.extend_from_slice(b"ABC");
+    // Target copy of 7 bytes from 0x40, overlapping what is being written
+    varint(&mut patch, ((7 - 1) << 2) | 3);
+    varint(&mut patch, 0x40 << 1);
+    // Source copy of 16 bytes from 0x10
+    varint(&mut patch, ((16 - 1) << 2) | 2);
+    varint(&mut patch, 0x10 << 1);
+    add_footer(&mut patch, &source, &target);
+
+    assert_eq!(apply_patch(&source, &patch).unwrap(), target);
+    assert!(matches!(apply_patch(&source[1..], &patch), Err(PatchError::SourceChecksum { .. })));
+}
+
+#[test]
+fn patched_roms_are_loaded() {
+    let source = cartridge_rom(MBC1, 0x01, 0x00);
+    let mut target = source.clone();
+    target[0x4000] = 0x42;
+    let rom = TempFile::new("clean.gb", &source);
+    let patch = TempFile::new("hack.ups", &ups_patch(&source, &target));
+
+    let mmu = Mmu::new(rom::load_patched(&rom.0, &[&patch.0]).unwrap());
+    assert_eq!(mmu.peek_byte(0x4000), 0x42);
+
+    let bad = TempFile::new("bad.ips", b"PATCH\x00");
+    assert_eq!(rom::load_patched(&rom.0, &[&bad.0]), Err(RomError::Patch(PatchError::Truncated)));
+}
+
+#[test]
+fn rejects_patches_outside_of_the_target() {
+    let source = small_rom();
+
+    // A target size no Game Boy ROM has, which must not be allocated
+    for magic in [b"UPS1", b"BPS1"] {
+        let mut patch = magic.to_vec();
+        varint(&mut patch, source.len());
+        varint(&mut patch, 1 << 50);
+        varint(&mut patch, 0);
+        add_footer(&mut patch, &source, &source);
+        assert_eq!(apply_patch(&source, &patch), Err(PatchError::TargetTooLarge(1 << 50)));
+    }
+
+    // A target copy going past the announced target size: 2 bytes read from the source, then 3 copied
+    let mut patch = b"BPS1".to_vec();
+    varint(&mut patch, source.len());
+    varint(&mut patch, 4);
+    varint(&mut patch, 0);
+    varint(&mut patch, (2 - 1) << 2);
+    varint(&mut patch, ((3 - 1) << 2) | 3);
+    varint(&mut patch, 0);
+    add_footer(&mut patch, &source, &source[..4]);
+    assert_eq!(apply_patch(&source, &patch), Err(PatchError::OutOfBounds(2)));
+
+    // A relative offset moving past the largest address
+    let mut patch = b"UPS1".to_vec();
+    varint(&mut patch, source.len());
+    varint(&mut patch, source.len());
+    varint(&mut patch, usize::MAX - 1);
+    patch.push(0);
+    varint(&mut patch, 5);
+    patch.push(0);
+    add_footer(&mut patch, &source, &source);
+    assert_eq!(apply_patch(&source, &patch), Err(PatchError::OutOfBounds(usize::MAX)));
+}
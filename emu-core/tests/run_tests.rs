@@ -0,0 +1,89 @@
+mod common;
+
+use emu_core::cpu::cpu::{Cpu, CpuState, StepInfo};
+use emu_core::interrupts::Interrupt;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::rom_with_program;
+
+const INC_B: u8 = 0x04;
+const EI: u8 = 0xFB;
+const NOP: u8 = 0x00;
+
+/// Creates a CPU running `program` from 0x0100, with interrupt handlers made of `handler`.
+/// The first step executes the NOP that `Cpu::new` starts with and fetches the first opcode.
+fn setup(program: &[u8], handler: &[u8]) -> Cpu<Mmu> {
+    let mut cpu = Cpu::new(Mmu::new(rom_with_program(program, handler)));
+    cpu.step();
+    cpu
+}
+
+#[test]
+fn step_reports_the_instruction_executed() {
+    // NOP; LD BC,0x1234; SWAP A; JR -2
+    let program = [NOP, 0x01, 0x34, 0x12, 0xCB, 0x37, 0x18, 0xFE];
+    let mut cpu = setup(&program, &[]);
+
+    let expected = [(0x0100, 0x00, 1), (0x0101, 0x01, 3), (0x0104, 0xCB, 2), (0x0106, 0x18, 3), (0x0106, 0x18, 3)];
+    for (pc, opcode, cycles) in expected {
+        let info = cpu.step();
+        assert_eq!(info, StepInfo { pc, opcode, cycles, executed: true, interrupt: None });
+    }
+    assert_eq!(cpu.m_cycles(), 13);
+    assert_eq!(cpu.t_cycles(), 52);
+}
+
+#[test]
+fn step_reports_interrupt_dispatch() {
+    // EI; NOP; NOP
+    let mut cpu = setup(&[EI, NOP, NOP], &[INC_B]);
+    cpu.step(); // EI
+    cpu.step(); // NOP (IME is now set)
+    cpu.mmu.write_byte(0xFFFF, 0x04);
+    cpu.mmu.interrupts_mut().request(Interrupt::Timer);
+
+    let info = cpu.try_step().unwrap();
+    assert_eq!(info, StepInfo { pc: 0x0102, opcode: NOP, cycles: 5, executed: false, interrupt: Some(Interrupt::Timer) });
+
+    let info = cpu.step();
+    assert_eq!(info, StepInfo { pc: 0x0050, opcode: INC_B, cycles: 1, executed: true, interrupt: None });
+}
+
+#[test]
+fn run_for_cycles_runs_whole_steps() {
+    // JP 0x0100
+    let mut cpu = setup(&[0xC3, 0x00, 0x01], &[]);
+    assert_eq!(cpu.run_for_cycles(8), 8);
+    assert_eq!(cpu.run_for_cycles(1), 4, "the last step runs to completion");
+    assert_eq!(cpu.m_cycles(), 13);
+    assert_eq!(cpu.run_for_cycles(0), 0);
+}
+
+#[test]
+fn run_for_cycles_returns_when_stopped() {
+    // LD A,0x20; LDH (P1),A; STOP
+    let program = [0x3E, 0x20, 0xE0, 0x00, 0x10, 0x00];
+    let mut cpu = setup(&program, &[]);
+    assert_eq!(cpu.run_for_cycles(1_000), 7);
+    assert_eq!(cpu.state(), CpuState::Stopped);
+}
+
+#[test]
+fn run_until_stops_at_the_first_match() {
+    // INC B; JR -3
+    let mut cpu = setup(&[INC_B, 0x18, 0xFD], &[]);
+    let cycles = cpu.run_until(|cpu| cpu.reg.b == 10);
+    assert_eq!(cycles, 10 + 9 * 3);
+    assert_eq!(cpu.m_cycles(), 1 + cycles);
+
+    assert_eq!(cpu.run_until(|cpu| cpu.reg.b == 10), 0, "already done");
+}
+
+#[test]
+fn run_until_returns_when_stopped() {
+    // LD A,0x20; LDH (P1),A; STOP
+    let program = [0x3E, 0x20, 0xE0, 0x00, 0x10, 0x00];
+    let mut cpu = setup(&program, &[]);
+    assert_eq!(cpu.run_until(|cpu| cpu.m_cycles() >= 1_000), 7);
+    assert_eq!(cpu.state(), CpuState::Stopped);
+}
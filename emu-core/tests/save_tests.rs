@@ -0,0 +1,214 @@
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use emu_core::cartridge::rtc::{CycleClock, RtcTime, RTC_FOOTER_SIZE, RTC_FOOTER_SIZE_32};
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::cartridge_rom;
+
+const MBC1_RAM: u8 = 0x02;
+const MBC1_RAM_BATTERY: u8 = 0x03;
+const MBC2_BATTERY: u8 = 0x06;
+const MBC3_TIMER_RAM_BATTERY: u8 = 0x10;
+const MBC5_RAM_BATTERY: u8 = 0x1B;
+const HUC3: u8 = 0xFE;
+
+const RAM_SIZE: usize = 0x2000;
+const HUC3_FOOTER_SIZE: usize = 17;
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+fn saved_ram_cartridge() -> Mmu {
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x01, 0x02));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0xA123, 0x42);
+    mmu
+}
+
+/// Builds an MBC3 clock footer, the way other emulators write it.
+fn rtc_footer(live: RtcTime, latched: RtcTime, timestamp: u64) -> Vec<u8> {
+    let mut footer = Vec::new();
+    for reg in live.registers().into_iter().chain(latched.registers()) {
+        footer.extend_from_slice(&(reg as u32).to_le_bytes());
+    }
+    footer.extend_from_slice(&timestamp.to_le_bytes());
+    footer
+}
+
+#[test]
+fn ram_is_exported_as_a_raw_save() {
+    let mut mmu = saved_ram_cartridge();
+    let save = mmu.export_save().unwrap();
+    assert_eq!(save.len(), RAM_SIZE);
+    assert_eq!(save[0x123], 0x42);
+
+    let mut other = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x01, 0x02));
+    other.load_save(&save);
+    other.write_byte(0x0000, 0x0A);
+    assert_eq!(other.peek_byte(0xA123), 0x42);
+
+    // Without a battery, there is nothing to save
+    assert_eq!(Mmu::new(cartridge_rom(MBC1_RAM, 0x01, 0x02)).export_save(), None);
+}
+
+#[test]
+fn save_files_round_trip() {
+    let path = std::env::temp_dir().join(format!("emu_core_save_test_{}.sav", std::process::id()));
+    let mut mmu = saved_ram_cartridge();
+    mmu.write_save_file(&path).unwrap();
+
+    let mut other = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x01, 0x02));
+    other.load_save_file(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(other.battery_ram().unwrap()[0x123], 0x42);
+    assert!(other.load_save_file(&path).is_err());
+}
+
+#[test]
+fn dirty_until_exported() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x01, 0x02));
+    assert!(!mmu.save_dirty());
+    // Disabled RAM ignores writes
+    mmu.write_byte(0xA000, 0x01);
+    assert!(!mmu.save_dirty());
+    mmu.write_byte(0x0000, 0x0A);
+    assert!(!mmu.save_dirty());
+    mmu.write_byte(0xA000, 0x01);
+    assert!(mmu.save_dirty());
+    mmu.export_save();
+    assert!(!mmu.save_dirty());
+
+    // Freshly loaded RAM isn't dirty either
+    mmu.write_byte(0xA000, 0x02);
+    mmu.load_battery_ram(&[0x03]);
+    assert!(!mmu.save_dirty());
+
+    // Nothing to flush without a battery
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM, 0x01, 0x02));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0xA000, 0x01);
+    assert!(!mmu.save_dirty());
+}
+
+#[test]
+fn autosaves_when_ram_is_disabled() {
+    let saves = Rc::new(RefCell::new(Vec::new()));
+    let mut mmu = Mmu::new(cartridge_rom(MBC1_RAM_BATTERY, 0x01, 0x02));
+    let sink = saves.clone();
+    mmu.set_autosave_callback(Box::new(move |save| sink.borrow_mut().push(save.to_vec())));
+
+    // Nothing was written yet
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0x0000, 0x00);
+    assert!(saves.borrow().is_empty());
+
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0xA010, 0x99);
+    mmu.write_byte(0x2000, 0x02); // bank switches don't end the save
+    assert!(saves.borrow().is_empty());
+    mmu.write_byte(0x0000, 0x00);
+    assert_eq!(saves.borrow().len(), 1);
+    assert_eq!(saves.borrow()[0][0x10], 0x99);
+    assert!(!mmu.save_dirty());
+}
+
+#[test]
+fn autosaves_follow_the_ram_enable_rule_of_the_mapper() {
+    let saves = Rc::new(RefCell::new(0));
+    let mut mmu = Mmu::new(cartridge_rom(MBC5_RAM_BATTERY, 0x01, 0x02));
+    let sink = saves.clone();
+    mmu.set_autosave_callback(Box::new(move |_| *sink.borrow_mut() += 1));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0xA000, 0x05);
+    // Only 0x0A enables RAM on MBC5
+    mmu.write_byte(0x0000, 0x1A);
+    assert_eq!(*saves.borrow(), 1);
+    assert!(!mmu.save_dirty());
+}
+
+#[test]
+fn mbc2_autosaves_on_ramg_writes_only() {
+    let saves = Rc::new(RefCell::new(0));
+    let mut mmu = Mmu::new(cartridge_rom(MBC2_BATTERY, 0x01, 0x00));
+    let sink = saves.clone();
+    mmu.set_autosave_callback(Box::new(move |_| *sink.borrow_mut() += 1));
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0xA000, 0x05);
+    // Bit 8 set: ROM bank register
+    mmu.write_byte(0x0100, 0x02);
+    assert_eq!(*saves.borrow(), 0);
+    mmu.write_byte(0x0000, 0x00);
+    assert_eq!(*saves.borrow(), 1);
+}
+
+#[test]
+fn mbc3_clock_is_saved_in_the_footer() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC3_TIMER_RAM_BATTERY, 0x01, 0x02));
+    mmu.set_time_source(Box::new(CycleClock::new()));
+    let rtc = mmu.rtc().unwrap();
+    rtc.set_time(RtcTime { hours: 5, minutes: 4, seconds: 3, days: 0x102, ..Default::default() });
+
+    let before = unix_time();
+    let save = mmu.export_save().unwrap();
+    assert_eq!(save.len(), RAM_SIZE + RTC_FOOTER_SIZE);
+    let footer = &save[RAM_SIZE..];
+    assert_eq!(&footer[0..20], &[3, 0, 0, 0, 4, 0, 0, 0, 5, 0, 0, 0, 0x02, 0, 0, 0, 0x01, 0, 0, 0]);
+    let timestamp = u64::from_le_bytes(footer[40..48].try_into().unwrap());
+    assert!(timestamp >= before && timestamp <= unix_time());
+}
+
+#[test]
+fn mbc3_clock_catches_up_with_the_time_elapsed_since_saving() {
+    let live = RtcTime { minutes: 10, ..Default::default() };
+    let latched = RtcTime { seconds: 7, ..Default::default() };
+    for size in [RTC_FOOTER_SIZE, RTC_FOOTER_SIZE_32] {
+        let mut footer = rtc_footer(live, latched, unix_time() - 3600);
+        footer.truncate(size);
+        let mut save = vec![0; RAM_SIZE];
+        save.extend_from_slice(&footer);
+
+        let mut mmu = Mmu::new(cartridge_rom(MBC3_TIMER_RAM_BATTERY, 0x01, 0x02));
+        mmu.set_time_source(Box::new(CycleClock::new()));
+        mmu.load_save(&save);
+        let rtc = mmu.rtc().unwrap();
+        assert_eq!(rtc.latched(), latched);
+        let time = rtc.time();
+        assert_eq!((time.hours, time.minutes), (1, 10));
+        assert!(time.seconds < 10);
+    }
+}
+
+#[test]
+fn mbc3_saves_without_footer_keep_the_clock() {
+    let mut mmu = Mmu::new(cartridge_rom(MBC3_TIMER_RAM_BATTERY, 0x01, 0x02));
+    mmu.set_time_source(Box::new(CycleClock::new()));
+    mmu.rtc().unwrap().advance(Duration::from_secs(42));
+    mmu.load_save(&vec![0x11; RAM_SIZE]);
+    assert_eq!(mmu.rtc().unwrap().time().seconds, 42);
+    mmu.write_byte(0x0000, 0x0A);
+    assert_eq!(mmu.peek_byte(0xA000), 0x11);
+}
+
+#[test]
+fn huc3_clock_is_saved_in_the_footer() {
+    let mut save = vec![0; 4 * RAM_SIZE];
+    save.extend_from_slice(&(unix_time() - 120).to_le_bytes());
+    save.extend_from_slice(&100u16.to_le_bytes());
+    save.extend_from_slice(&5u16.to_le_bytes());
+    save.extend_from_slice(&[0; 5]);
+
+    let mut mmu = Mmu::new(cartridge_rom(HUC3, 0x06, 0x03));
+    mmu.set_time_source(Box::new(CycleClock::new()));
+    mmu.load_save(&save);
+
+    let save = mmu.export_save().unwrap();
+    assert_eq!(save.len(), 4 * RAM_SIZE + HUC3_FOOTER_SIZE);
+    let footer = &save[4 * RAM_SIZE..];
+    assert_eq!(u16::from_le_bytes([footer[8], footer[9]]), 102);
+    assert_eq!(u16::from_le_bytes([footer[10], footer[11]]), 5);
+}
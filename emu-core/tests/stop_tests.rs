@@ -0,0 +1,77 @@
+mod common;
+
+use emu_core::cpu::cpu::{Cpu, CpuState};
+use emu_core::joypad::Button;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::rom_with_program;
+
+const INC_B: u8 = 0x04;
+const STOP: [u8; 2] = [0x10, 0x00];
+
+fn run(cpu: &mut Cpu<Mmu>, ticks: usize) {
+    for _ in 0..ticks {
+        cpu.tick();
+    }
+}
+
+#[test]
+fn stop_waits_for_joypad_line_low() {
+    // LD A,0x20; LDH (P1),A (select the d-pad); STOP; INC B
+    let program = [0x3E, 0x20, 0xE0, 0x00, STOP[0], STOP[1], INC_B];
+    let mut cpu = Cpu::new(Mmu::new(rom_with_program(&program, &[])));
+    run(&mut cpu, 4);
+    assert_eq!(cpu.state(), CpuState::Stopped);
+
+    run(&mut cpu, 1000);
+    assert_eq!(cpu.state(), CpuState::Stopped);
+    assert!(cpu.mmu.lcd_blanked());
+    assert_eq!(cpu.mmu.peek_byte(0xFF04), 0, "the system clock is stopped");
+    assert_eq!(cpu.reg.b, 0);
+
+    // Buttons that are not selected do not pull any line low
+    cpu.mmu.set_button(Button::A, true);
+    run(&mut cpu, 10);
+    assert_eq!(cpu.state(), CpuState::Stopped);
+
+    cpu.mmu.set_button(Button::Down, true);
+    cpu.tick();
+    assert_eq!(cpu.state(), CpuState::Running);
+    assert!(!cpu.mmu.lcd_blanked());
+    assert_eq!(cpu.reg.b, 1);
+}
+
+#[test]
+fn stop_resets_divider() {
+    let mut cpu = Cpu::new(Mmu::new(rom_with_program(&[STOP[0], STOP[1]], &[])));
+    cpu.mmu.tick(0xFF);
+    assert_ne!(cpu.mmu.peek_byte(0xFF04), 0);
+
+    cpu.tick(); // NOP
+    cpu.tick(); // STOP
+    assert_eq!(cpu.mmu.peek_byte(0xFF04), 0);
+}
+
+#[test]
+fn stop_performs_armed_cgb_speed_switch() {
+    // LD A,0x01; LDH (KEY1),A; STOP; INC B
+    let program = [0x3E, 0x01, 0xE0, 0x4D, STOP[0], STOP[1], INC_B];
+    let mut rom = rom_with_program(&program, &[]);
+    rom[0x0143] = 0x80; // CGB enhanced cartridge
+    let mut cpu = Cpu::new(Mmu::new(rom));
+
+    run(&mut cpu, 4);
+    assert_eq!(cpu.state(), CpuState::Running);
+    assert!(cpu.mmu.double_speed());
+    assert_eq!(cpu.mmu.peek_byte(0xFF4D), 0xFE);
+
+    cpu.tick();
+    assert_eq!(cpu.reg.b, 1);
+}
+
+#[test]
+fn key1_is_not_mapped_on_dmg() {
+    let mut cpu = Cpu::new(Mmu::new(rom_with_program(&[], &[])));
+    cpu.mmu.poke_byte(0xFF4D, 0x01);
+    assert_eq!(cpu.mmu.peek_byte(0xFF4D), 0xFF);
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use emu_core::cartridge::rtc::CycleClock;
+use emu_core::cartridge::Mapper;
+use emu_core::error::StateError;
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{bank_at, cartridge_rom};
+
+const TAMA5: u8 = 0xFD;
+
+// M-cycles per second at normal speed
+const M_CYCLES_PER_SECOND: u32 = 1_048_576;
+
+fn tama5_cartridge() -> Mmu {
+    let mut mmu = Mmu::new(cartridge_rom(TAMA5, 0x04, 0x00));
+    mmu.set_time_source(Box::new(CycleClock::new()));
+    mmu
+}
+
+fn write_register(mmu: &mut Mmu, reg: u8, val: u8) {
+    mmu.write_byte(0xA001, reg);
+    mmu.write_byte(0xA000, val);
+}
+
+fn read_register(mmu: &mut Mmu, reg: u8) -> u8 {
+    mmu.write_byte(0xA001, reg);
+    mmu.read_byte(0xA000)
+}
+
+/// Runs a command on an address of the microcontroller, with a byte of data.
+fn command(mmu: &mut Mmu, command: u8, addr: u8, data: u8) -> u8 {
+    write_register(mmu, 0x4, data & 0x0F);
+    write_register(mmu, 0x5, data >> 4);
+    write_register(mmu, 0x6, command << 1 | addr >> 4);
+    write_register(mmu, 0x7, addr & 0x0F);
+    read_register(mmu, 0xC) & 0x0F | (read_register(mmu, 0xD) & 0x0F) << 4
+}
+
+/// Reads a digit of the clock.
+fn rtc_digit(mmu: &mut Mmu, reg: u8) -> u8 {
+    command(mmu, 0x3, reg, 0)
+}
+
+#[test]
+fn rom_banking_through_registers() {
+    let mut mmu = tama5_cartridge();
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::Tama5);
+    write_register(&mut mmu, 0x0, 0x05);
+    write_register(&mut mmu, 0x1, 0x01);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x15);
+    assert_eq!(bank_at(&mmu, 0x0000), 0);
+    assert_eq!(read_register(&mut mmu, 0xA), 0xF1);
+}
+
+#[test]
+fn states_with_invalid_registers_are_rejected() {
+    let mut mmu = tama5_cartridge();
+    write_register(&mut mmu, 0x0, 0x0F);
+    let mut state = mmu.save_cartridge_state();
+    assert_eq!(mmu.load_cartridge_state(&state), Ok(()));
+
+    // The state ends with the selected register, the 16 registers and the last byte read
+    let len = state.len();
+    assert_eq!([state[len - 22], state[len - 17]], [0x0, 0x0F]);
+    state[len - 17] = 0x1F;
+    assert_eq!(mmu.load_cartridge_state(&state), Err(StateError::InvalidValue(0x1F)));
+    state[len - 17] = 0x0F;
+    state[len - 22] = 0x10;
+    assert_eq!(mmu.load_cartridge_state(&state), Err(StateError::InvalidValue(0x10)));
+}
+
+#[test]
+fn ram_is_saved() {
+    let mut mmu = tama5_cartridge();
+    command(&mut mmu, 0x0, 0x13, 0xA5);
+    assert_eq!(command(&mut mmu, 0x1, 0x13, 0), 0xA5);
+    assert_eq!(mmu.battery_ram().unwrap()[0x13], 0xA5);
+
+    let mut save = [0; 32];
+    save[0x1F] = 0x5A;
+    mmu.load_battery_ram(&save);
+    assert_eq!(command(&mut mmu, 0x1, 0x1F, 0), 0x5A);
+}
+
+#[test]
+fn clock_counts_the_date() {
+    let mut mmu = tama5_cartridge();
+    // 23:59:59 on Wednesday, 28 February 2024 (0: Sunday)
+    for (reg, digit) in [(0x0, 9), (0x1, 5), (0x2, 9), (0x3, 5), (0x4, 3), (0x5, 2), (0x6, 3)] {
+        command(&mut mmu, 0x2, reg, digit);
+    }
+    for (reg, digit) in [(0x7, 8), (0x8, 2), (0x9, 2), (0xA, 0), (0xB, 4), (0xC, 2)] {
+        command(&mut mmu, 0x2, reg, digit);
+    }
+
+    for _ in 0..M_CYCLES_PER_SECOND / 128 {
+        mmu.tick(128);
+    }
+    let digits: Vec<u8> = (0x0..=0xC).map(|reg| rtc_digit(&mut mmu, reg)).collect();
+    // 00:00:00 on Thursday, 29 February 2024
+    assert_eq!(digits, [0, 0, 0, 0, 0, 0, 4, 9, 2, 2, 0, 4, 2]);
+}
+
+#[test]
+fn clock_is_saved_in_the_footer() {
+    let mut mmu = tama5_cartridge();
+    command(&mut mmu, 0x0, 0x00, 0x42);
+    // 12:34:56 on Friday, 31 December 1999
+    for (reg, digit) in [(0x0, 6), (0x1, 5), (0x2, 4), (0x3, 3), (0x4, 2), (0x5, 1), (0x6, 5)] {
+        command(&mut mmu, 0x2, reg, digit);
+    }
+    for (reg, digit) in [(0x7, 1), (0x8, 3), (0x9, 2), (0xA, 1), (0xB, 9), (0xC, 9)] {
+        command(&mut mmu, 0x2, reg, digit);
+    }
+    let save = mmu.export_save().unwrap();
+    assert_eq!(save.len(), 32 + 15);
+    assert_eq!(save[0], 0x42);
+    assert_eq!(save[40..], [56, 34, 12, 5, 31, 12, 99]);
+
+    let mut other = tama5_cartridge();
+    other.load_save(&save);
+    let digits: Vec<u8> = (0x0..=0xC).map(|reg| rtc_digit(&mut other, reg)).collect();
+    assert_eq!(digits, [6, 5, 4, 3, 2, 1, 5, 1, 3, 2, 1, 9, 9]);
+    assert_eq!(command(&mut other, 0x1, 0x00, 0), 0x42);
+
+    // The clock catches up with the time elapsed since saving, here an hour
+    let mut footer = save[32..].to_vec();
+    let timestamp = u64::from_le_bytes(footer[0..8].try_into().unwrap()) - 3600;
+    footer[0..8].copy_from_slice(&timestamp.to_le_bytes());
+    other.load_save(&[&save[..32], &footer].concat());
+    assert_eq!([rtc_digit(&mut other, 0x4), rtc_digit(&mut other, 0x5)], [3, 1]);
+
+    // Footers with counters out of range are ignored
+    footer[13] = 13;
+    other.load_save(&[&save[..32], &footer].concat());
+    assert_eq!([rtc_digit(&mut other, 0x4), rtc_digit(&mut other, 0x5)], [3, 1]);
+}
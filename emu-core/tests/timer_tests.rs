@@ -0,0 +1,49 @@
+use emu_core::timer::Timer;
+
+#[test]
+fn div_increments_every_64_m_cycles_and_resets_on_write() {
+    let mut timer = Timer::new();
+    timer.tick(63);
+    assert_eq!(timer.read_byte(0xFF04), 0);
+    timer.tick(1);
+    assert_eq!(timer.read_byte(0xFF04), 1);
+
+    timer.write_byte(0xFF04, 0x42);
+    assert_eq!(timer.read_byte(0xFF04), 0);
+}
+
+#[test]
+fn tima_overflow_reloads_tma_one_cycle_later() {
+    let mut timer = Timer::new();
+    timer.write_byte(0xFF06, 0xAB);
+    timer.write_byte(0xFF05, 0xFF);
+    timer.write_byte(0xFF07, 0x05); // enabled, increments every 4 M-cycles
+
+    assert!(!timer.tick(4));
+    assert_eq!(timer.read_byte(0xFF05), 0x00, "TIMA reads 0 during the reload delay");
+    assert!(timer.tick(1));
+    assert_eq!(timer.read_byte(0xFF05), 0xAB);
+}
+
+#[test]
+fn next_event_matches_interrupt_timing() {
+    for tac in 0x04..=0x07 {
+        let mut timer = Timer::new();
+        timer.tick(37);
+        timer.write_byte(0xFF05, 0xF0);
+        timer.write_byte(0xFF07, tac);
+
+        let cycles = timer.next_event().expect("an enabled timer schedules an interrupt");
+        for _ in 1..cycles {
+            assert!(!timer.tick(1), "interrupt requested too early with TAC={:02X}", tac);
+        }
+        assert!(timer.tick(1), "interrupt not requested with TAC={:02X}", tac);
+    }
+}
+
+#[test]
+fn disabled_timer_has_no_event() {
+    let timer = Timer::new();
+    assert_eq!(timer.next_event(), None);
+    assert_eq!(timer.read_byte(0xFF07), 0xF8);
+}
@@ -0,0 +1,155 @@
+mod common;
+
+use emu_core::cpu::cpu::Cpu;
+use emu_core::cpu::opcodes::{Condition, FlagEffect, Mnemonic, OpcodeInfo, Operand, CB_OPCODES, OPCODES};
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{MemoryCycle, MockMemory};
+
+// M-cycles taken by each opcode (including its fetch), with all flags cleared:
+// conditional branches on NZ/NC are taken, and the ones on Z/C are not.
+// 0 marks the opcodes that are not timed here (STOP, HALT, CB prefix, illegal opcodes).
+#[rustfmt::skip]
+const OPCODE_CYCLES: [u8; 256] = [
+//  0  1  2  3  4  5  6  7  8  9  A  B  C  D  E  F
+    1, 3, 2, 2, 1, 1, 2, 1, 5, 2, 2, 2, 1, 1, 2, 1, // 0x
+    0, 3, 2, 2, 1, 1, 2, 1, 3, 2, 2, 2, 1, 1, 2, 1, // 1x
+    3, 3, 2, 2, 1, 1, 2, 1, 2, 2, 2, 2, 1, 1, 2, 1, // 2x
+    3, 3, 2, 2, 3, 3, 3, 1, 2, 2, 2, 2, 1, 1, 2, 1, // 3x
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 4x
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 5x
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 6x
+    2, 2, 2, 2, 2, 2, 0, 2, 1, 1, 1, 1, 1, 1, 2, 1, // 7x
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 8x
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // 9x
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // Ax
+    1, 1, 1, 1, 1, 1, 2, 1, 1, 1, 1, 1, 1, 1, 2, 1, // Bx
+    5, 3, 4, 4, 6, 4, 2, 4, 2, 4, 3, 0, 3, 6, 2, 4, // Cx
+    5, 3, 4, 0, 6, 4, 2, 4, 2, 4, 3, 0, 3, 0, 2, 4, // Dx
+    3, 3, 2, 0, 0, 4, 2, 4, 4, 1, 4, 0, 0, 0, 2, 4, // Ex
+    3, 3, 2, 1, 0, 4, 2, 4, 3, 2, 4, 1, 0, 0, 2, 4, // Fx
+];
+
+/// Runs a single instruction from WRAM with the given flags, and returns the CPU after it.
+fn run_with_flags(opcode: u8, operand: u8, flags: u8) -> Cpu<MockMemory> {
+    let mut mmu = MockMemory::default();
+    for addr in 0xC000..=0xC003 {
+        mmu.poke_byte(addr, operand);
+    }
+    mmu.poke_byte(0xC000, opcode);
+
+    let mut cpu = Cpu::new(mmu);
+    cpu.reg.f = flags;
+    cpu.reg.pc = 0xC001;
+    cpu.prefetched = opcode;
+    // Any access outside of the instruction reads open bus, which is fine for timing purposes
+    let _ = cpu.try_step();
+    cpu
+}
+
+/// Runs a single instruction from WRAM, and returns the bus cycles it took.
+fn run_instruction(opcode: u8, operand: u8) -> Vec<MemoryCycle> {
+    run_with_flags(opcode, operand, 0).mmu.get_cycles()
+}
+
+/// Returns the M-cycles an instruction should take with the given flags, according to the opcode table.
+fn expected_cycles(info: &OpcodeInfo, flags: u8) -> u8 {
+    let taken = match info.operands[0] {
+        Some(Operand::Cond(Condition::NZ)) => flags & 0x80 == 0,
+        Some(Operand::Cond(Condition::Z)) => flags & 0x80 != 0,
+        Some(Operand::Cond(Condition::NC)) => flags & 0x10 == 0,
+        Some(Operand::Cond(Condition::C)) => flags & 0x10 != 0,
+        _ => true,
+    };
+    if taken { info.cycles_taken } else { info.cycles }
+}
+
+/// Checks the flags set by an instruction against the effects listed in the opcode table.
+fn assert_flag_effects(info: &OpcodeInfo, before: u8, after: u8, name: &str) {
+    let effects = [(info.flags.z, 0x80), (info.flags.n, 0x40), (info.flags.h, 0x20), (info.flags.c, 0x10)];
+    for (effect, mask) in effects {
+        match effect {
+            FlagEffect::Unaffected => assert_eq!(after & mask, before & mask, "{}: flag 0x{:02X}", name, mask),
+            FlagEffect::Reset => assert_eq!(after & mask, 0, "{}: flag 0x{:02X}", name, mask),
+            FlagEffect::Set => assert_eq!(after & mask, mask, "{}: flag 0x{:02X}", name, mask),
+            FlagEffect::Affected => {},
+        }
+    }
+}
+
+#[test]
+fn instructions_take_one_m_cycle_per_bus_access() {
+    for (opcode, &expected) in OPCODE_CYCLES.iter().enumerate() {
+        if expected == 0 {
+            continue;
+        }
+        let cycles = run_instruction(opcode as u8, 0xC0);
+        assert_eq!(cycles.len(), expected as usize, "opcode 0x{:02X}: {:?}", opcode, cycles);
+    }
+}
+
+#[test]
+fn cb_instructions_take_one_m_cycle_per_bus_access() {
+    for opcode in 0..=0xFF_u8 {
+        let expected = match (opcode >> 6, opcode & 0x07) {
+            (_, r) if r != 6 => 2,
+            (1, _) => 3, // BIT b, (HL)
+            _ => 4,      // read-modify-write (HL)
+        };
+        let cycles = run_instruction(0xCB, opcode);
+        assert_eq!(cycles.len(), expected, "opcode 0xCB{:02X}: {:?}", opcode, cycles);
+    }
+}
+
+#[test]
+fn opcode_table_matches_measured_cycles_and_lengths() {
+    for (opcode, info) in OPCODES.iter().enumerate() {
+        if matches!(info.mnemonic, Mnemonic::Halt | Mnemonic::Stop | Mnemonic::Prefix | Mnemonic::Illegal) {
+            continue;
+        }
+        for flags in [0x00, 0xF0] {
+            let cpu = run_with_flags(opcode as u8, 0xC0, flags);
+            let cycles = cpu.mmu.get_cycles();
+            assert_eq!(cycles.len(), expected_cycles(info, flags) as usize, "opcode 0x{:02X}: {:?}", opcode, cycles);
+            assert_eq!(cpu.m_cycles(), cycles.len() as u64, "opcode 0x{:02X}: cycle counter", opcode);
+            assert_flag_effects(info, flags, cpu.reg.f, &format!("opcode 0x{:02X}", opcode));
+
+            let branches = matches!(info.mnemonic,
+                Mnemonic::Jr | Mnemonic::Jp | Mnemonic::Call | Mnemonic::Ret | Mnemonic::Reti | Mnemonic::Rst);
+            if !branches {
+                // PC points after the prefetched opcode of the next instruction
+                assert_eq!(cpu.reg.pc, 0xC001 + info.length as u16, "opcode 0x{:02X}", opcode);
+            }
+        }
+    }
+}
+
+#[test]
+fn cb_opcode_table_matches_measured_cycles_and_lengths() {
+    for (opcode, info) in CB_OPCODES.iter().enumerate() {
+        for flags in [0x00, 0xF0] {
+            let cpu = run_with_flags(0xCB, opcode as u8, flags);
+            assert_eq!(cpu.mmu.get_cycles().len(), info.cycles as usize, "opcode 0xCB{:02X}", opcode);
+            assert_eq!(cpu.m_cycles(), info.cycles as u64, "opcode 0xCB{:02X}: cycle counter", opcode);
+            assert_eq!(cpu.reg.pc, 0xC001 + info.length as u16, "opcode 0xCB{:02X}", opcode);
+            assert_flag_effects(info, flags, cpu.reg.f, &format!("opcode 0xCB{:02X}", opcode));
+        }
+    }
+}
+
+#[test]
+fn every_mmu_access_advances_the_machine() {
+    let mut mmu = Mmu::new(vec![0; 0x8000]);
+    for addr in 0..63 {
+        mmu.read_byte(addr);
+    }
+    assert_eq!(mmu.peek_byte(0xFF04), 0);
+
+    // DIV increments every 64 M-cycles, and this access sees it
+    assert_eq!(mmu.read_byte(0xFF04), 1);
+
+    for _ in 0..64 {
+        mmu.write_byte(0xC000, 0x00);
+    }
+    assert_eq!(mmu.peek_byte(0xFF04), 2);
+}
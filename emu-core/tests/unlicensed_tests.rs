@@ -0,0 +1,138 @@
+mod common;
+
+use std::cell::Cell;
+use std::rc::Rc;
+
+use emu_core::cartridge::header::NINTENDO_LOGO;
+use emu_core::cartridge::{detect_type, Mapper};
+use emu_core::memory::{MemoryBus, Mmu};
+
+use crate::common::{bank_at, cartridge_rom, fix_header_checksum};
+
+const ROM_ONLY: u8 = 0x00;
+const MBC3_TIMER_RAM_BATTERY: u8 = 0x10;
+const MBC5_RAM_BATTERY: u8 = 0x1B;
+
+/// Replaces the logo at 0x0104 with the publisher's own, as unlicensed cartridges do.
+fn replace_logo(rom: &mut [u8]) {
+    rom[0x0104..0x0134].fill(0x55);
+}
+
+/// Creates a Sachen ROM: its own logo at 0x0104, and the Nintendo logo where the boot ROM reads it.
+/// The second one has the address lines A0/A6 and A1/A4 swapped.
+fn sachen_rom(scrambled: bool, cgb: bool) -> Vec<u8> {
+    let mut rom = cartridge_rom(ROM_ONLY, 0x04, 0x00);
+    replace_logo(&mut rom);
+    for (i, &byte) in NINTENDO_LOGO.iter().enumerate() {
+        let mut addr = 0x0184 + i;
+        if scrambled {
+            let bit = |n: usize| addr >> n & 1;
+            addr = (addr & 0xFFAC) | bit(6) | bit(4) << 1 | bit(1) << 4 | bit(0) << 6;
+        }
+        rom[addr] = byte;
+    }
+    if cgb {
+        rom[0x0143] = 0x80;
+    }
+    fix_header_checksum(&mut rom);
+    rom
+}
+
+#[test]
+fn wisdom_tree_switches_32kb_by_address() {
+    let mut rom = cartridge_rom(ROM_ONLY, 0x04, 0x00);
+    rom[0x0148] = 0x00;
+    rom[0x1234..0x1234 + 11].copy_from_slice(b"WISDOM TREE");
+    fix_header_checksum(&mut rom);
+    let mut mmu = Mmu::new(rom);
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::WisdomTree);
+
+    mmu.write_byte(0x0003, 0xFF);
+    assert_eq!(bank_at(&mmu, 0x0000), 6);
+    assert_eq!(bank_at(&mmu, 0x4000), 7);
+    // The value written doesn't matter, only the address
+    mmu.write_byte(0x2101, 0x00);
+    assert_eq!(bank_at(&mmu, 0x0000), 2);
+    assert_eq!(bank_at(&mmu, 0x4000), 3);
+}
+
+#[test]
+fn rom_only_headers_without_the_publisher_are_not_wisdom_tree() {
+    let rom = cartridge_rom(ROM_ONLY, 0x04, 0x00);
+    assert_eq!(detect_type(&rom).mapper, Mapper::Mbc1);
+}
+
+#[test]
+fn m161_locks_after_the_first_bank_write() {
+    let mut rom = cartridge_rom(MBC3_TIMER_RAM_BATTERY, 0x03, 0x00);
+    rom[0x0134..0x013E].copy_from_slice(b"TETRIS SET");
+    fix_header_checksum(&mut rom);
+    let mut mmu = Mmu::new(rom);
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::M161);
+    assert!(!mmu.cartridge_type().timer);
+
+    assert_eq!(bank_at(&mmu, 0x4000), 1);
+    mmu.write_byte(0x4000, 0x03);
+    assert_eq!(bank_at(&mmu, 0x0000), 6);
+    assert_eq!(bank_at(&mmu, 0x4000), 7);
+    mmu.write_byte(0x4000, 0x01);
+    assert_eq!(bank_at(&mmu, 0x0000), 6);
+}
+
+#[test]
+fn sachen_logo_is_found_scrambled_or_not() {
+    assert_eq!(detect_type(&sachen_rom(false, false)).mapper, Mapper::SachenMmc1);
+    assert_eq!(detect_type(&sachen_rom(true, false)).mapper, Mapper::SachenMmc1);
+    assert_eq!(detect_type(&sachen_rom(true, true)).mapper, Mapper::SachenMmc2);
+}
+
+#[test]
+fn sachen_base_bank_and_mask() {
+    let mut mmu = Mmu::new(sachen_rom(false, false));
+    assert_eq!(bank_at(&mmu, 0x0000), 0);
+    assert_eq!(bank_at(&mmu, 0x4000), 1);
+    mmu.write_byte(0x2000, 0x00);
+    assert_eq!(bank_at(&mmu, 0x4000), 1);
+
+    // A multicart menu maps a 4 banks game at bank 0x18, which locks the base bank and mask
+    mmu.write_byte(0x4000, 0x3C);
+    mmu.write_byte(0x0000, 0x18);
+    assert_eq!(bank_at(&mmu, 0x0000), 0x18);
+    mmu.write_byte(0x2000, 0x02);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x1A);
+    mmu.write_byte(0x2000, 0x07);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x1B);
+
+    mmu.write_byte(0x0000, 0x00);
+    mmu.write_byte(0x4000, 0x00);
+    assert_eq!(bank_at(&mmu, 0x0000), 0x18);
+    assert_eq!(bank_at(&mmu, 0x4000), 0x1B);
+}
+
+#[test]
+fn li_cheng_ignores_some_bank_writes() {
+    let mut rom = cartridge_rom(MBC5_RAM_BATTERY, 0x04, 0x03);
+    replace_logo(&mut rom);
+    let mut mmu = Mmu::new(rom);
+    assert_eq!(mmu.cartridge_type().mapper, Mapper::LiCheng);
+    assert!(mmu.cartridge_type().battery);
+
+    mmu.write_byte(0x2000, 0x05);
+    assert_eq!(bank_at(&mmu, 0x4000), 5);
+    mmu.write_byte(0x2101, 0x00);
+    mmu.write_byte(0x2FFF, 0x00);
+    assert_eq!(bank_at(&mmu, 0x4000), 5);
+    mmu.write_byte(0x2100, 0x09);
+    assert_eq!(bank_at(&mmu, 0x4000), 9);
+
+    mmu.write_byte(0x0000, 0x0A);
+    mmu.write_byte(0xA000, 0x42);
+    assert_eq!(mmu.battery_ram().unwrap()[0], 0x42);
+
+    // Disabling RAM ends the save, like on MBC5
+    let saves = Rc::new(Cell::new(0));
+    let sink = saves.clone();
+    mmu.set_autosave_callback(Box::new(move |_| sink.set(sink.get() + 1)));
+    mmu.write_byte(0x0000, 0x00);
+    assert_eq!(saves.get(), 1);
+}